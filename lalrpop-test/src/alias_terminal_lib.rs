@@ -0,0 +1,9 @@
+/// A token type with two spellings of the same operator, and two
+/// kinds of number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    AmpAmp,
+    And,
+    Num(i32),
+    Int(i32),
+}
@@ -0,0 +1,9 @@
+/// A token type whose payloads are bound by the terminal patterns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    Let,
+    Eq,
+    Plus,
+    Num(i64),
+    Ident { name: String, raw: bool },
+}
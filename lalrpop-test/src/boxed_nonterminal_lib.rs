@@ -0,0 +1,15 @@
+/// A value with a lot of dead weight, standing in for a large syntax
+/// tree node.
+pub struct Big {
+    pub value: i32,
+    _padding: [u8; 256],
+}
+
+impl Big {
+    pub fn new(value: i32) -> Self {
+        Big {
+            value,
+            _padding: [0; 256],
+        }
+    }
+}
@@ -0,0 +1,7 @@
+/// A token type whose tokenizer marks the end of input explicitly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    Num(i32),
+    Plus,
+    Eof,
+}
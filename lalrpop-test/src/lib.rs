@@ -7,8 +7,9 @@ use std::path::Path;
 use std::process::Command;
 
 use lalrpop_util::lalrpop_mod;
+use lalrpop_util::state_machine::Alternative;
 
-use lalrpop_util::{ErrorRecovery, ParseError};
+use lalrpop_util::{ErrorRecovery, LimitExceeded, ParseError};
 
 use crate::util::tok::Tok;
 
@@ -41,9 +42,24 @@ lalrpop_mod_test!(expr);
 /// more interesting demonstration of parsing full expressions, using LALR not LR
 lalrpop_mod_test!(expr_lalr);
 
+/// more interesting demonstration of parsing full expressions, using SLR not LR
+lalrpop_mod_test!(expr_slr);
+
 /// more interesting demonstration of parsing full expressions, using intern tok
 lalrpop_mod_test!(expr_intern_tok);
 
+/// the same expressions, with the action table compacted
+lalrpop_mod_test!(expr_compact);
+
+/// the same expressions, parsed from a slice of tokens
+lalrpop_mod_test!(expr_slice);
+
+/// test the `FIRST_*` constants emitted by `#[first_sets]`
+lalrpop_mod_test!(first_sets);
+
+/// test dispatching the actions through the `Actions` trait
+lalrpop_mod_test!(action_trait);
+
 /// tests #![attributes] for generated module
 lalrpop_mod_test!(
     #[allow(dead_code, unknown_lints)]
@@ -82,6 +98,9 @@ mod lifetime_tok_lib;
 /// test that exercises locations and spans
 lalrpop_mod_test!(loc);
 
+/// test that `@L`/`@R` give spans of individual symbols
+lalrpop_mod_test!(loc_per_symbol);
+
 /// regression test for location issue #90
 lalrpop_mod_test!(loc_issue_90);
 mod loc_issue_90_lib;
@@ -187,6 +206,131 @@ mod user_defined_error_visibility;
 
 lalrpop_mod_test!(zero_length_match);
 
+/// test that tokens can be pushed into the parser one at a time
+lalrpop_mod_test!(push_parser);
+
+/// test a designated terminal that marks the end of the input
+lalrpop_mod_test!(eof_terminal);
+mod eof_terminal_lib;
+
+/// test terminals that the parser drops from the token stream
+lalrpop_mod_test!(skip_terminal);
+mod skip_terminal_lib;
+
+/// test terminals that match more than one token
+lalrpop_mod_test!(alias_terminal);
+mod alias_terminal_lib;
+
+/// test that shifts and reductions can be reported as events
+lalrpop_mod_test!(event_parser);
+
+/// test that the stack contents are returned alongside parse errors
+lalrpop_mod_test!(partial_results);
+
+/// test that deeply nested input is rejected by a recursion limit
+lalrpop_mod_test!(recursion_limit);
+
+/// test a recursive ascent parser split into several modules
+lalrpop_mod_test!(state_modules);
+
+/// test a parser that only recognizes its input
+lalrpop_mod_test!(recognizer);
+
+/// test that errors name only the expected tokens that would help
+lalrpop_mod_test!(narrow_expected);
+
+/// test a parser that looks further ahead at a conflict
+lalrpop_mod_test!(backtrack);
+lalrpop_mod_test!(backtrack_hidden_left_recursion);
+
+/// test that parsers of different grammars can share a trait object type
+lalrpop_mod_test!(parser_trait_sum);
+lalrpop_mod_test!(parser_trait_product);
+
+/// test consecutive empty reductions in a recursive ascent parser
+lalrpop_mod_test!(nullable_chain);
+
+/// test that actions can mutate a context passed to the parser
+lalrpop_mod_test!(context_param);
+
+/// test that chains of a nonassociative operator are rejected
+lalrpop_mod_test!(nonassoc);
+
+/// test parsing a prefix of the input and resuming after it
+lalrpop_mod_test!(parse_prefix);
+
+/// test parsing one value after another from the same input
+lalrpop_mod_test!(parse_items);
+
+/// test a grammar with an expected conflict
+lalrpop_mod_test!(dangling_else);
+
+/// test resolving an expected conflict in favor of reducing
+lalrpop_mod_test!(dangling_else_reduce);
+
+/// test naming the tokens of a custom token type
+lalrpop_mod_test!(token_names);
+
+/// test terminal patterns that bind the payload of a token
+lalrpop_mod_test!(bound_terminals);
+mod bound_terminals_lib;
+
+/// test counting how often each production is reduced
+lalrpop_mod_test!(count_reductions);
+
+/// test nested `*`, `+` and `?` operators
+lalrpop_mod_test!(nested_repeat);
+
+/// test fallible actions in a recursive ascent parser
+lalrpop_mod_test!(fallible_ascent);
+
+/// test `parse_str`, which tokenizes a string before parsing it
+lalrpop_mod_test!(parse_str);
+
+/// test `#[trace]`, which prints each shift and reduction
+lalrpop_mod_test!(trace);
+
+/// test a prefix operator that shares its token with an infix one
+lalrpop_mod_test!(unary_minus);
+
+/// test reparsing an edited input
+lalrpop_mod_test!(incremental);
+
+/// test that tokens can be classified by a function before matching
+lalrpop_mod_test!(classify);
+
+/// test start symbols that derive only the empty string or one token
+lalrpop_mod_test!(degenerate);
+
+/// test the generated lexer of a grammar without terminals
+lalrpop_mod_test!(empty_lexer);
+
+/// test that table-driven parsers give up after too many reductions
+lalrpop_mod_test!(reduction_limit);
+
+/// test that table-driven parsers can reserve room on their stacks
+lalrpop_mod_test!(stack_capacity);
+
+/// test boxing a nonterminal in a recursive ascent parser
+lalrpop_mod_test!(boxed_nonterminal);
+mod boxed_nonterminal_lib;
+
+/// test the free `parse` function of a `#[parse_fn]` nonterminal
+lalrpop_mod_test!(parse_fn);
+
+/// test that a function can choose among the actions of an ambiguous grammar
+lalrpop_mod_test!(disambiguate);
+
+/// test that an index out of range from the `disambiguate` function is
+/// a parse error
+lalrpop_mod_test!(disambiguate_reject);
+
+/// test a grammar that parses bytes without a tokenizer
+lalrpop_mod_test!(bytes);
+
+/// test a grammar that parses chars without a tokenizer
+lalrpop_mod_test!(chars);
+
 pub fn use_cfg_created_parser() {
     #[cfg(feature = "test-set")]
     cfg::CreatedParser::new();
@@ -197,6 +341,24 @@ pub fn use_cfg_created_parser() {
 /// refer to it in order to test `super::` handling in action code.
 const ZERO: i32 = 0;
 
+/// Resolves the conflicts of the `disambiguate` grammar as if `*` bound
+/// more tightly than `-`, and both were left-associative.
+fn choose_by_precedence(alternatives: &[Alternative], lookahead: Option<&Tok>) -> usize {
+    let reduce = alternatives
+        .iter()
+        .position(|alternative| matches!(alternative, Alternative::Reduce(_)))
+        .unwrap();
+    match (alternatives[reduce], lookahead) {
+        (Alternative::Reduce(r#"Expr = Expr "-" Expr"#), Some(Tok::Times)) => 0,
+        _ => reduce,
+    }
+}
+
+/// Chooses none of the alternatives of the `disambiguate_reject` grammar.
+fn reject_conflicts(alternatives: &[Alternative], _: Option<&Tok>) -> usize {
+    alternatives.len()
+}
+
 #[test]
 fn expr_test1() {
     util::test(|v| expr::ExprParser::new().parse(1, v), "22 - 3", 22 - 3);
@@ -286,6 +448,18 @@ fn parse_error_map_token_and_location() {
     assert!(message.contains("at line 2"));
 }
 
+#[test]
+fn parse_error_line_col() {
+    let expr = "(1+\n(2++3))";
+    let err = expr_intern_tok::ExprParser::new()
+        .parse(1, expr)
+        .unwrap_err();
+    let tracker = lalrpop_util::line_col::LineColTracker::new(expr);
+    let line_col = err.line_col(&tracker).unwrap();
+    assert_eq!((line_col.line, line_col.column), (2, 4));
+    assert_eq!(line_col.to_string(), "2:4");
+}
+
 #[test]
 fn parse_error_map_err() {
     let input = "---+";
@@ -529,6 +703,23 @@ fn loc_empty() {
     util::test_loc(|v| loc::ItemsParser::new().parse(v), "", vec![(0, 0)]);
 }
 
+#[test]
+fn loc_per_symbol() {
+    util::test_loc(
+        |v| loc_per_symbol::PairParser::new().parse(v),
+        "1,-2",
+        vec![0, 1, 4, 5], // tok.rs spans are measured in 2*chars
+    );
+
+    // with an empty `Sign`, both its `@L` and its `@R` resolve to
+    // the start of the lookahead
+    util::test_loc(
+        |v| loc_per_symbol::PairParser::new().parse(v),
+        "1,2",
+        vec![0, 1, 4, 4],
+    );
+}
+
 #[test]
 fn use_super_test1() {
     util::test(|v| use_super::SParser::new().parse(v), "()", 0);
@@ -1113,6 +1304,34 @@ fn verify_lalrpop_generates_itself() {
     );
 }
 
+#[test]
+fn recursive_ascent_output_is_reproducible() {
+    let grammar_file = Path::new("src/sub_ascent.lalrpop");
+    let generate = |out_dir: &str| {
+        assert!(Command::new("../target/debug/lalrpop")
+            .args([
+                "--force",
+                "--no-whitespace",
+                "--comments",
+                "--out-dir",
+                out_dir,
+                grammar_file.to_str().expect("grammar path is not UTF-8"),
+            ])
+            .status()
+            .expect("lalrpop run failed")
+            .success());
+        fs::read_to_string(Path::new(out_dir).join(grammar_file.with_extension("rs"))).unwrap()
+    };
+
+    let first = generate("../target/reproducible_a");
+    let second = generate("../target/reproducible_b");
+    util::compare_str(
+        &first,
+        &second,
+        "Generating the same grammar twice produced different output",
+    );
+}
+
 #[test]
 fn comments() {
     assert_eq!(
@@ -1190,3 +1409,1033 @@ fn test_zero_length_match() {
     let res = zero_length_match::AParser::new().parse("B");
     assert!(matches!(res, Err(ParseError::InvalidToken { location: _ })));
 }
+
+#[test]
+fn push_parser_feed_matches_parse() {
+    use lalrpop_util::state_machine::Step;
+
+    let input = "22 * (3 - 1) - 4";
+    let batch = push_parser::ExprParser::new()
+        .parse(2, util::tok::tokenize(input))
+        .unwrap();
+
+    let mut parser = push_parser::ExprParser::new().push_parser(2);
+    for token in util::tok::tokenize(input) {
+        assert!(matches!(parser.feed(Some(token)), Ok(Step::More)));
+    }
+    match parser.feed(None) {
+        Ok(Step::Done(result)) => assert_eq!(result, batch),
+        _ => panic!("expected the push parser to accept the input"),
+    }
+}
+
+#[test]
+fn push_parser_feed_error() {
+    let mut parser = push_parser::ExprParser::new().push_parser(1);
+    let mut tokens = util::tok::tokenize("1 - - 2").into_iter();
+    assert!(parser.feed(tokens.next()).is_ok());
+    assert!(parser.feed(tokens.next()).is_ok());
+    match parser.feed(tokens.next()) {
+        Err(ParseError::UnrecognizedToken { token, .. }) => assert_eq!(token, (4, Tok::Minus, 5)),
+        _ => panic!("expected an unrecognized token error"),
+    }
+}
+
+#[test]
+fn push_parser_needs_more_input() {
+    let parser = push_parser::ExprParser::new();
+    let incomplete = parser.parse(1, util::tok::tokenize("(1 -"));
+    assert!(incomplete.unwrap_err().needs_more_input());
+    let unbalanced = parser.parse(1, util::tok::tokenize("(1 - 2))"));
+    assert!(!unbalanced.unwrap_err().needs_more_input());
+    assert_eq!(parser.parse(1, util::tok::tokenize("(1 - 2)")), Ok(-1));
+}
+
+#[test]
+fn push_parser_parse_stream() {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+
+    let input = "22 * (3 - 1) - 4";
+    let batch = push_parser::ExprParser::new()
+        .parse(2, util::tok::tokenize(input))
+        .unwrap();
+
+    // every other poll finds no token ready yet
+    let mut tokens = util::tok::tokenize(input).into_iter();
+    let mut ready = false;
+    let source = |cx: &mut Context<'_>| {
+        ready = !ready;
+        if ready {
+            Poll::Ready(tokens.next().map(Ok))
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    };
+    let mut future = pin!(push_parser::ExprParser::new()
+        .push_parser(2)
+        .parse_stream(source));
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    let mut pending = 0;
+    let result = loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(result) => break result,
+            Poll::Pending => pending += 1,
+        }
+    };
+    assert_eq!(result, Ok(batch));
+    assert_eq!(pending, util::tok::tokenize(input).len());
+}
+
+#[test]
+fn eof_terminal() {
+    use crate::eof_terminal_lib::Tok;
+
+    let parser = eof_terminal::SumParser::new();
+    let tokens = vec![Tok::Num(1), Tok::Plus, Tok::Num(2), Tok::Eof];
+    assert_eq!(parser.parse(tokens), Ok(3));
+
+    // anything after the end-of-input token is never looked at
+    let tokens = vec![Tok::Num(1), Tok::Eof, Tok::Plus];
+    assert_eq!(parser.parse(tokens), Ok(1));
+
+    // and running dry still works as before
+    assert_eq!(parser.parse(vec![Tok::Num(4)]), Ok(4));
+
+    // but hitting it early is an unexpected end of input
+    let tokens = vec![Tok::Num(1), Tok::Plus, Tok::Eof];
+    assert!(matches!(
+        parser.parse(tokens),
+        Err(ParseError::UnrecognizedEof { .. })
+    ));
+}
+
+#[test]
+fn skip_terminal() {
+    use crate::skip_terminal_lib::Tok;
+
+    let parser = skip_terminal::SumParser::new();
+    let tokens = vec![
+        Tok::Space,
+        Tok::Num(1),
+        Tok::Space,
+        Tok::Comment("// one"),
+        Tok::Plus,
+        Tok::Space,
+        Tok::Num(2),
+        Tok::Comment("// two"),
+    ];
+    assert_eq!(parser.parse(tokens), Ok(3));
+
+    // nothing but skipped tokens is an empty input
+    let tokens = vec![Tok::Space, Tok::Comment("// nothing")];
+    assert!(matches!(
+        parser.parse(tokens),
+        Err(ParseError::UnrecognizedEof { .. })
+    ));
+}
+
+#[test]
+fn alias_terminal() {
+    use crate::alias_terminal_lib::Tok;
+
+    let parser = alias_terminal::ConjParser::new();
+    let tokens = vec![Tok::Num(1), Tok::AmpAmp, Tok::Int(2), Tok::And, Tok::Num(3)];
+    assert_eq!(parser.parse(tokens), Ok(vec![1, 2, 3]));
+
+    let tokens = vec![Tok::Int(1), Tok::And, Tok::And];
+    assert!(matches!(
+        parser.parse(tokens),
+        Err(ParseError::UnrecognizedToken {
+            token: ((), Tok::And, ()),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn event_parser_builds_lossless_tree() {
+    use lalrpop_util::cst::{self, Element};
+
+    // `tokenize` does not report byte offsets, so lex the single
+    // character tokens here
+    let source = " 1 -2  - 3 ";
+    let tokens = source
+        .char_indices()
+        .filter(|&(_, c)| !c.is_whitespace())
+        .map(|(i, c)| match c {
+            '-' => (i, Tok::Minus, i + 1),
+            _ => (i, Tok::Num(c.to_digit(10).unwrap() as i32), i + 1),
+        });
+    let tree = cst::build(
+        event_parser::ExprParser::new().event_parser(),
+        source,
+        tokens,
+    )
+    .unwrap();
+    assert_eq!(tree.text(), source);
+    assert_eq!(tree.trailing_trivia, " ");
+
+    // `Expr = Expr "-" Term` at the root, with the last `-` preceded by
+    // two spaces
+    assert_eq!(tree.root.kind, 0);
+    assert_eq!(tree.root.children.len(), 3);
+    match &tree.root.children[1] {
+        Element::Token(token) => {
+            assert_eq!((token.leading_trivia, token.text), ("  ", "-"));
+            assert_eq!(token.token, Tok::Minus);
+        }
+        Element::Node(_) => panic!("expected the `-` token"),
+    }
+
+    let result = cst::build(
+        event_parser::ExprParser::new().event_parser(),
+        "1 2",
+        util::tok::tokenize("1 2"),
+    );
+    assert!(matches!(
+        result,
+        Err(ParseError::UnrecognizedToken {
+            token: (2, Tok::Num(2), 3),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn event_parser_reduction_order() {
+    use lalrpop_util::state_machine::Event;
+
+    // Productions are numbered in nonterminal order:
+    // 0: Expr = Expr "-" Term, 1: Expr = Term, 2: Term = Num
+    let mut events = vec![];
+    event_parser::ExprParser::new()
+        .event_parser()
+        .parse(util::tok::tokenize("1 - 2 - 3"), |event| {
+            events.push(match event {
+                Event::Shift((_, tok, _)) => format!("shift {:?}", tok),
+                Event::Reduce(production, n) => format!("reduce {} ({})", production, n),
+            })
+        })
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![
+            "shift Num(1)",
+            "reduce 2 (1)",
+            "reduce 1 (1)",
+            "shift Minus",
+            "shift Num(2)",
+            "reduce 2 (1)",
+            "reduce 0 (3)",
+            "shift Minus",
+            "shift Num(3)",
+            "reduce 2 (1)",
+            "reduce 0 (3)",
+        ]
+    );
+
+    let result = event_parser::ExprParser::new()
+        .event_parser()
+        .parse(util::tok::tokenize("1 2"), |_| ());
+    assert!(matches!(
+        result,
+        Err(ParseError::UnrecognizedToken {
+            token: (2, Tok::Num(2), 3),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn expr_slr_test() {
+    util::test(
+        |v| expr_slr::ExprParser::new().parse(1, v),
+        "22 * 3 - (6 + 4 / 2)",
+        22 * 3 - (6 + 4 / 2),
+    );
+}
+
+#[test]
+fn expr_compact_matches_dense_tables() {
+    for input in [
+        "22 * 3 - (6 + 4 / 2)",
+        "1 + 2 * (3 - 4) / 5",
+        "(((7)))",
+        "1 +",
+        ")",
+        "",
+    ] {
+        let tokens: Vec<_> = util::tok::tokenize(input)
+            .into_iter()
+            .map(|(_, tok, _)| tok)
+            .collect();
+        assert_eq!(
+            expr_compact::ExprParser::new().parse(1, tokens.clone()),
+            expr::ExprParser::new().parse(1, tokens),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn expr_slice_matches_iterator() {
+    for input in [
+        "22 * 3 - (6 + 4 / 2)",
+        "1 + 2 * (3 - 4) / 5",
+        "(((7)))",
+        "1 +",
+        ")",
+        "",
+    ] {
+        let tokens: Vec<_> = util::tok::tokenize(input)
+            .into_iter()
+            .map(|(_, tok, _)| tok)
+            .collect();
+        assert_eq!(
+            expr_slice::ExprParser::new().parse(1, &tokens),
+            expr::ExprParser::new().parse(1, tokens.clone()),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn first_sets() {
+    assert_eq!(first_sets::FIRST_EXPR, first_sets::FIRST_TERM);
+    assert_eq!(
+        first_sets::FIRST_TERM,
+        &[r#""(""#, r#""-""#, r##"r#"[0-9]+"#"##]
+    );
+    assert_eq!(first_sets::ExprParser::new().parse("-1 + (2)"), Ok(1));
+}
+
+#[test]
+fn action_trait_implementations() {
+    use action_trait::Actions;
+    use std::cell::Cell;
+
+    // runs the actions of the grammar
+    struct Evaluate;
+    impl Actions for Evaluate {}
+
+    // records the numbers instead of scaling them, and subtracts
+    struct Record(Cell<Vec<i32>>);
+    impl Actions for Record {
+        fn action1(
+            &self,
+            _: i32,
+            _: &str,
+            (_, l, _): (usize, i32, usize),
+            _: (usize, &str, usize),
+            (_, r, _): (usize, i32, usize),
+        ) -> i32 {
+            l - r
+        }
+
+        fn action3(&self, _: i32, _: &str, (_, n, _): (usize, i32, usize)) -> i32 {
+            let mut numbers = self.0.take();
+            numbers.push(n);
+            self.0.set(numbers);
+            n
+        }
+    }
+
+    let parser = action_trait::ExprParser::new();
+    assert_eq!(parser.parse(&Evaluate, 10, "1 + (2 + 3)"), Ok(60));
+
+    let record = Record(Cell::new(vec![]));
+    assert_eq!(parser.parse(&record, 10, "1 + (2 + 3)"), Ok(2));
+    // the tests run both the recursive ascent and the table-driven
+    // parser, so each number is recorded twice
+    assert_eq!(record.0.take(), vec![1, 2, 3, 1, 2, 3]);
+}
+
+#[test]
+fn partial_results_on_error() {
+    use partial_results::ItemsSymbol;
+
+    let parser = partial_results::ItemsParser::new();
+    assert_eq!(
+        parser.parse_partial(util::tok::tokenize("1, 2,")).ok(),
+        Some(vec![1, 2])
+    );
+
+    let partial = match parser.parse_partial(util::tok::tokenize("1, 2, 3 4,")) {
+        Ok(_) => panic!("expected a parse error"),
+        Err(partial) => partial,
+    };
+    assert!(matches!(
+        partial.error,
+        ParseError::UnrecognizedToken {
+            token: (10, Tok::Num(4), 11),
+            ..
+        }
+    ));
+
+    // the items parsed so far, followed by the dangling `3`
+    let items: Vec<_> = partial
+        .symbols
+        .into_iter()
+        .map(|(_, symbol, _)| match symbol {
+            ItemsSymbol::Variant1(n) => format!("{}", n),
+            ItemsSymbol::Variant2(v) => format!("{:?}", v),
+            _ => panic!("unexpected symbol on the stack"),
+        })
+        .collect();
+    assert_eq!(items, vec!["[1, 2]", "3"]);
+}
+
+#[test]
+fn recursion_limit_on_nested_input() {
+    let parser = recursion_limit::TermParser::new();
+    let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+    assert_eq!(parser.parse(util::tok::tokenize(&nested(10))), Ok(1));
+    assert_eq!(
+        parser.parse(util::tok::tokenize(&nested(10_000))),
+        Err(ParseError::User {
+            error: "recursion limit reached"
+        })
+    );
+}
+
+#[test]
+fn state_modules_parse() {
+    let parser = state_modules::ExprParser::new();
+    assert_eq!(parser.parse("2 + 3 * (10 - 4) / 2"), Ok(11));
+    assert!(matches!(
+        parser.parse("2 + * 3"),
+        Err(ParseError::UnrecognizedToken { .. })
+    ));
+}
+
+#[test]
+fn recognizer_checks_input() {
+    let parser = recognizer::ExprParser::new();
+    assert_eq!(parser.parse("1 + (2 + 3)"), Ok(()));
+    assert!(matches!(
+        parser.parse("1 + "),
+        Err(ParseError::UnrecognizedEof { .. })
+    ));
+}
+
+#[test]
+fn narrow_expected_missing_semicolon() {
+    let parser = narrow_expected::StmtsParser::new();
+    assert_eq!(parser.parse("let x = 1 + 2; let y = 3;"), Ok(vec![3, 3]));
+    match parser.parse("let x = 1 + 2 let y = 3;") {
+        Err(ParseError::UnrecognizedToken { expected, .. }) => {
+            assert_eq!(expected, vec![r#"";""#]);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match parser.parse("let x = 1 + 2") {
+        Err(ParseError::UnrecognizedEof { expected, .. }) => {
+            assert_eq!(expected, vec![r#"";""#]);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    // When no single token would help, every expected token is named.
+    match parser.parse("let x = 1 + );") {
+        Err(ParseError::UnrecognizedToken { expected, .. }) => {
+            assert_eq!(expected, vec![r#""(""#, r##"r#"[0-9]+"#"##]);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn backtrack_two_tokens() {
+    let parser = backtrack::DeclsParser::new();
+    assert_eq!(
+        parser.parse("x: int; outer: loop; y: bool;").unwrap(),
+        vec!["field x: int", "label OUTER", "field y: bool"]
+    );
+    assert!(matches!(
+        parser.parse("x: int; y:"),
+        Err(ParseError::UnrecognizedEof { .. })
+    ));
+    match parser.parse("x: ;") {
+        Err(ParseError::UnrecognizedToken { token, .. }) => assert_eq!(token.0, 3),
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn backtrack_gives_up_on_endless_reductions() {
+    let parser = backtrack_hidden_left_recursion::AParser::new();
+    assert_eq!(parser.parse("x"), Ok(0));
+    assert_eq!(parser.parse("x c"), Ok(1));
+    // no number of `B`s gets through `x x`, so trying ever more of them
+    // would go on forever
+    assert!(matches!(
+        parser.parse("x x"),
+        Err(ParseError::UnrecognizedToken { .. })
+    ));
+}
+
+#[test]
+fn parser_trait_objects() {
+    let parsers: Vec<
+        Box<dyn lalrpop_util::Parser<usize, Tok<'static>, &'static str, Output = i32>>,
+    > = vec![
+        Box::new(parser_trait_sum::ExprParser::new()),
+        Box::new(parser_trait_product::ExprParser::new()),
+    ];
+
+    let results: Vec<_> = parsers
+        .iter()
+        .zip(["2 + 3 + 4", "2 * 3 * 4"])
+        .map(|(parser, input)| parser.parse(&mut util::tok::tokenize(input).into_iter().map(Ok)))
+        .collect();
+    assert_eq!(results, vec![Ok(9), Ok(24)]);
+
+    assert!(matches!(
+        parsers[0].parse(&mut util::tok::tokenize("2 +").into_iter().map(Ok)),
+        Err(ParseError::UnrecognizedEof { .. })
+    ));
+}
+
+#[test]
+fn nullable_chain_recursive_ascent() {
+    let parser = nullable_chain::ItemsParser::new();
+    let parse = |input| parser.parse(util::tok::tokenize(input)).unwrap();
+
+    assert_eq!(parse(""), vec!["bc"]);
+    assert_eq!(parse(","), vec!["bc", "bc"]);
+    assert_eq!(parse("()"), vec!["(bc)c"]);
+    assert_eq!(parse("(()),1 2"), vec!["((bc)c)c", "b12c"]);
+    assert_eq!(parse("(()3),,()"), vec!["((bc)3c)c", "bc", "(bc)c"]);
+}
+
+#[test]
+fn context_param_diagnostics() {
+    let parser = context_param::ExprParser::new();
+    let mut diagnostics = vec![];
+
+    let result = parser.parse(&mut diagnostics, util::tok::tokenize("((4 / 0)) / 0"));
+    assert_eq!(result, Ok(0));
+    assert_eq!(
+        diagnostics,
+        vec!["division by zero at 4", "division by zero at 0"]
+    );
+
+    // the context can be used again once the parser is done with it
+    diagnostics.clear();
+    assert_eq!(
+        parser.parse(&mut diagnostics, util::tok::tokenize("8 / 2")),
+        Ok(4)
+    );
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn nonassoc_chain_is_rejected() {
+    let parser = nonassoc::ExprParser::new();
+
+    assert_eq!(parser.parse("1 < 2 + 3"), Ok(1));
+    assert_eq!(parser.parse("1 + 2 < 3"), Ok(0));
+
+    // the second `<` can only follow a `+`, never another comparison
+    match parser.parse("1 < 2 < 3").unwrap_err() {
+        ParseError::UnrecognizedToken { token, expected } => {
+            assert_eq!((token.0, (token.1).1, token.2), (6, "<", 7));
+            assert_eq!(expected, vec![r#""+""#.to_string()]);
+        }
+        err => panic!("Unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn parse_prefix_then_resume() {
+    let parser = parse_prefix::SumParser::new();
+
+    // `1 + 2` is a complete sum and `3` cannot continue it, so parsing
+    // stops there; the tokens after `3` remain in the iterator
+    let mut tokens = util::tok::tokenize("1 + 2 3 + 4").into_iter();
+    let (sum, leftover) = parser.parse_prefix(tokens.by_ref()).unwrap();
+    assert_eq!(sum, 3);
+    assert_eq!(leftover, Some((6, Tok::Num(3), 7)));
+
+    let rest = leftover.into_iter().chain(tokens);
+    assert_eq!(parser.parse_prefix(rest), Ok((7, None)));
+
+    // a token that could never continue the parse ends it as well
+    assert_eq!(
+        parser.parse_prefix(util::tok::tokenize("1 + 2 ) 4")),
+        Ok((3, Some((6, Tok::RParen, 7))))
+    );
+
+    // the input read so far must still be complete
+    assert!(matches!(
+        parser.parse_prefix(util::tok::tokenize("1 + ) 4")),
+        Err(ParseError::UnrecognizedToken {
+            token: (4, Tok::RParen, 5),
+            ..
+        })
+    ));
+    assert!(matches!(
+        parser.parse(util::tok::tokenize("1 + 2 ) 4")),
+        Err(ParseError::UnrecognizedToken { .. })
+    ));
+}
+
+#[test]
+fn parse_items_lazily() {
+    let parser = parse_items::GroupParser::new();
+
+    // each group is yielded once the token after it has been read
+    let read = std::cell::Cell::new(0);
+    let tokens = util::tok::tokenize("(1 + 2) (3) (4 + 5 + 6)")
+        .into_iter()
+        .inspect(|_| read.set(read.get() + 1));
+    let mut items = parser.parse_items(tokens);
+    assert_eq!(items.next(), Some(Ok(3)));
+    assert_eq!(read.get(), 6);
+    assert_eq!(items.next(), Some(Ok(3)));
+    assert_eq!(read.get(), 9);
+    assert_eq!(items.next(), Some(Ok(15)));
+    assert_eq!(items.next(), None);
+
+    // an error ends the iteration
+    let items: Vec<_> = parser
+        .parse_items(util::tok::tokenize("(1) (2 +) (3)"))
+        .collect();
+    assert!(matches!(
+        items[..],
+        [
+            Ok(1),
+            Err(ParseError::UnrecognizedToken {
+                token: (12, Tok::RParen, 13),
+                ..
+            })
+        ]
+    ));
+
+    assert_eq!(parser.parse_items(util::tok::tokenize("")).next(), None);
+}
+
+#[test]
+fn dangling_else_binds_to_inner_if() {
+    let parser = dangling_else::StmtParser::new();
+    assert_eq!(
+        parser.parse("if A then if B then x else y").unwrap(),
+        "(if A (if B x y))"
+    );
+    assert_eq!(
+        parser.parse("if A then if B then x else y else z").unwrap(),
+        "(if A (if B x y) z)"
+    );
+}
+
+#[test]
+fn dangling_else_reduce_binds_to_outer_if() {
+    let parser = dangling_else_reduce::StmtParser::new();
+    assert_eq!(
+        parser.parse("if A then if B then x else y").unwrap(),
+        "(if A (if B x) y)"
+    );
+    assert!(parser.parse("if A then if B then x else y else z").is_err());
+}
+
+#[test]
+fn token_names_in_boxed_errors() {
+    fn run(input: &str) -> Result<i32, Box<dyn std::error::Error>> {
+        let tokens = util::tok::tokenize(input);
+        let value = token_names::ExprParser::new()
+            .parse(tokens)
+            .map_err(|e| e.map_token(|t| token_names::token_name(&t)))?;
+        Ok(value)
+    }
+
+    assert_eq!(run("(1 + 2) + 3").unwrap(), 6);
+    assert_eq!(
+        run("1 + )").unwrap_err().to_string(),
+        "Unrecognized token `\")\"` found at 4:5\n\
+         Expected one of \"(\" or Num"
+    );
+    assert_eq!(token_names::token_name(&Tok::LParen), r#""(""#);
+    assert_eq!(token_names::token_name(&Tok::Minus), "<unknown token>");
+}
+
+#[test]
+fn bound_terminals_shift_payload() {
+    use crate::bound_terminals_lib::Tok;
+
+    let tokens = vec![
+        Tok::Let,
+        Tok::Ident {
+            name: "x".to_string(),
+            raw: false,
+        },
+        Tok::Eq,
+        Tok::Num(1 << 40),
+        Tok::Plus,
+        Tok::Num(-2),
+    ];
+    let result = bound_terminals::BindingParser::new().parse(tokens);
+    assert_eq!(result, Ok(("x".to_string(), (1 << 40) - 2)));
+}
+
+#[test]
+fn count_reductions_histogram() {
+    let parser = count_reductions::ExprParser::new();
+    assert_eq!(parser.parse(util::tok::tokenize("1 - (2 - 3) - 4")), Ok(-2));
+    assert_eq!(
+        parser.take_reduction_counts(),
+        vec![
+            ("Expr = Expr \"-\" Term", 3),
+            ("Expr = Term", 2),
+            ("Term = Num", 4),
+            ("Term = \"(\" Expr \")\"", 1),
+            ("__Expr = Expr", 1),
+        ]
+    );
+
+    // taking the counts resets them
+    assert_eq!(parser.parse(util::tok::tokenize("5")), Ok(5));
+    let counts = parser.take_reduction_counts();
+    assert_eq!(counts.iter().map(|&(_, n)| n).sum::<usize>(), 3);
+}
+
+#[test]
+fn nested_repeat_collects() {
+    let rows = |input| nested_repeat::RowsParser::new().parse(util::tok::tokenize(input));
+    assert_eq!(rows("1 2, 3,"), Ok(vec![vec![1, 2], vec![3]]));
+    assert_eq!(rows(""), Ok(vec![]));
+    assert!(rows("1, ,").is_err());
+
+    let group = |input| nested_repeat::GroupParser::new().parse(util::tok::tokenize(input));
+    assert_eq!(group("()"), Ok(None));
+    assert_eq!(group("(4 5 6)"), Ok(Some(vec![4, 5, 6])));
+}
+
+#[test]
+fn fallible_ascent_stops_at_user_error() {
+    let sum = |input| fallible_ascent::SumParser::new().parse(util::tok::tokenize(input));
+    assert_eq!(sum("1 + (2 + 3)"), Ok(6));
+    assert_eq!(
+        sum("1 + (2147483647 + 1) + 2"),
+        Err(ParseError::User { error: "overflow" })
+    );
+    assert_eq!(
+        sum("2147483647 + 1 +"),
+        Err(ParseError::User { error: "overflow" })
+    );
+}
+
+#[test]
+fn parse_str_tokenizes_input() {
+    assert_eq!(parse_str::ExprParser::new().parse_str("5 - (3 - 1)"), Ok(3));
+    assert_eq!(parse_str::TermParser::new().parse_str("(7)"), Ok(7));
+    assert_eq!(
+        parse_str::ExprParser::new().parse_str("5 -"),
+        Err(ParseError::UnrecognizedEof {
+            location: 3,
+            expected: vec![r#""(""#.to_string(), "Num".to_string()],
+        })
+    );
+}
+
+#[test]
+fn trace_parses() {
+    // the trace itself goes to stderr; run with `--nocapture` to see it
+    util::test(|v| trace::ExprParser::new().parse(v), "1 - (2 - 3)", 2);
+}
+
+#[test]
+fn unary_minus_binds_tighter() {
+    let parser = unary_minus::ExprParser::new();
+    assert_eq!(parser.parse("- 2 * 3").unwrap(), "((-2) * 3)");
+    assert_eq!(parser.parse("1 - -2 * 3").unwrap(), "(1 - ((-2) * 3))");
+    assert_eq!(parser.parse("- - 2 - 1").unwrap(), "((-(-2)) - 1)");
+    assert_eq!(parser.parse("-(2 - 1)").unwrap(), "(-(2 - 1))");
+}
+
+#[test]
+fn incremental_reparse_resumes_before_edit() {
+    use std::cell::Cell;
+
+    let input: Vec<_> = (0..100).map(|n| n.to_string()).collect();
+    let input = input.join(", ");
+    let tokens = util::tok::tokenize(&input);
+    let actions = Cell::new(0);
+    let mut parser = incremental::ItemsParser::new()
+        .incremental_parser(&actions)
+        .checkpoint_interval(1);
+    let items = parser.parse(tokens.clone()).unwrap();
+    assert_eq!(items, (0..100).collect::<Vec<_>>());
+    assert_eq!(actions.get(), 100);
+
+    // replace the last item, `99`, with `99 - 9`
+    actions.set(0);
+    let len = tokens.len();
+    let edit = vec![
+        (0, Tok::Num(99), 0),
+        (0, Tok::Minus, 0),
+        (0, Tok::Num(9), 0),
+    ];
+    let items = parser.reparse(len - 1..len, edit).unwrap();
+    assert_eq!(items.last(), Some(&90));
+    assert_eq!(items.len(), 100);
+    assert_eq!(actions.get(), 1);
+
+    // a parse error keeps the edit, which can then be fixed
+    let len = parser.tokens().len();
+    assert!(parser.reparse(len..len, vec![(0, Tok::Minus, 0)]).is_err());
+    assert_eq!(parser.tokens().len(), len + 1);
+    let items = parser.reparse(len..len + 1, vec![]).unwrap();
+    assert_eq!(items.last(), Some(&90));
+
+    // editing the front parses everything again
+    actions.set(0);
+    let items = parser.reparse(0..1, vec![(0, Tok::Num(7), 0)]).unwrap();
+    assert_eq!(items[0], 7);
+    assert_eq!(actions.get(), 100);
+
+    // with fewer checkpoints, a little more is parsed again
+    let mut parser = incremental::ItemsParser::new()
+        .incremental_parser(&actions)
+        .checkpoint_interval(10);
+    parser.parse(tokens.clone()).unwrap();
+    actions.set(0);
+    let len = tokens.len();
+    let items = parser
+        .reparse(len - 1..len, vec![(0, Tok::Num(1), 0)])
+        .unwrap();
+    assert_eq!(items.last(), Some(&1));
+    assert!((1..10).contains(&actions.get()));
+
+    // by default, the 199 tokens get a checkpoint every 14 tokens
+    let mut parser = incremental::ItemsParser::new().incremental_parser(&actions);
+    parser.parse(tokens.clone()).unwrap();
+    actions.set(0);
+    let items = parser
+        .reparse(len - 1..len, vec![(0, Tok::Num(1), 0)])
+        .unwrap();
+    assert_eq!(items.last(), Some(&1));
+    assert!((1..=7).contains(&actions.get()));
+    actions.set(0);
+    let items = parser.reparse(100..101, vec![(0, Tok::Num(1), 0)]).unwrap();
+    assert_eq!(items[50], 1);
+    assert!((50..=57).contains(&actions.get()));
+}
+
+#[test]
+fn classify_matches_kinds() {
+    let parser = classify::PairsParser::new();
+    assert_eq!(
+        parser.parse(util::tok::tokenize("1 2, 3 4 5 6")).unwrap(),
+        vec![(1, 2), (3, 4), (5, 6)]
+    );
+
+    assert_eq!(classify::token_name(&Tok::Num(2)), "Even");
+    assert_eq!(classify::token_name(&Tok::Comma), "Other");
+
+    // an even number where an odd one should be
+    assert!(matches!(
+        parser.parse(util::tok::tokenize("1 2 4")),
+        Err(ParseError::UnrecognizedToken {
+            token: (4, Tok::Num(4), 5),
+            ..
+        })
+    ));
+
+    // any token that is not a number is an `Other`
+    assert!(matches!(
+        parser.parse(util::tok::tokenize("1 -")),
+        Err(ParseError::UnrecognizedToken {
+            token: (2, Tok::Minus, 3),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn degenerate_start_symbols() {
+    let empty = degenerate::EmptyParser::new();
+    let no_tokens: Vec<(usize, Tok<'static>, usize)> = vec![];
+    assert_eq!(empty.parse(no_tokens.clone()), Ok("empty"));
+    assert!(matches!(
+        empty.parse(vec![(0, Tok::Num(1), 1)]),
+        Err(ParseError::UnrecognizedToken {
+            token: (0, Tok::Num(1), 1),
+            ..
+        })
+    ));
+
+    let single = degenerate::SingleParser::new();
+    assert_eq!(single.parse(vec![(0, Tok::Num(1), 1)]), Ok(1));
+    assert!(matches!(
+        single.parse(no_tokens),
+        Err(ParseError::UnrecognizedEof { location: 0, .. })
+    ));
+    assert!(matches!(
+        single.parse(vec![(0, Tok::Num(1), 1), (2, Tok::Num(2), 3)]),
+        Err(ParseError::UnrecognizedToken {
+            token: (2, Tok::Num(2), 3),
+            ..
+        })
+    ));
+
+    let empty = empty_lexer::EmptyParser::new();
+    assert_eq!(empty.parse(""), Ok("empty"));
+    assert_eq!(empty.parse("  "), Ok("empty"));
+    assert!(matches!(
+        empty.parse("x"),
+        Err(ParseError::InvalidToken { location: 0 })
+    ));
+}
+
+#[test]
+fn reduction_limit_on_long_input() {
+    let parser = reduction_limit::SumParser::new();
+    let sum = |terms: usize| vec!["1"; terms].join("+");
+
+    // each term is reduced once, and so is the start symbol at the end
+    assert_eq!(parser.parse(util::tok::tokenize(&sum(99))), Ok(99));
+    assert_eq!(
+        parser.parse(util::tok::tokenize(&sum(100))),
+        Err(ParseError::User {
+            error: LimitExceeded::Reductions { location: 397 }
+        })
+    );
+    assert!(matches!(
+        parser.parse(util::tok::tokenize(&sum(10_000))),
+        Err(ParseError::User {
+            error: LimitExceeded::Reductions { .. }
+        })
+    ));
+}
+
+#[test]
+fn stack_capacity_nested_input() {
+    let parser = stack_capacity::SumParser::new();
+    let sum = |terms: usize| vec!["1"; terms].join("+");
+
+    // inputs that fit in the reserved stack and ones that outgrow it
+    assert_eq!(parser.parse(util::tok::tokenize(&sum(1))), Ok(1));
+    assert_eq!(parser.parse(util::tok::tokenize(&sum(32))), Ok(32));
+    assert_eq!(parser.parse(util::tok::tokenize(&sum(1_000))), Ok(1_000));
+}
+
+#[test]
+fn boxed_nonterminal() {
+    let parser = boxed_nonterminal::SumParser::new();
+    assert_eq!(parser.parse(util::tok::tokenize("1 + (2 + 3) + 4")), Ok(10));
+}
+
+#[test]
+fn parse_fn() {
+    assert_eq!(
+        parse_fn::parse(util::tok::tokenize("1 + (2 + 3) + 4")),
+        Ok(10)
+    );
+    assert!(parse_fn::parse(util::tok::tokenize("1 +")).is_err());
+    // the other public nonterminals still have only their parsers
+    assert_eq!(
+        parse_fn::TermParser::new().parse(util::tok::tokenize("(2 + 3)")),
+        Ok(5)
+    );
+}
+
+#[test]
+fn symbol_tables() {
+    type Parser = reduction_limit::SumParser;
+    assert_eq!(Parser::TERMINALS, [r#""+""#, "Num"]);
+    assert_eq!(Parser::NONTERMINALS, ["Sum", "__Sum"]);
+    assert_eq!(Parser::START_STATE, 0);
+
+    // the error terminal comes last, as in the action table
+    assert_eq!(error_recovery::ItemParser::TERMINALS.last(), Some(&"error"));
+}
+
+#[test]
+fn disambiguate_by_precedence() {
+    let parser = disambiguate::ExprParser::new();
+    let parse = |input| parser.parse(util::tok::tokenize(input)).unwrap();
+    assert_eq!(parse("10 - 3 - 2"), 5);
+    assert_eq!(parse("1 - 2 * 3 - 4"), -9);
+    assert_eq!(parse("2 * 3 - 4 * 5"), -14);
+}
+
+#[test]
+fn disambiguate_out_of_range_is_error() {
+    let parser = disambiguate_reject::ExprParser::new();
+    assert_eq!(parser.parse(util::tok::tokenize("10 - 3")), Ok(7));
+    assert!(matches!(
+        parser.parse(util::tok::tokenize("10 - 3 - 2")),
+        Err(ParseError::UnrecognizedToken {
+            token: (6, Tok::Minus, 7),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn chars_parse_assignment() {
+    let parser = chars::AssignmentParser::new();
+    let parse = |input: &str| {
+        parser.parse(
+            input
+                .char_indices()
+                .map(|(offset, c)| (offset, c, offset + c.len_utf8())),
+        )
+    };
+    assert_eq!(parse("x=1"), Ok(("x".to_string(), 1)));
+    assert_eq!(parse("λ_2=42"), Ok(("λ_2".to_string(), 42)));
+    assert!(matches!(
+        parse("2x=1"),
+        Err(ParseError::UnrecognizedToken {
+            token: (0, '2', 1),
+            ..
+        })
+    ));
+    assert!(matches!(
+        parse("x=é"),
+        Err(ParseError::UnrecognizedToken {
+            token: (2, 'é', 4),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn bytes_parse_numbers() {
+    let parser = bytes::NumberParser::new();
+    let parse = |input: &[u8]| {
+        parser.parse(
+            input
+                .iter()
+                .enumerate()
+                .map(|(offset, &byte)| (offset, byte, offset + 1)),
+        )
+    };
+    assert_eq!(parse(b"1234"), Ok(1234));
+    assert_eq!(parse(b"+7"), Ok(7));
+    assert_eq!(parse(b"-42"), Ok(-42));
+    assert_eq!(parse(b"#fF"), Ok(255));
+    assert!(matches!(
+        parse(b"12a"),
+        Err(ParseError::UnrecognizedToken {
+            token: (2, b'a', 3),
+            ..
+        })
+    ));
+}
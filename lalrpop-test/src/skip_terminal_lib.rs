@@ -0,0 +1,8 @@
+/// A token type whose tokenizer keeps whitespace and comments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    Num(i32),
+    Plus,
+    Space,
+    Comment(&'static str),
+}
@@ -96,3 +96,19 @@ where
 
     (slice_end, None)
 }
+
+/// Splits numbers into even and odd ones, for grammars that classify
+/// their tokens with `#[classify]`.
+pub enum Parity {
+    Even(i32),
+    Odd(i32),
+    Other,
+}
+
+pub fn parity(tok: &Tok<'_>) -> Parity {
+    match *tok {
+        Tok::Num(n) if n % 2 == 0 => Parity::Even(n),
+        Tok::Num(n) => Parity::Odd(n),
+        _ => Parity::Other,
+    }
+}
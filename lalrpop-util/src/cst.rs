@@ -0,0 +1,126 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::state_machine::{Event, EventParser, ParseError, ParserDefinition, TokenTriple};
+
+/// A concrete syntax tree that keeps every token of the input, together
+/// with the text between tokens (whitespace, comments and the like), so
+/// that the source can be reconstructed exactly.
+///
+/// Trees are built by [`build`] from the events of an [`EventParser`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxTree<'input, K, T> {
+    /// The node of the start symbol.
+    pub root: Node<'input, K, T>,
+
+    /// The text after the last token.
+    pub trailing_trivia: &'input str,
+}
+
+/// The reduction of a production, with the tokens and nodes it was
+/// reduced from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node<'input, K, T> {
+    /// The index of the production, as reported by
+    /// [`Event::Reduce`].
+    pub kind: K,
+    pub children: Vec<Element<'input, K, T>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Element<'input, K, T> {
+    Node(Node<'input, K, T>),
+    Token(Token<'input, T>),
+}
+
+/// A shifted token, along with the text it was lexed from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token<'input, T> {
+    /// The text between the previous token (or the start of the input)
+    /// and this one.
+    pub leading_trivia: &'input str,
+    pub text: &'input str,
+    pub token: T,
+}
+
+impl<'input, K, T> SyntaxTree<'input, K, T> {
+    /// Reconstructs the source the tree was parsed from.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.root.write_text(&mut text);
+        text.push_str(self.trailing_trivia);
+        text
+    }
+}
+
+impl<'input, K, T> Node<'input, K, T> {
+    /// Appends the text of the node, including the trivia before each of
+    /// its tokens, to `out`.
+    pub fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                Element::Node(node) => node.write_text(out),
+                Element::Token(token) => {
+                    out.push_str(token.leading_trivia);
+                    out.push_str(token.text);
+                }
+            }
+        }
+    }
+}
+
+/// Parses `tokens` with `parser` and returns the concrete syntax tree.
+///
+/// The locations of the tokens must be byte offsets into `source`, so
+/// this works with LALRPOP's own lexer and with any lexer that reports
+/// `usize` locations in the same way. A grammar gets an `event_parser`
+/// constructor with the `#[event_parser]` attribute.
+///
+/// # Panics
+///
+/// Panics if the tokens overlap or are out of order, or if a location is
+/// not a character boundary in `source`.
+pub fn build<'input, D, I>(
+    parser: EventParser<D>,
+    source: &'input str,
+    tokens: I,
+) -> Result<SyntaxTree<'input, D::ReduceIndex, D::Token>, ParseError<D>>
+where
+    D: ParserDefinition<Location = usize>,
+    I: IntoIterator<Item = TokenTriple<D>>,
+{
+    let mut stack = Vec::new();
+    let mut end = 0;
+    parser.parse(tokens, |event| match event {
+        Event::Shift((start, token, stop)) => {
+            assert!(
+                end <= start && start <= stop,
+                "token at {}..{} overlaps the previous one, which ended at {}",
+                start,
+                stop,
+                end
+            );
+            stack.push(Element::Token(Token {
+                leading_trivia: &source[end..start],
+                text: &source[start..stop],
+                token,
+            }));
+            end = stop;
+        }
+        Event::Reduce(kind, len) => {
+            let children = stack.split_off(stack.len() - len);
+            stack.push(Element::Node(Node { kind, children }));
+        }
+    })?;
+
+    // the start symbol is the only thing left on the stack, since the
+    // final reduction to the augmented start symbol is not reported
+    let root = match (stack.pop(), stack.is_empty()) {
+        (Some(Element::Node(root)), true) => root,
+        _ => unreachable!("the accepted stack holds more than the start symbol"),
+    };
+    Ok(SyntaxTree {
+        root,
+        trailing_trivia: &source[end..],
+    })
+}
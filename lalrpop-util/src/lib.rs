@@ -7,8 +7,11 @@ use core::fmt;
 #[cfg(feature = "std")]
 use std::error::Error;
 
+pub mod cst;
 #[cfg(feature = "lexer")]
 pub mod lexer;
+pub mod line_col;
+pub mod lookahead;
 pub mod state_machine;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -75,6 +78,17 @@ impl<L, T, E> ParseError<L, T, E> {
         }
     }
 
+    /// Whether the parse failed only because the input ended too soon:
+    /// the input read so far is a valid start, and some token could
+    /// continue it. A REPL can use this to prompt for another line
+    /// rather than report an error.
+    pub fn needs_more_input(&self) -> bool {
+        match self {
+            ParseError::UnrecognizedEof { expected, .. } => !expected.is_empty(),
+            _ => false,
+        }
+    }
+
     pub fn map_location<LL>(self, op: impl FnMut(L) -> LL) -> ParseError<LL, T, E> {
         self.map_intern(op, |x| x, |x| x)
     }
@@ -159,12 +173,90 @@ where
     }
 }
 
+/// A limit set on a generated parser, reported through
+/// `ParseError::User`: the grammar's error type must implement
+/// `From<LimitExceeded<L>>`. The default error type, `&'static str`,
+/// does.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum LimitExceeded<L> {
+    /// The input nests more deeply than the `#[recursion_limit]` of a
+    /// recursive ascent parser allows.
+    Recursion {
+        /// The end of the symbol at which the limit was reached
+        location: L,
+    },
+
+    /// A table-driven parser reduces more productions than its
+    /// `#[reduction_limit]` allows.
+    Reductions {
+        /// The start of the lookahead token, or the end of the input
+        /// read so far, at which the limit was reached
+        location: L,
+    },
+}
+
+impl<L> LimitExceeded<L> {
+    fn message(&self) -> &'static str {
+        match self {
+            LimitExceeded::Recursion { .. } => "recursion limit reached",
+            LimitExceeded::Reductions { .. } => "reduction limit reached",
+        }
+    }
+}
+
+impl<L: fmt::Display> fmt::Display for LimitExceeded<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Recursion { location } => {
+                write!(f, "Recursion limit reached at {}", location)
+            }
+            LimitExceeded::Reductions { location } => {
+                write!(f, "Reduction limit reached at {}", location)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<L: fmt::Debug + fmt::Display> Error for LimitExceeded<L> {}
+
+impl<L> From<LimitExceeded<L>> for &'static str {
+    fn from(limit: LimitExceeded<L>) -> Self {
+        limit.message()
+    }
+}
+
+impl<L> From<LimitExceeded<L>> for String {
+    fn from(limit: LimitExceeded<L>) -> Self {
+        String::from(limit.message())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ErrorRecovery<L, T, E> {
     pub error: ParseError<L, T, E>,
     pub dropped_tokens: Vec<(L, T, L)>,
 }
 
+/// A parser for some start symbol, usable as a trait object, so that
+/// parsers generated from different grammars can be stored and called
+/// through one interface. LALRPOP implements this for the parsers of
+/// grammars annotated with `#[parser_trait]`.
+///
+/// The tokens are handed over as a `dyn Iterator`; the generated
+/// `parse` methods, which take any `IntoIterator`, remain available
+/// on the parser types themselves.
+pub trait Parser<L, T, E> {
+    /// The type of the start symbol.
+    type Output;
+
+    fn parse(
+        &self,
+        tokens: &mut dyn Iterator<Item = Result<(L, T, L), E>>,
+    ) -> Result<Self::Output, ParseError<L, T, E>>;
+}
+
 /// Define a module using the generated parse from a `.lalrpop` file.
 ///
 /// You have to specify the name of the module and the path of the file
@@ -219,4 +311,41 @@ mod tests {
              Expected one of t1, t2 or t3"
         );
     }
+
+    #[test]
+    fn needs_more_input() {
+        let eof = |expected: Vec<&str>| ParseError::UnrecognizedEof::<i32, &str, &str> {
+            location: 3,
+            expected: expected.into_iter().map(|s| s.to_string()).collect(),
+        };
+        assert!(eof(vec!["t1"]).needs_more_input());
+        assert!(!eof(vec![]).needs_more_input());
+        assert!(!ParseError::ExtraToken::<i32, &str, &str> {
+            token: (3, "t0", 4)
+        }
+        .needs_more_input());
+    }
+
+    #[test]
+    fn limit_exceeded() {
+        let limit = LimitExceeded::Recursion { location: 7 };
+        assert_eq!(limit.to_string(), "Recursion limit reached at 7");
+        assert_eq!(<&str>::from(limit.clone()), "recursion limit reached");
+        assert_eq!(String::from(limit), "recursion limit reached");
+    }
+
+    #[test]
+    fn line_col_tracker() {
+        let tracker = line_col::LineColTracker::new("ab\nλc\n\nd");
+        let at = |offset| {
+            let line_col::LineCol { line, column } = tracker.line_col(offset);
+            (line, column)
+        };
+        assert_eq!(at(0), (1, 1));
+        assert_eq!(at(2), (1, 3));
+        assert_eq!(at(3), (2, 1));
+        assert_eq!(at(5), (2, 2));
+        assert_eq!(at(7), (3, 1));
+        assert_eq!(at(9), (4, 2));
+    }
 }
@@ -0,0 +1,92 @@
+use core::cell::OnceCell;
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use crate::ParseError;
+
+/// A 1-based line and column in some source text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Maps the byte offsets produced by a lexer over `&str` input (such
+/// as the lexer LALRPOP generates for grammars without an `extern`
+/// token type) to lines and columns.
+///
+/// The input is only scanned for newlines the first time an offset is
+/// looked up, so creating a tracker for every parse costs nothing when
+/// the parse succeeds.
+pub struct LineColTracker<'input> {
+    text: &'input str,
+    line_starts: OnceCell<Vec<usize>>,
+}
+
+impl<'input> LineColTracker<'input> {
+    pub fn new(text: &'input str) -> Self {
+        LineColTracker {
+            text,
+            line_starts: OnceCell::new(),
+        }
+    }
+
+    /// Returns the line and column of the byte at `offset`. Columns
+    /// count characters rather than bytes, and an offset at the very
+    /// end of the text refers to the position just past its last
+    /// character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is past the end of the text or does not lie
+    /// on a character boundary.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let line_starts = self.line_starts.get_or_init(|| {
+            let newlines = self.text.match_indices('\n').map(|(i, _)| i + 1);
+            core::iter::once(0).chain(newlines).collect()
+        });
+        let line = line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.text[line_starts[line]..offset].chars().count();
+        LineCol {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+}
+
+impl<L, T, E> ParseError<L, T, E> {
+    /// The location at which the error was found: the start of the
+    /// offending token, or the position at which the input ended.
+    /// `User` errors carry no location, so this returns `None` for
+    /// them.
+    pub fn location(&self) -> Option<&L> {
+        match self {
+            ParseError::InvalidToken { location }
+            | ParseError::UnrecognizedEof { location, .. } => Some(location),
+            ParseError::UnrecognizedToken {
+                token: (start, _, _),
+                ..
+            }
+            | ParseError::ExtraToken {
+                token: (start, _, _),
+            } => Some(start),
+            ParseError::User { .. } => None,
+        }
+    }
+}
+
+impl<T, E> ParseError<usize, T, E> {
+    /// The line and column of [`location`](Self::location), for a
+    /// parser whose locations are byte offsets into the text that
+    /// `tracker` was created for.
+    pub fn line_col(&self, tracker: &LineColTracker<'_>) -> Option<LineCol> {
+        self.location().map(|&offset| tracker.line_col(offset))
+    }
+}
@@ -0,0 +1,57 @@
+/// A token iterator over a slice, which keeps its place in the input as
+/// an index into the slice and so can look any number of tokens ahead.
+///
+/// Generated parsers read their input through a `SliceTokens` when the
+/// grammar is annotated with `#[slice_input]`. Each token is passed to
+/// `convert` as it is consumed, to turn it into what the parser expects.
+pub struct SliceTokens<'a, T, F> {
+    tokens: &'a [T],
+    position: usize,
+    convert: F,
+}
+
+impl<'a, T, F> SliceTokens<'a, T, F> {
+    /// Creates a cursor at the start of `tokens`.
+    pub fn new(tokens: &'a [T], convert: F) -> Self {
+        SliceTokens {
+            tokens,
+            position: 0,
+            convert,
+        }
+    }
+
+    /// The index of the token that `next` would return.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the `n`th upcoming token without consuming it, where
+    /// `peek(0)` is the token that `next` would return. Returns `None`
+    /// if the input ends first.
+    pub fn peek(&self, n: usize) -> Option<&'a T> {
+        self.tokens.get(self.position.checked_add(n)?)
+    }
+
+    /// The tokens that have not been consumed yet.
+    pub fn remaining(&self) -> &'a [T] {
+        &self.tokens[self.position..]
+    }
+}
+
+impl<'a, T, F, R> Iterator for SliceTokens<'a, T, F>
+where
+    F: FnMut(&'a T) -> R,
+{
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some((self.convert)(token))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.tokens.len() - self.position;
+        (len, Some(len))
+    }
+}
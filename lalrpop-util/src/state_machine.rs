@@ -1,5 +1,9 @@
-use alloc::{string::String, vec, vec::Vec};
+use alloc::{collections::VecDeque, string::String, vec, vec::Vec};
 use core::fmt::Debug;
+use core::future::Future;
+use core::ops::Range;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 
 #[cfg(feature = "std")]
 const DEBUG_ENABLED: bool = false;
@@ -13,6 +17,10 @@ macro_rules! debug {
     }
 }
 
+/// How many reductions a `#[backtrack]` parser simulates when trying
+/// one of the actions of a conflict before it gives up on the action.
+const MAX_SPECULATED_REDUCTIONS: usize = 10_000;
+
 pub trait ParserDefinition: Sized {
     /// Represents a location in the input text. If you are using the
     /// default tokenizer, this will be a `usize`.
@@ -89,6 +97,52 @@ pub trait ParserDefinition: Sized {
     /// not to the user's terminals but to the "special terminal" `$`.
     fn eof_action(&self, state: Self::StateIndex) -> Self::Action;
 
+    /// The action to take on the lookahead, which is `None` at the end
+    /// of the input. The parser uses this rather than `action` or
+    /// `eof_action` whenever it acts on the lookahead, so that a parser
+    /// built with `#[disambiguate]` can let the user choose among the
+    /// actions of an ambiguous state. By default, this looks the
+    /// action up in the table.
+    fn lookahead_action(
+        &self,
+        state: Self::StateIndex,
+        lookahead: Option<(Self::TokenIndex, &Self::Token)>,
+    ) -> Self::Action {
+        match lookahead {
+            Some((token_index, _)) => self.action(state, token_index),
+            None => self.eof_action(state),
+        }
+    }
+
+    /// The actions among which a parser built with `#[backtrack]`
+    /// chooses in the given state on the given lookahead (`None` for
+    /// EOF), in the order in which it tries them. This is empty unless
+    /// the state has a conflict on the lookahead.
+    fn conflicting_actions(
+        &self,
+        state: Self::StateIndex,
+        opt_token_index: Option<Self::TokenIndex>,
+    ) -> &[Self::Action] {
+        let _ = (state, opt_token_index);
+        &[]
+    }
+
+    /// For a parser built with `#[backtrack]`, the number of tokens,
+    /// starting with the lookahead, that must be shifted after one of
+    /// the `conflicting_actions` for the parser to take it. Zero for
+    /// other parsers.
+    fn backtrack_window(&self) -> usize {
+        0
+    }
+
+    /// How many symbols to reserve room for on the parser stacks
+    /// before parsing, so that inputs nesting no deeper than this do
+    /// not have to grow them. Zero for parsers built without
+    /// `#[stack_capacity]`.
+    fn stack_capacity(&self) -> usize {
+        0
+    }
+
     /// If we reduce to a nonterminal in the given state, what state
     /// do we go to? This is infallible due to the nature of LR(1)
     /// grammars.
@@ -110,6 +164,20 @@ pub trait ParserDefinition: Sized {
         self.expected_tokens(*states.last().unwrap())
     }
 
+    /// Returns the expected tokens reported when the token with the
+    /// given index (or EOF, for `None`) cannot be parsed after
+    /// `states`. Definitions can use the token to leave out expected
+    /// tokens that would not help; by default, this is the same as
+    /// `expected_tokens_from_states`.
+    fn expected_tokens_before(
+        &self,
+        states: &[Self::StateIndex],
+        opt_token_index: Option<Self::TokenIndex>,
+    ) -> Vec<String> {
+        let _ = opt_token_index;
+        self.expected_tokens_from_states(states)
+    }
+
     /// True if this grammar supports error recovery.
     fn uses_error_recovery(&self) -> bool;
 
@@ -170,6 +238,18 @@ pub enum SimulatedReduce<D: ParserDefinition> {
     Accept,
 }
 
+/// One of the actions among which the function given to
+/// `#[disambiguate]` chooses, in the order of the slice it receives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Alternative {
+    /// Shift the lookahead.
+    Shift,
+
+    /// Reduce by the given production, written as in the grammar,
+    /// e.g. `Expr = Expr "+" Expr`.
+    Reduce(&'static str),
+}
+
 // These aliases are an elaborate hack to get around
 // the warnings when you define a type alias like `type Foo<D: Trait>`
 #[doc(hidden)]
@@ -189,6 +269,21 @@ pub type TokenTriple<D> = (Location<D>, Token<D>, Location<D>);
 pub type SymbolTriple<D> = (Location<D>, Symbol<D>, Location<D>);
 pub type ErrorRecovery<D> = crate::ErrorRecovery<Location<D>, Token<D>, Error<D>>;
 
+/// The error returned by [`Parser::drive_partial`]: alongside the
+/// error itself, it carries whatever was left on the parser's stack,
+/// i.e. the tokens shifted and nonterminals reduced so far, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialParse<L, T, E, S> {
+    pub error: crate::ParseError<L, T, E>,
+    pub symbols: Vec<(L, S, L)>,
+}
+
+pub type PartialParseError<D> = PartialParse<Location<D>, Token<D>, Error<D>, Symbol<D>>;
+
+/// The result of [`Parser::drive_prefix`]: the value parsed, and the
+/// token the parser stopped at, if any.
+pub type PrefixParseResult<D> = Result<(Success<D>, Option<TokenTriple<D>>), ParseError<D>>;
+
 pub struct Parser<D, I>
 where
     D: ParserDefinition,
@@ -199,6 +294,27 @@ where
     states: Vec<D::StateIndex>,
     symbols: Vec<SymbolTriple<D>>,
     last_location: D::Location,
+
+    // Tokens read ahead of the lookahead to choose among conflicting
+    // actions, for a `#[backtrack]` parser.
+    peeked: VecDeque<Result<TokenTriple<D>, ParseError<D>>>,
+
+    // Set by `drive_prefix`: rather than reporting an unexpected
+    // token, finish the parse if the input could end there, and keep
+    // the token in `leftover`.
+    stop_early: bool,
+    leftover: Option<TokenTriple<D>>,
+}
+
+/// The stacks a parse starts with: the start state and no symbols,
+/// with room for `stack_capacity` more of each.
+fn initial_stacks<D: ParserDefinition>(
+    definition: &D,
+) -> (Vec<D::StateIndex>, Vec<SymbolTriple<D>>) {
+    let capacity = definition.stack_capacity();
+    let mut states = Vec::with_capacity(capacity + 1);
+    states.push(definition.start_state());
+    (states, Vec::with_capacity(capacity))
 }
 
 enum NextToken<D: ParserDefinition> {
@@ -212,17 +328,46 @@ where
     D: ParserDefinition,
     I: Iterator<Item = Result<TokenTriple<D>, ParseError<D>>>,
 {
-    pub fn drive(definition: D, tokens: I) -> ParseResult<D> {
+    fn new(definition: D, tokens: I, stop_early: bool) -> Self {
         let last_location = definition.start_location();
-        let start_state = definition.start_state();
+        let (states, symbols) = initial_stacks(&definition);
         Parser {
             definition,
             tokens,
-            states: vec![start_state],
-            symbols: vec![],
+            states,
+            symbols,
             last_location,
+            peeked: VecDeque::new(),
+            stop_early,
+            leftover: None,
         }
-        .parse()
+    }
+
+    pub fn drive(definition: D, tokens: I) -> ParseResult<D> {
+        Parser::new(definition, tokens, false).parse()
+    }
+
+    /// Like `drive`, but if parsing fails, the contents of the stack
+    /// at the point of failure are returned along with the error, so
+    /// that callers can make use of the part of the input that did
+    /// parse.
+    pub fn drive_partial(definition: D, tokens: I) -> Result<Success<D>, PartialParseError<D>> {
+        let mut parser = Parser::new(definition, tokens, false);
+        parser.parse().map_err(|error| PartialParse {
+            error,
+            symbols: parser.symbols,
+        })
+    }
+
+    /// Like `drive`, but parses only a prefix of the input: parsing
+    /// stops at the first token that cannot continue the parse, as
+    /// long as the input read before it is complete. That token, if
+    /// any, is returned along with the result; the tokens after it
+    /// are left in `tokens`.
+    pub fn drive_prefix(definition: D, tokens: I) -> PrefixParseResult<D> {
+        let mut parser = Parser::new(definition, tokens, true);
+        let success = parser.parse()?;
+        Ok((success, parser.leftover))
     }
 
     fn top_state(&self) -> D::StateIndex {
@@ -246,8 +391,7 @@ where
             debug!("\\ token_index: {:?}", token_index);
 
             'inner: loop {
-                let top_state = self.top_state();
-                let action = self.definition.action(top_state, token_index);
+                let action = self.lookahead_action(Some((token_index, &lookahead.1)));
                 debug!("\\ action: {:?}", action);
 
                 if let Some(target_state) = action.as_shift() {
@@ -263,11 +407,20 @@ where
 
                     if let Some(r) = self.reduce(reduce_index, Some(&lookahead.0)) {
                         return match r {
+                            Ok(success) if self.stop_early => {
+                                self.leftover = Some(lookahead);
+                                Ok(success)
+                            }
                             // we reached eof, but still have lookahead
                             Ok(_) => Err(crate::ParseError::ExtraToken { token: lookahead }),
                             Err(e) => Err(e),
                         };
                     }
+                } else if self.stop_early && self.accepts_eof() {
+                    debug!("\\ error -- stopping before the lookahead");
+
+                    self.leftover = Some(lookahead);
+                    return self.parse_eof();
                 } else {
                     debug!("\\ error -- initiating error recovery!");
 
@@ -288,8 +441,7 @@ where
     /// Invoked when we have no more tokens to consume.
     fn parse_eof(&mut self) -> ParseResult<D> {
         loop {
-            let top_state = self.top_state();
-            let action = self.definition.eof_action(top_state);
+            let action = self.lookahead_action(None);
             if let Some(reduce_index) = action.as_reduce() {
                 if let Some(result) =
                     self.definition
@@ -320,12 +472,15 @@ where
         if !self.definition.uses_error_recovery() {
             debug!("\\ error -- no error recovery!");
 
-            return NextToken::Done(Err(
-                self.unrecognized_token_error(opt_lookahead, &self.states)
-            ));
+            return NextToken::Done(Err(self.unexpected_lookahead_error(
+                opt_lookahead,
+                opt_token_index,
+                &self.states,
+            )));
         }
 
-        let error = self.unrecognized_token_error(opt_lookahead.clone(), &self.states);
+        let error =
+            self.unexpected_lookahead_error(opt_lookahead.clone(), opt_token_index, &self.states);
 
         let mut dropped_tokens = vec![];
 
@@ -586,6 +741,157 @@ where
         }
     }
 
+    /// Whether the input read so far forms a complete parse, i.e.
+    /// whether reaching EOF now would be accepted.
+    fn accepts_eof(&self) -> bool {
+        let mut states = self.states.clone();
+        loop {
+            let top = *states.last().unwrap();
+            let reduce_action = match self.definition.eof_action(top).as_reduce() {
+                Some(reduce_action) => reduce_action,
+                None => return false,
+            };
+            match self.definition.simulate_reduce(reduce_action) {
+                SimulatedReduce::Reduce {
+                    states_to_pop,
+                    nonterminal_produced,
+                } => {
+                    states.truncate(states.len() - states_to_pop);
+                    let top = *states.last().unwrap();
+                    states.push(self.definition.goto(top, nonterminal_produced));
+                }
+                SimulatedReduce::Accept => return true,
+            }
+        }
+    }
+
+    /// The action to take on the lookahead in the current state. In a
+    /// state with a conflict on the lookahead, a `#[backtrack]` parser
+    /// reads up to `backtrack_window` tokens ahead, and takes the first
+    /// of the conflicting actions after which all of them, or all of
+    /// them up to the end of the input, can be parsed. If there is no
+    /// such action, it takes the first, which leads to the error.
+    fn lookahead_action(&mut self, lookahead: Option<(D::TokenIndex, &D::Token)>) -> D::Action {
+        let top_state = self.top_state();
+        let window = self.definition.backtrack_window();
+        if window > 0 {
+            let opt_token_index = lookahead.map(|(token_index, _)| token_index);
+            let alternatives = self
+                .definition
+                .conflicting_actions(top_state, opt_token_index)
+                .to_vec();
+            if !alternatives.is_empty() {
+                let mut tokens = vec![opt_token_index];
+                if opt_token_index.is_some() {
+                    tokens.extend(self.peek_token_indices(window - 1));
+                }
+                debug!("\\ backtracking over {:?}", tokens);
+                let states = &self.states;
+                return alternatives
+                    .iter()
+                    .copied()
+                    .find(|&action| self.speculate(states.clone(), action, &tokens))
+                    .unwrap_or(alternatives[0]);
+            }
+        }
+        self.definition.lookahead_action(top_state, lookahead)
+    }
+
+    /// Reads up to `count` tokens past the lookahead without consuming
+    /// them, and returns their indices, with `None` for the end of the
+    /// input. Stops early at the end of the input, or at a token that
+    /// is an error or cannot be classified.
+    fn peek_token_indices(&mut self, count: usize) -> Vec<Option<D::TokenIndex>> {
+        let mut indices = vec![];
+        for index in 0..count {
+            if index == self.peeked.len() {
+                match self.tokens.next() {
+                    Some(token) => self.peeked.push_back(token),
+                    None => {
+                        indices.push(None);
+                        break;
+                    }
+                }
+            }
+            match self.peeked[index] {
+                Ok((_, ref token, _)) => match self.definition.token_to_index(token) {
+                    Some(token_index) => indices.push(Some(token_index)),
+                    None => break,
+                },
+                Err(_) => break,
+            }
+        }
+        indices
+    }
+
+    /// Whether, after taking `action` on the first of `tokens` with the
+    /// given state stack, the parser could go on to shift every one of
+    /// `tokens` (or accept, at the end of the input). Conflicts met on
+    /// the way are tried in turn, depth first. Only the state stack is
+    /// simulated, so no action code runs. Hidden left recursion can
+    /// reduce empty productions forever, so the speculation fails after
+    /// `MAX_SPECULATED_REDUCTIONS` reductions.
+    fn speculate(
+        &self,
+        states: Vec<D::StateIndex>,
+        action: D::Action,
+        tokens: &[Option<D::TokenIndex>],
+    ) -> bool {
+        let mut budget = MAX_SPECULATED_REDUCTIONS;
+        // (states, action to take next, index into `tokens`)
+        let mut pending = vec![(states, action, 0)];
+        while let Some((mut states, action, mut position)) = pending.pop() {
+            if let Some(target_state) = action.as_shift() {
+                states.push(target_state);
+                position += 1;
+            } else if let Some(reduce_index) = action.as_reduce() {
+                if budget == 0 {
+                    debug!("\\ speculation ran out of reductions");
+                    return false;
+                }
+                budget -= 1;
+                match self.definition.simulate_reduce(reduce_index) {
+                    SimulatedReduce::Reduce {
+                        states_to_pop,
+                        nonterminal_produced,
+                    } => {
+                        states.truncate(states.len() - states_to_pop);
+                        let top = *states.last().unwrap();
+                        states.push(self.definition.goto(top, nonterminal_produced));
+                    }
+                    SimulatedReduce::Accept => {
+                        if tokens[position].is_none() {
+                            return true;
+                        }
+                        continue;
+                    }
+                }
+            } else {
+                continue;
+            }
+
+            let opt_token_index = match tokens.get(position) {
+                Some(&opt_token_index) => opt_token_index,
+                None => return true,
+            };
+            let top = *states.last().unwrap();
+            let alternatives = self.definition.conflicting_actions(top, opt_token_index);
+            if alternatives.is_empty() {
+                let action = match opt_token_index {
+                    Some(token_index) => self.definition.action(top, token_index),
+                    None => self.definition.eof_action(top),
+                };
+                pending.push((states, action, position));
+            } else {
+                // pushed in reverse, so that the first is tried first
+                for &action in alternatives.iter().rev() {
+                    pending.push((states.clone(), action, position));
+                }
+            }
+        }
+        false
+    }
+
     fn reduce(
         &mut self,
         action: D::ReduceIndex,
@@ -612,11 +918,31 @@ where
         }
     }
 
+    /// Like `unrecognized_token_error`, for a lookahead that was
+    /// classified as the token with the given index (or EOF).
+    fn unexpected_lookahead_error(
+        &self,
+        token: Option<TokenTriple<D>>,
+        opt_token_index: Option<D::TokenIndex>,
+        states: &[D::StateIndex],
+    ) -> ParseError<D> {
+        let expected = self
+            .definition
+            .expected_tokens_before(states, opt_token_index);
+        match token {
+            Some(token) => crate::ParseError::UnrecognizedToken { token, expected },
+            None => crate::ParseError::UnrecognizedEof {
+                location: self.last_location.clone(),
+                expected,
+            },
+        }
+    }
+
     /// Consume the next token from the input and classify it into a
     /// token index. Classification can fail with an error. If there
     /// are no more tokens, signal EOF.
     fn next_token(&mut self) -> NextToken<D> {
-        let token = match self.tokens.next() {
+        let token = match self.peeked.pop_front().or_else(|| self.tokens.next()) {
             Some(Ok(v)) => v,
             Some(Err(e)) => return NextToken::Done(Err(e)),
             None => return NextToken::Eof,
@@ -637,6 +963,485 @@ where
     }
 }
 
+/// The outcome of feeding a token to a [`PushParser`].
+pub enum Step<D: ParserDefinition> {
+    /// The token was consumed; the parser is waiting for more input.
+    More,
+
+    /// End of input was reached and the start symbol was reduced.
+    Done(Success<D>),
+}
+
+/// A parser that is driven by the caller, one token at a time,
+/// rather than pulling tokens out of an iterator. This is useful when
+/// the input arrives incrementally (e.g., in an editor) and the caller
+/// wants to be able to pause between tokens.
+///
+/// Error recovery is not supported: the first error encountered is
+/// returned from `feed`, after which the parser must not be used
+/// again.
+pub struct PushParser<D: ParserDefinition> {
+    definition: D,
+    states: Vec<D::StateIndex>,
+    symbols: Vec<SymbolTriple<D>>,
+    last_location: D::Location,
+}
+
+impl<D: ParserDefinition> PushParser<D> {
+    pub fn new(definition: D) -> Self {
+        let last_location = definition.start_location();
+        let (states, symbols) = initial_stacks(&definition);
+        PushParser {
+            definition,
+            states,
+            symbols,
+            last_location,
+        }
+    }
+
+    /// Feed the next token to the parser, or `None` to signal the end
+    /// of the input. Returns `Step::Done` with the parse result once
+    /// the end of input has been accepted, and `Step::More` otherwise.
+    pub fn feed(&mut self, token: Option<TokenTriple<D>>) -> Result<Step<D>, ParseError<D>> {
+        let lookahead = match token {
+            Some(lookahead) => lookahead,
+            None => return self.feed_eof().map(Step::Done),
+        };
+
+        debug!("+ FEED: {:?}", lookahead);
+
+        self.last_location = lookahead.2.clone();
+        let token_index = match self.definition.token_to_index(&lookahead.1) {
+            Some(i) => i,
+            None => return Err(self.unrecognized_token_error(Some(lookahead))),
+        };
+
+        loop {
+            let top_state = *self.states.last().unwrap();
+            let action = self
+                .definition
+                .lookahead_action(top_state, Some((token_index, &lookahead.1)));
+            debug!("\\ action: {:?}", action);
+
+            if let Some(target_state) = action.as_shift() {
+                let symbol = self.definition.token_to_symbol(token_index, lookahead.1);
+                self.states.push(target_state);
+                self.symbols.push((lookahead.0, symbol, lookahead.2));
+                return Ok(Step::More);
+            } else if let Some(reduce_index) = action.as_reduce() {
+                if let Some(r) = self.definition.reduce(
+                    reduce_index,
+                    Some(&lookahead.0),
+                    &mut self.states,
+                    &mut self.symbols,
+                ) {
+                    return match r {
+                        Ok(_) => Err(crate::ParseError::ExtraToken { token: lookahead }),
+                        Err(e) => Err(e),
+                    };
+                }
+            } else {
+                return Err(self.unrecognized_token_error(Some(lookahead)));
+            }
+        }
+    }
+
+    /// Returns a future that feeds the parser tokens from `source`
+    /// until it runs dry, and resolves to the result of the parse.
+    /// `source` is polled like a `futures::Stream`: it returns
+    /// `Poll::Pending` while no token is available yet, after arranging
+    /// for the task to be woken, and `Poll::Ready(None)` at the end of
+    /// the input. A stream can be passed as
+    /// `|cx| stream.as_mut().poll_next(cx)`.
+    pub fn parse_stream<S>(self, source: S) -> StreamParse<D, S>
+    where
+        S: FnMut(&mut Context<'_>) -> Poll<Option<Result<TokenTriple<D>, ParseError<D>>>>,
+    {
+        StreamParse {
+            parser: self,
+            source,
+        }
+    }
+
+    fn feed_eof(&mut self) -> ParseResult<D> {
+        loop {
+            let top_state = *self.states.last().unwrap();
+            let action = self.definition.lookahead_action(top_state, None);
+            if let Some(reduce_index) = action.as_reduce() {
+                if let Some(result) =
+                    self.definition
+                        .reduce(reduce_index, None, &mut self.states, &mut self.symbols)
+                {
+                    return result;
+                }
+            } else {
+                return Err(self.unrecognized_token_error(None));
+            }
+        }
+    }
+
+    fn unrecognized_token_error(&self, token: Option<TokenTriple<D>>) -> ParseError<D> {
+        let expected = self.definition.expected_tokens_from_states(&self.states);
+        match token {
+            Some(token) => crate::ParseError::UnrecognizedToken { token, expected },
+            None => crate::ParseError::UnrecognizedEof {
+                location: self.last_location.clone(),
+                expected,
+            },
+        }
+    }
+}
+
+/// The future returned by [`PushParser::parse_stream`].
+pub struct StreamParse<D: ParserDefinition, S> {
+    parser: PushParser<D>,
+    source: S,
+}
+
+impl<D, S> Future for StreamParse<D, S>
+where
+    D: ParserDefinition,
+    S: FnMut(&mut Context<'_>) -> Poll<Option<Result<TokenTriple<D>, ParseError<D>>>>,
+    PushParser<D>: Unpin,
+    S: Unpin,
+{
+    type Output = ParseResult<D>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<ParseResult<D>> {
+        let this = self.get_mut();
+        loop {
+            let token = match (this.source)(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(token))) => Some(token),
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                Poll::Ready(None) => None,
+            };
+            match this.parser.feed(token) {
+                Ok(Step::More) => {}
+                Ok(Step::Done(result)) => return Poll::Ready(Ok(result)),
+                Err(error) => return Poll::Ready(Err(error)),
+            }
+        }
+    }
+}
+
+/// An iterator that parses one value after another from the same
+/// tokens, as with repeated calls to [`Parser::drive_prefix`]. Each
+/// value is yielded as soon as the parser sees the token after it, so
+/// the tokens are read lazily and the values never pile up.
+///
+/// The iteration ends with the input, or after yielding the first
+/// error. Since each value starts where the last one stopped, the
+/// start symbol must not match the empty input; if it does, a token
+/// that cannot start a value is reported as `ExtraToken`.
+pub struct ItemParser<D, I>
+where
+    D: ParserDefinition,
+    I: Iterator<Item = Result<TokenTriple<D>, ParseError<D>>>,
+{
+    definition: D,
+    tokens: I,
+    leftover: Option<TokenTriple<D>>,
+    done: bool,
+}
+
+impl<D, I> ItemParser<D, I>
+where
+    D: ParserDefinition + Clone,
+    I: Iterator<Item = Result<TokenTriple<D>, ParseError<D>>>,
+{
+    pub fn new(definition: D, tokens: I) -> Self {
+        ItemParser {
+            definition,
+            tokens,
+            leftover: None,
+            done: false,
+        }
+    }
+}
+
+impl<D, I> Iterator for ItemParser<D, I>
+where
+    D: ParserDefinition + Clone,
+    I: Iterator<Item = Result<TokenTriple<D>, ParseError<D>>>,
+{
+    type Item = ParseResult<D>;
+
+    fn next(&mut self) -> Option<ParseResult<D>> {
+        if self.done {
+            return None;
+        }
+
+        let first = match self.leftover.take() {
+            Some(token) => Ok(token),
+            None => match self.tokens.next() {
+                Some(token) => token,
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            },
+        };
+
+        let mut read = 0;
+        let tokens = core::iter::once(first)
+            .chain(&mut self.tokens)
+            .inspect(|_| read += 1);
+        let result = match Parser::drive_prefix(self.definition.clone(), tokens) {
+            // stopping at the very first token makes no progress
+            Ok((_, Some(token))) if read == 1 => Err(crate::ParseError::ExtraToken { token }),
+            Ok((value, leftover)) => {
+                self.leftover = leftover;
+                Ok(value)
+            }
+            Err(error) => Err(error),
+        };
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+impl<D> Clone for PushParser<D>
+where
+    D: ParserDefinition + Clone,
+    D::Symbol: Clone,
+{
+    fn clone(&self) -> Self {
+        PushParser {
+            definition: self.definition.clone(),
+            states: self.states.clone(),
+            symbols: self.symbols.clone(),
+            last_location: self.last_location.clone(),
+        }
+    }
+}
+
+/// A parser that keeps its input, and copies of itself taken along the
+/// way, so that the input can be edited and parsed again. A reparse
+/// resumes from the last copy taken before the edit, so the tokens in
+/// front of it are not parsed again.
+///
+/// Taking a copy clones the whole stack, values included, so a copy
+/// before every token would make the first parse quadratic. By default
+/// the copies are instead spaced by the square root of the number of
+/// tokens, which bounds both the number of copies and the tokens parsed
+/// again after an edit by that square root. `checkpoint_interval` fixes
+/// the spacing instead.
+pub struct IncrementalParser<D: ParserDefinition> {
+    tokens: Vec<TokenTriple<D>>,
+    // each copy with the number of tokens it has been fed, in order;
+    // the first has been fed none
+    checkpoints: Vec<(usize, PushParser<D>)>,
+    interval: Option<usize>,
+}
+
+impl<D> IncrementalParser<D>
+where
+    D: ParserDefinition + Clone,
+    D::Symbol: Clone,
+{
+    pub fn new(definition: D) -> Self {
+        IncrementalParser {
+            tokens: vec![],
+            checkpoints: vec![(0, PushParser::new(definition))],
+            interval: None,
+        }
+    }
+
+    /// Takes a copy of the parser before every `interval` tokens,
+    /// instead of spacing the copies by the square root of the number
+    /// of tokens. With an interval of 1, a reparse starts right before
+    /// the edit, but the first parse takes quadratic time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn checkpoint_interval(mut self, interval: usize) -> Self {
+        assert!(interval > 0, "the checkpoint interval must be at least 1");
+        self.checkpoints.truncate(1);
+        self.interval = Some(interval);
+        self
+    }
+
+    /// The tokens that were parsed last.
+    pub fn tokens(&self) -> &[TokenTriple<D>] {
+        &self.tokens
+    }
+
+    /// Parses `tokens`, replacing any previous input.
+    pub fn parse<I>(&mut self, tokens: I) -> ParseResult<D>
+    where
+        I: IntoIterator<Item = TokenTriple<D>>,
+    {
+        self.reparse(0..self.tokens.len(), tokens)
+    }
+
+    /// Replaces the tokens in `range` with `replacement` and parses the
+    /// edited input, starting from the last copy of the parser taken
+    /// before `range.start`. The edit is kept even if parsing fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the current tokens.
+    pub fn reparse<I>(&mut self, range: Range<usize>, replacement: I) -> ParseResult<D>
+    where
+        I: IntoIterator<Item = TokenTriple<D>>,
+    {
+        self.tokens.splice(range.clone(), replacement);
+        let interval = self
+            .interval
+            .unwrap_or_else(|| square_root(self.tokens.len()));
+
+        // the checkpoints only go as far as the last parse got
+        let resume = self
+            .checkpoints
+            .iter()
+            .rposition(|(fed, _)| *fed <= range.start)
+            .unwrap();
+        self.checkpoints.truncate(resume + 1);
+        let (start, parser) = &self.checkpoints[resume];
+        let (start, mut last, mut parser) = (*start, *start, parser.clone());
+        for index in start..self.tokens.len() {
+            if index >= last + interval {
+                self.checkpoints.push((index, parser.clone()));
+                last = index;
+            }
+            parser.feed(Some(self.tokens[index].clone()))?;
+        }
+        match parser.feed(None)? {
+            Step::Done(result) => Ok(result),
+            Step::More => unreachable!("the end of the input was not accepted"),
+        }
+    }
+}
+
+/// The integer square root of `n`, but at least 1.
+fn square_root(n: usize) -> usize {
+    let mut root = 1;
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    root
+}
+
+/// An event reported by an [`EventParser`].
+pub enum Event<D: ParserDefinition> {
+    /// A token was shifted onto the stack.
+    Shift(TokenTriple<D>),
+
+    /// The production with the given index was reduced, popping the
+    /// given number of children off the stack.
+    Reduce(D::ReduceIndex, usize),
+}
+
+/// A parser that runs the state machine without executing any action
+/// code. Instead, each shift and reduce is reported to a sink as an
+/// [`Event`], leaving it to the caller to build whatever structure it
+/// likes (e.g., a concrete syntax tree).
+///
+/// The reduction of the start symbol is not reported. Error recovery
+/// is not supported.
+pub struct EventParser<D: ParserDefinition> {
+    definition: D,
+    states: Vec<D::StateIndex>,
+    last_location: D::Location,
+}
+
+impl<D: ParserDefinition> EventParser<D> {
+    pub fn new(definition: D) -> Self {
+        let last_location = definition.start_location();
+        let mut states = Vec::with_capacity(definition.stack_capacity() + 1);
+        states.push(definition.start_state());
+        EventParser {
+            definition,
+            states,
+            last_location,
+        }
+    }
+
+    /// Parse `tokens`, passing every shift and reduce to `sink` in
+    /// the order in which they happen.
+    pub fn parse<I, F>(mut self, tokens: I, mut sink: F) -> Result<(), ParseError<D>>
+    where
+        I: IntoIterator<Item = TokenTriple<D>>,
+        F: FnMut(Event<D>),
+    {
+        for lookahead in tokens {
+            debug!("+ EVENT TOKEN: {:?}", lookahead);
+
+            self.last_location = lookahead.2.clone();
+            let token_index = match self.definition.token_to_index(&lookahead.1) {
+                Some(i) => i,
+                None => return Err(self.unrecognized_token_error(Some(lookahead))),
+            };
+
+            loop {
+                let top_state = *self.states.last().unwrap();
+                let action = self
+                    .definition
+                    .lookahead_action(top_state, Some((token_index, &lookahead.1)));
+                if let Some(target_state) = action.as_shift() {
+                    self.states.push(target_state);
+                    sink(Event::Shift(lookahead));
+                    break;
+                } else if let Some(reduce_index) = action.as_reduce() {
+                    if !self.reduce(reduce_index, &mut sink) {
+                        return Err(crate::ParseError::ExtraToken { token: lookahead });
+                    }
+                } else {
+                    return Err(self.unrecognized_token_error(Some(lookahead)));
+                }
+            }
+        }
+
+        loop {
+            let top_state = *self.states.last().unwrap();
+            let action = self.definition.lookahead_action(top_state, None);
+            if let Some(reduce_index) = action.as_reduce() {
+                if !self.reduce(reduce_index, &mut sink) {
+                    return Ok(());
+                }
+            } else {
+                return Err(self.unrecognized_token_error(None));
+            }
+        }
+    }
+
+    /// Applies the reduction to the state stack and reports it.
+    /// Returns false if this was the final, accepting reduction.
+    fn reduce<F>(&mut self, reduce_index: D::ReduceIndex, sink: &mut F) -> bool
+    where
+        F: FnMut(Event<D>),
+    {
+        match self.definition.simulate_reduce(reduce_index) {
+            SimulatedReduce::Reduce {
+                states_to_pop,
+                nonterminal_produced,
+            } => {
+                let len = self.states.len() - states_to_pop;
+                self.states.truncate(len);
+                let top_state = *self.states.last().unwrap();
+                let next_state = self.definition.goto(top_state, nonterminal_produced);
+                self.states.push(next_state);
+                sink(Event::Reduce(reduce_index, states_to_pop));
+                true
+            }
+            SimulatedReduce::Accept => false,
+        }
+    }
+
+    fn unrecognized_token_error(&self, token: Option<TokenTriple<D>>) -> ParseError<D> {
+        let expected = self.definition.expected_tokens_from_states(&self.states);
+        match token {
+            Some(token) => crate::ParseError::UnrecognizedToken { token, expected },
+            None => crate::ParseError::UnrecognizedEof {
+                location: self.last_location.clone(),
+                expected,
+            },
+        }
+    }
+}
+
 /// In LALRPOP generated rules, we actually use `i32`, `i16`, or `i8`
 /// to represent all of the various indices (we use the smallest one
 /// that will fit). So implement `ParserAction` for each of those.
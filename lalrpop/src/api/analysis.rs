@@ -0,0 +1,106 @@
+//! The results of analyzing a grammar, for tools such as linters or
+//! editor integrations that want to know about the grammar rather than
+//! about its parser. See `Configuration::analyze_grammar`.
+//!
+//! Symbols and lookahead tokens are spelled as in the grammar, e.g.
+//! `"+"` or `Expr`, and the end of the input is `Eof`, as in the report
+//! file.
+
+use crate::api::automaton::Production;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A grammar as seen from one start symbol. Like `Automaton`, it owns
+/// all of its data, so it can outlive the grammar it was built from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grammar {
+    pub(crate) terminals: Vec<String>,
+    pub(crate) nonterminals: Vec<String>,
+    pub(crate) productions: Vec<Production>,
+    pub(crate) nullable: BTreeSet<String>,
+    pub(crate) follow: BTreeMap<String, Vec<String>>,
+}
+
+impl Grammar {
+    /// Every terminal the grammar recognizes, e.g. its keywords, in the
+    /// order in which they are numbered in the generated tables.
+    pub fn terminals(&self) -> &[String] {
+        &self.terminals
+    }
+
+    /// Every nonterminal of the grammar, in sorted order. This includes
+    /// the artificial start symbols, such as `__Expr`, that wrap the
+    /// public nonterminals and the one the grammar is analyzed from.
+    pub fn nonterminals(&self) -> &[String] {
+        &self.nonterminals
+    }
+
+    /// All the productions of the grammar, grouped by nonterminal, in
+    /// the same order as `Automaton::productions`.
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    /// The nonterminals that can derive the empty input, directly or
+    /// through other nullable nonterminals. These often cause
+    /// conflicts, e.g. when two of them come next to each other.
+    pub fn nullable(&self) -> &BTreeSet<String> {
+        &self.nullable
+    }
+
+    /// The FOLLOW set of `nonterminal`: the terminals that can come
+    /// right after it in some input, and `Eof` if it can end the input.
+    /// `None` if the grammar has no such nonterminal.
+    pub fn follow(&self, nonterminal: &str) -> Option<&[String]> {
+        self.follow.get(nonterminal).map(|tokens| &tokens[..])
+    }
+}
+
+/// A visitor over the productions of a `Grammar` and the symbols in
+/// them, for analyses such as counting how often each terminal is used.
+/// Implementors override the methods for the parts they are interested
+/// in; the default methods walk down into the rest.
+pub trait GrammarVisitor {
+    fn visit_production(&mut self, grammar: &Grammar, production: &Production) {
+        walk_production(self, grammar, production)
+    }
+
+    fn visit_symbol(&mut self, grammar: &Grammar, symbol: &str) {
+        walk_symbol(self, grammar, symbol)
+    }
+
+    /// Called for each use of a terminal in a production.
+    fn visit_terminal(&mut self, _terminal: &str) {}
+
+    /// Called for each use of a nonterminal in a production.
+    fn visit_nonterminal(&mut self, _nonterminal: &str) {}
+}
+
+/// Visits every production of `grammar`, and through the default
+/// methods, each of the symbols in them.
+pub fn walk_grammar<V: GrammarVisitor + ?Sized>(grammar: &Grammar, visitor: &mut V) {
+    for production in &grammar.productions {
+        visitor.visit_production(grammar, production);
+    }
+}
+
+pub fn walk_production<V: GrammarVisitor + ?Sized>(
+    visitor: &mut V,
+    grammar: &Grammar,
+    production: &Production,
+) {
+    for symbol in production.symbols() {
+        visitor.visit_symbol(grammar, symbol);
+    }
+}
+
+pub fn walk_symbol<V: GrammarVisitor + ?Sized>(visitor: &mut V, grammar: &Grammar, symbol: &str) {
+    if grammar
+        .nonterminals
+        .iter()
+        .any(|nonterminal| nonterminal == symbol)
+    {
+        visitor.visit_nonterminal(symbol);
+    } else {
+        visitor.visit_terminal(symbol);
+    }
+}
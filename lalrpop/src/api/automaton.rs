@@ -0,0 +1,179 @@
+//! A read-only view of the LR(1) states of a grammar, for tools that
+//! walk the automaton, e.g. to explain a conflict. See
+//! `Configuration::build_automaton`.
+//!
+//! Symbols and lookahead tokens are spelled as in the grammar, e.g.
+//! `"+"` or `Expr`, and the end of the input is `Eof`, as in the report
+//! file.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The states of the parser of one start symbol. It owns all of its
+/// data, referring to productions by index, so it can outlive the
+/// grammar, be sent to another thread or be kept in a cache.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Automaton {
+    pub(crate) productions: Vec<Production>,
+    pub(crate) states: Vec<State>,
+}
+
+impl Automaton {
+    /// All the productions of the grammar, which items and reductions
+    /// refer to by their index in this slice.
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    /// The states, each at its own `index`. The parser starts in state 0.
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    /// Runs the parser over `tokens`, terminals spelled as in the
+    /// grammar, and returns the index of the state it is in afterwards,
+    /// or `None` if the tokens are not a prefix of any input the parser
+    /// accepts. Reductions are made as the next token calls for them, so
+    /// the result is the state right after the last shift; its shifts
+    /// and reduction lookaheads are the terminals that can come next,
+    /// e.g. to offer as completions.
+    pub fn simulate(&self, tokens: &[&str]) -> Option<usize> {
+        let mut stack = vec![0];
+        for &terminal in tokens {
+            loop {
+                let state = &self.states[*stack.last()?];
+                if let Some(&next) = state.shifts.get(terminal) {
+                    stack.push(next);
+                    break;
+                }
+                let reduction = state
+                    .reductions
+                    .iter()
+                    .find(|reduction| reduction.lookahead.iter().any(|token| token == terminal))?;
+                let production = &self.productions[reduction.production];
+                stack.truncate(stack.len().checked_sub(production.symbols.len())?);
+                let next = self.states[*stack.last()?]
+                    .gotos
+                    .get(&production.nonterminal)?;
+                stack.push(*next);
+            }
+        }
+        stack.last().copied()
+    }
+}
+
+/// A production of the grammar, e.g. `Expr = Expr "+" Term`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Production {
+    pub(crate) nonterminal: String,
+    pub(crate) symbols: Vec<String>,
+}
+
+impl Production {
+    pub fn nonterminal(&self) -> &str {
+        &self.nonterminal
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+}
+
+impl fmt::Display for Production {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} =", self.nonterminal)?;
+        for symbol in &self.symbols {
+            write!(fmt, " {}", symbol)?;
+        }
+        Ok(())
+    }
+}
+
+/// An LR(1) item: a production, how much of it has been recognized,
+/// and the tokens that may follow it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub(crate) production: usize,
+    pub(crate) index: usize,
+    pub(crate) lookahead: Vec<String>,
+}
+
+impl Item {
+    /// The index of the production in `Automaton::productions`.
+    pub fn production(&self) -> usize {
+        self.production
+    }
+
+    /// The position of the dot, which comes before the symbol at this
+    /// index: 1 for `X = A • B C`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn lookahead(&self) -> &[String] {
+        &self.lookahead
+    }
+}
+
+/// A production that a state reduces on the given lookahead tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reduction {
+    pub(crate) production: usize,
+    pub(crate) lookahead: Vec<String>,
+}
+
+impl Reduction {
+    /// The index of the production in `Automaton::productions`.
+    pub fn production(&self) -> usize {
+        self.production
+    }
+
+    pub fn lookahead(&self) -> &[String] {
+        &self.lookahead
+    }
+}
+
+/// A state of the parser, with its items and its actions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub(crate) index: usize,
+    // the kernel items come first, then the items they expand to
+    pub(crate) items: Vec<Item>,
+    pub(crate) kernel_len: usize,
+    pub(crate) shifts: BTreeMap<String, usize>,
+    pub(crate) gotos: BTreeMap<String, usize>,
+    pub(crate) reductions: Vec<Reduction>,
+}
+
+impl State {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// All the items of the state, starting with the kernel.
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// The kernel of the state: the items that are not the result of an
+    /// epsilon move from another item in the state. For every state
+    /// other than the start state, these are exactly the items where
+    /// something has been shifted.
+    pub fn kernel_items(&self) -> &[Item] {
+        &self.items[..self.kernel_len]
+    }
+
+    /// The state reached by shifting each terminal.
+    pub fn shift_entries(&self) -> &BTreeMap<String, usize> {
+        &self.shifts
+    }
+
+    /// The state reached after reducing each nonterminal.
+    pub fn goto_entries(&self) -> &BTreeMap<String, usize> {
+        &self.gotos
+    }
+
+    pub fn reduction_entries(&self) -> &[Reduction] {
+        &self.reductions
+    }
+}
@@ -0,0 +1,109 @@
+//! The hook through which a build script can resolve the conflicts of
+//! a grammar itself, see `Configuration::set_conflict_resolver`.
+
+use std::fmt;
+
+/// A state of the parser in which more than one action is possible on
+/// the same lookahead token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The index of the state, as in the report file.
+    pub state: usize,
+
+    /// The lookahead token, spelled as in the grammar, e.g. `"+"`.
+    pub lookahead: String,
+
+    /// Whether the lookahead token can be shifted.
+    pub shift: bool,
+
+    /// The productions that can be reduced, e.g. `Expr = Expr "+" Expr`,
+    /// ordered by the name of their nonterminal and then in the order in
+    /// which the grammar declares them.
+    pub productions: Vec<String>,
+}
+
+/// Identifies one of the `productions` of a `Conflict`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProductionIndex(pub usize);
+
+/// What to do about a `Conflict`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Shift the lookahead token, and drop the reductions. The grammar
+    /// fails to build if the conflict has no shift.
+    Shift,
+
+    /// Reduce the given production, and drop the other actions. The
+    /// grammar fails to build if the conflict has no such production.
+    Reduce(ProductionIndex),
+
+    /// Drop every action, so that the lookahead token is a syntax
+    /// error in this state (like a non-associative operator).
+    Error,
+
+    /// Leave the conflict as it is, so that it is reported and the
+    /// grammar fails to build.
+    Report,
+}
+
+/// Decides how the conflicts of a grammar are resolved.
+///
+/// The resolver is consulted once for each state and lookahead token
+/// with a conflict, after the grammar's precedence and associativity
+/// annotations have been applied. Grammars with `#[expect_conflicts]`,
+/// `#[disambiguate]` or `#[backtrack]` handle their conflicts
+/// themselves and never reach the resolver.
+///
+/// ```
+/// use lalrpop::{Conflict, ConflictResolver, ProductionIndex, Resolution};
+///
+/// /// Resolves every conflict in favor of shifting, as yacc does.
+/// struct PreferShift;
+///
+/// impl ConflictResolver for PreferShift {
+///     fn resolve(&self, conflict: &Conflict) -> Resolution {
+///         if conflict.shift {
+///             Resolution::Shift
+///         } else {
+///             Resolution::Reduce(ProductionIndex(0))
+///         }
+///     }
+/// }
+///
+/// lalrpop::Configuration::new().set_conflict_resolver(PreferShift);
+/// ```
+pub trait ConflictResolver {
+    fn resolve(&self, conflict: &Conflict) -> Resolution;
+}
+
+/// The default resolver, which reports every conflict.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ReportEverything;
+
+impl ConflictResolver for ReportEverything {
+    fn resolve(&self, _: &Conflict) -> Resolution {
+        Resolution::Report
+    }
+}
+
+/// Renders the conflict for the log, for example: state 4, on
+/// lookahead `"+"`: shift or reduce by `Expr = Expr "+" Expr`.
+impl fmt::Display for Conflict {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "state {}, on lookahead `{}`: ",
+            self.state, self.lookahead
+        )?;
+        if self.shift {
+            write!(fmt, "shift or ")?;
+        }
+        for (i, production) in self.productions.iter().enumerate() {
+            if i > 0 {
+                write!(fmt, " or ")?;
+            }
+            write!(fmt, "reduce by `{}`", production)?;
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,19 @@ use std::env::current_dir;
 use std::error::Error;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
+
+pub mod analysis;
+pub mod automaton;
+mod conflict;
+#[cfg(test)]
+mod test;
+
+use self::analysis::Grammar;
+use self::automaton::Automaton;
+pub use self::conflict::{
+    Conflict, ConflictResolver, ProductionIndex, ReportEverything, Resolution,
+};
 
 /// Configure various aspects of how LALRPOP works.
 /// Intended for use within a `build.rs` script.
@@ -114,6 +127,9 @@ impl Configuration {
 
     /// If true, emit comments into the generated code. This makes the
     /// generated code significantly larger. Default is false.
+    ///
+    /// The comment on each state lists its items, each followed by the
+    /// `///` comment written above its rule in the grammar, if any.
     pub fn emit_comments(&mut self, val: bool) -> &mut Configuration {
         self.session.emit_comments = val;
         self
@@ -132,6 +148,22 @@ impl Configuration {
         self
     }
 
+    /// If true, emit a `.report.json` file next to the generated code,
+    /// describing the states and conflicts of the parser of each public
+    /// nonterminal in a form that other tools can read.
+    pub fn emit_json_report(&mut self, val: bool) -> &mut Configuration {
+        self.session.emit_json_report = val;
+        self
+    }
+
+    /// If true, emit a `.snapshot` file next to the generated code,
+    /// listing the states of the parser as plain text. Checking it in
+    /// makes any change to the parse table show up as a diff.
+    pub fn emit_snapshot(&mut self, val: bool) -> &mut Configuration {
+        self.session.emit_snapshot = val;
+        self
+    }
+
     /// Minimal logs: only for errors that halt progress.
     pub fn log_quiet(&mut self) -> &mut Configuration {
         self.session.log.set_level(Level::Taciturn);
@@ -158,6 +190,44 @@ impl Configuration {
         self
     }
 
+    /// Sets the resolver that decides how the conflicts of the
+    /// grammars are resolved, instead of reporting them as errors. See
+    /// `ConflictResolver`. The resolver must be `Send` and `Sync`, like
+    /// the rest of the configuration.
+    pub fn set_conflict_resolver<R>(&mut self, resolver: R) -> &mut Configuration
+    where
+        R: ConflictResolver + Send + Sync + 'static,
+    {
+        self.session.conflict_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Sets a callback that `build_automaton` and `check_lr1` call with
+    /// the index of each state they construct and the number of
+    /// conflicts found so far, e.g. to show progress on a large
+    /// grammar. If it returns `false`, construction stops and they
+    /// return an error. `process` does not call it.
+    pub fn set_progress_callback<F>(&mut self, callback: F) -> &mut Configuration
+    where
+        F: Fn(usize, usize) -> bool + Send + Sync + 'static,
+    {
+        self.session.progress_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets a callback that `build_automaton` and `check_lr1` show each
+    /// state as soon as it is complete, before the next one is
+    /// constructed, e.g. to draw the automaton while it grows. Its
+    /// items and reductions refer to productions by their index in
+    /// `Automaton::productions`. `process` does not call it.
+    pub fn set_state_observer<F>(&mut self, observer: F) -> &mut Configuration
+    where
+        F: Fn(&automaton::State) + Send + Sync + 'static,
+    {
+        self.session.state_observer = Some(Arc::new(observer));
+        self
+    }
+
     /// Sets the features used during compilation, disables the use of cargo features.
     /// (Default: Loaded from `CARGO_FEATURE_{}` environment variables).
     pub fn set_features<I>(&mut self, iterable: I) -> &mut Configuration
@@ -176,6 +246,52 @@ impl Configuration {
         self
     }
 
+    /// Builds the LR(1) states of the nonterminal `start` of the given
+    /// `.lalrpop` file, for a tool to inspect; no code is generated.
+    /// These are the states of the canonical LR(1) automaton, which may
+    /// be more than the generated parser has, and they are built even
+    /// if the grammar has conflicts. `start` need not be public: it is
+    /// wrapped in a production of its own, so that there is a single
+    /// accept state, as for the public nonterminals.
+    pub fn build_automaton<P: AsRef<Path>>(
+        &self,
+        path: P,
+        start: &str,
+    ) -> Result<Automaton, Box<dyn Error>> {
+        let session = Rc::new(self.session.clone());
+        build::build_automaton(session, path.as_ref(), start)
+    }
+
+    /// Checks whether the nonterminal `start` of the given `.lalrpop`
+    /// file is LR(1), returning the conflicts of its canonical LR(1)
+    /// automaton, one for each state and lookahead token, as a
+    /// `ConflictResolver` would see them. There are none if the grammar
+    /// is LR(1). No code is generated, and the states are dropped as
+    /// soon as their conflicts are known, so this takes much less memory
+    /// than `build_automaton` for a large grammar.
+    pub fn check_lr1<P: AsRef<Path>>(
+        &self,
+        path: P,
+        start: &str,
+    ) -> Result<Vec<Conflict>, Box<dyn Error>> {
+        let session = Rc::new(self.session.clone());
+        build::check_lr1(session, path.as_ref(), start)
+    }
+
+    /// Analyzes the given `.lalrpop` file as seen from its nonterminal
+    /// `start`, for a tool that wants to know about the grammar rather
+    /// than about its parser; no code is generated. As for
+    /// `build_automaton`, `start` need not be public, and it is wrapped
+    /// in a production of its own, so that `Eof` follows it.
+    pub fn analyze_grammar<P: AsRef<Path>>(
+        &self,
+        path: P,
+        start: &str,
+    ) -> Result<Grammar, Box<dyn Error>> {
+        let session = Rc::new(self.session.clone());
+        build::analyze_grammar(session, path.as_ref(), start)
+    }
+
     /// Process all files according to the `set_in_dir` and
     /// `set_out_dir` configuration.
     pub fn process(&self) -> Result<(), Box<dyn Error>> {
@@ -0,0 +1,429 @@
+use super::Configuration;
+
+/// Build scripts may hand the configuration to other threads, and a
+/// conflict resolver or a callback must not prevent that.
+#[test]
+fn configuration_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let mut config = Configuration::new();
+    config.set_conflict_resolver(super::ReportEverything);
+    config.set_progress_callback(|_, _| true);
+    config.set_state_observer(|_| ());
+    assert_send_sync(&config);
+}
+
+/// Writes `grammar` to a file of its own and builds the states of `start`.
+fn build_automaton(name: &str, grammar: &str, start: &str) -> super::Automaton {
+    let path = std::env::temp_dir().join(format!("lalrpop-{}.lalrpop", name));
+    std::fs::write(&path, grammar).unwrap();
+    let automaton = Configuration::new().build_automaton(&path, start);
+    std::fs::remove_file(&path).unwrap();
+    automaton.unwrap()
+}
+
+/// Renders an item as in the report file, e.g. `E = E • "+" T ["+", Eof]`.
+fn show_item(automaton: &super::Automaton, item: &super::automaton::Item) -> String {
+    let production = &automaton.productions()[item.production()];
+    let mut symbols = production.symbols().to_vec();
+    symbols.insert(item.index(), "•".to_string());
+    format!(
+        "{} = {} [{}]",
+        production.nonterminal(),
+        symbols.join(" "),
+        item.lookahead().join(", ")
+    )
+}
+
+#[test]
+fn automaton_kernels() {
+    let automaton = build_automaton(
+        "automaton-kernels",
+        r#"
+grammar;
+pub E: () = { E "+" T => (), T => () };
+T: () = "x" => ();
+"#,
+        "E",
+    );
+    let states = automaton.states();
+    let kernels: Vec<Vec<String>> = states
+        .iter()
+        .map(|state| {
+            state
+                .kernel_items()
+                .iter()
+                .map(|item| show_item(&automaton, item))
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        kernels,
+        vec![
+            vec![r#"__E = • E [Eof]"#],
+            vec![r#"E = E • "+" T ["+", Eof]"#, r#"__E = E • [Eof]"#],
+            vec![r#"E = T • ["+", Eof]"#],
+            vec![r#"T = "x" • ["+", Eof]"#],
+            vec![r#"E = E "+" • T ["+", Eof]"#],
+            vec![r#"E = E "+" T • ["+", Eof]"#],
+        ]
+    );
+
+    // the kernel comes first among the items of a state
+    let items: Vec<_> = states[0]
+        .items()
+        .iter()
+        .map(|item| show_item(&automaton, item))
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            r#"__E = • E [Eof]"#,
+            r#"E = • E "+" T ["+", Eof]"#,
+            r#"E = • T ["+", Eof]"#,
+            r#"T = • "x" ["+", Eof]"#,
+        ]
+    );
+    assert_eq!(states[0].shift_entries()[r#""x""#], 3);
+    assert_eq!(states[0].goto_entries()["E"], 1);
+    assert_eq!(states[0].goto_entries()["T"], 2);
+    let reduction = &states[5].reduction_entries()[0];
+    assert_eq!(
+        automaton.productions()[reduction.production()].to_string(),
+        r#"E = E "+" T"#
+    );
+    assert_eq!(reduction.lookahead(), [r#""+""#, "Eof"]);
+}
+
+#[test]
+fn automaton_is_owned() {
+    let automaton = build_automaton(
+        "automaton-is-owned",
+        r#"
+grammar;
+pub S: () = { S "," "x" => (), "x" => () };
+"#,
+        "S",
+    );
+
+    // nothing borrows the grammar, so the states can be moved to
+    // another thread
+    let (automaton, reductions) = std::thread::spawn(move || {
+        let reductions: usize = automaton
+            .states()
+            .iter()
+            .map(|state| state.reduction_entries().len())
+            .sum();
+        (automaton, reductions)
+    })
+    .join()
+    .unwrap();
+    assert_eq!(reductions, 3);
+    assert_eq!(automaton.states().len(), 5);
+}
+
+#[test]
+fn automaton_simulate() {
+    let automaton = build_automaton(
+        "automaton-simulate",
+        r#"
+grammar;
+pub E: () = { E "+" T => (), T => () };
+T: () = { "X" => (), "(" E ")" => () };
+"#,
+        "E",
+    );
+
+    // the terminals that can follow a prefix
+    let next = |tokens: &[&str]| -> Option<Vec<String>> {
+        let state = &automaton.states()[automaton.simulate(tokens)?];
+        let mut next: Vec<_> = state
+            .shift_entries()
+            .keys()
+            .cloned()
+            .chain(
+                state
+                    .reduction_entries()
+                    .iter()
+                    .flat_map(|reduction| reduction.lookahead().iter().cloned()),
+            )
+            .collect();
+        next.sort();
+        next.dedup();
+        Some(next)
+    };
+
+    assert_eq!(next(&[]).unwrap(), [r#""(""#, r#""X""#]);
+    assert_eq!(next(&[r#""(""#, r#""X""#]).unwrap(), [r#"")""#, r#""+""#]);
+    assert_eq!(
+        next(&[r#""X""#, r#""+""#, r#""X""#]).unwrap(),
+        [r#""+""#, "Eof"]
+    );
+    assert_eq!(
+        next(&[r#""(""#, r#""X""#, r#"")""#, r#""+""#]).unwrap(),
+        [r#""(""#, r#""X""#]
+    );
+    assert_eq!(next(&[r#""X""#, r#""+""#, r#""X""#, r#"")""#]), None);
+    assert_eq!(next(&[r#""X""#, r#"")""#]), None);
+    assert_eq!(next(&[r#""+""#]), None);
+}
+
+/// A nonterminal that is not public gets a start symbol of its own.
+#[test]
+fn automaton_of_private_nonterminal() {
+    let automaton = build_automaton(
+        "automaton-of-private-nonterminal",
+        r#"
+grammar;
+pub E: () = { E "+" T => (), T => () };
+T: () = { "x" => (), "(" E ")" => () };
+"#,
+        "T",
+    );
+    let states = automaton.states();
+    let start: Vec<_> = states[0]
+        .kernel_items()
+        .iter()
+        .map(|item| show_item(&automaton, item))
+        .collect();
+    assert_eq!(start, [r#"__T = • T [Eof]"#]);
+
+    // only the new production accepts, at the end of the input
+    let accept: Vec<_> = states
+        .iter()
+        .filter(|state| {
+            state.reduction_entries().iter().any(|reduction| {
+                automaton.productions()[reduction.production()].nonterminal() == "__T"
+            })
+        })
+        .map(|state| state.index())
+        .collect();
+    assert_eq!(accept, [states[0].goto_entries()["T"]]);
+    let state = automaton.simulate(&[r#""(""#, r#""x""#, r#"")""#]).unwrap();
+    let kernel: Vec<_> = states[state]
+        .kernel_items()
+        .iter()
+        .map(|item| show_item(&automaton, item))
+        .collect();
+    assert_eq!(kernel, [r#"T = "(" E ")" • [Eof]"#]);
+}
+
+#[test]
+fn check_lr1() {
+    let check = |name, grammar| {
+        let path = std::env::temp_dir().join(format!("lalrpop-{}.lalrpop", name));
+        std::fs::write(&path, grammar).unwrap();
+        let conflicts = Configuration::new().check_lr1(&path, "E");
+        std::fs::remove_file(&path).unwrap();
+        conflicts.unwrap()
+    };
+
+    let conflicts = check(
+        "check-lr1-ambiguous",
+        r#"
+grammar;
+pub E: () = { E "+" E => (), "x" => () };
+"#,
+    );
+    let conflicts: Vec<_> = conflicts
+        .iter()
+        .map(|conflict| {
+            format!(
+                "{} {} {:?}",
+                conflict.lookahead, conflict.shift, conflict.productions
+            )
+        })
+        .collect();
+    assert_eq!(conflicts, [r#""+" true ["E = E \"+\" E"]"#]);
+
+    let conflicts = check(
+        "check-lr1-unambiguous",
+        r#"
+grammar;
+pub E: () = { E "+" "x" => (), "x" => () };
+"#,
+    );
+    assert_eq!(conflicts, []);
+}
+
+#[test]
+fn progress_callback() {
+    use std::sync::{Arc, Mutex};
+
+    let path = std::env::temp_dir().join("lalrpop-progress-callback.lalrpop");
+    std::fs::write(
+        &path,
+        r#"
+grammar;
+pub E: () = { E "+" E => (), "x" => () };
+"#,
+    )
+    .unwrap();
+
+    // every state is reported, in order, with the conflicts so far
+    let seen = Arc::new(Mutex::new(vec![]));
+    let mut config = Configuration::new();
+    let record = seen.clone();
+    config.set_progress_callback(move |index, conflicts| {
+        record.lock().unwrap().push((index, conflicts));
+        true
+    });
+    let automaton = config.build_automaton(&path, "E").unwrap();
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen.len(), automaton.states().len());
+    assert!(seen.iter().enumerate().all(|(i, &(index, _))| i == index));
+    assert_eq!(seen.last().unwrap().1, 1);
+
+    // returning `false` cancels construction
+    let mut config = Configuration::new();
+    config.set_progress_callback(|index, _| index < 2);
+    let automaton = config.build_automaton(&path, "E");
+    let conflicts = config.check_lr1(&path, "E");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        automaton.unwrap_err().to_string(),
+        "state construction was cancelled"
+    );
+    assert_eq!(
+        conflicts.unwrap_err().to_string(),
+        "state construction was cancelled"
+    );
+}
+
+#[test]
+fn state_observer() {
+    use std::sync::{Arc, Mutex};
+
+    let path = std::env::temp_dir().join("lalrpop-state-observer.lalrpop");
+    std::fs::write(
+        &path,
+        r#"
+grammar;
+pub E: () = { E "+" T => (), T => () };
+T: () = "x" => ();
+"#,
+    )
+    .unwrap();
+
+    // the states are shown as they are built, complete
+    let seen = Arc::new(Mutex::new(vec![]));
+    let mut config = Configuration::new();
+    let record = seen.clone();
+    config.set_state_observer(move |state| record.lock().unwrap().push(state.clone()));
+    let automaton = config.build_automaton(&path, "E").unwrap();
+    assert_eq!(*seen.lock().unwrap(), automaton.states());
+
+    // `check_lr1` shows them too, without keeping them
+    seen.lock().unwrap().clear();
+    let conflicts = config.check_lr1(&path, "E").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(conflicts.is_empty());
+    assert_eq!(*seen.lock().unwrap(), automaton.states());
+}
+
+/// Writes `grammar` to a file of its own and analyzes it from `start`.
+fn analyze_grammar(name: &str, grammar: &str, start: &str) -> super::analysis::Grammar {
+    let path = std::env::temp_dir().join(format!("lalrpop-{}.lalrpop", name));
+    std::fs::write(&path, grammar).unwrap();
+    let analysis = Configuration::new().analyze_grammar(&path, start);
+    std::fs::remove_file(&path).unwrap();
+    analysis.unwrap()
+}
+
+/// The textbook expression grammar, as in the dragon book.
+const EXPRESSIONS: &str = r#"
+grammar;
+pub E: () = { E "+" T => (), T => () };
+T: () = { T "*" F => (), F => () };
+F: () = { "(" E ")" => (), "id" => () };
+"#;
+
+#[test]
+fn vocabulary() {
+    let analysis = analyze_grammar("vocabulary", EXPRESSIONS, "T");
+    assert_eq!(
+        analysis.terminals(),
+        [r#""(""#, r#"")""#, r#""*""#, r#""+""#, r#""id""#]
+    );
+    assert_eq!(analysis.nonterminals(), ["E", "F", "T", "__E", "__T"]);
+}
+
+#[test]
+fn nullable() {
+    let analysis = analyze_grammar(
+        "nullable",
+        r#"
+grammar;
+pub S: () = A "x" => ();
+A: () = { B C => (), "a" => () };
+B: () = { C => (), "b" => () };
+C: () = { => (), "c" => () };
+"#,
+        "S",
+    );
+    let nullable: Vec<_> = analysis.nullable().iter().collect();
+    assert_eq!(nullable, ["A", "B", "C"]);
+}
+
+#[test]
+fn follow_sets() {
+    let analysis = analyze_grammar("follow-sets", EXPRESSIONS, "E");
+    assert_eq!(analysis.follow("E").unwrap(), [r#"")""#, r#""+""#, "Eof"]);
+    assert_eq!(
+        analysis.follow("T").unwrap(),
+        [r#"")""#, r#""*""#, r#""+""#, "Eof"]
+    );
+    assert_eq!(analysis.follow("F"), analysis.follow("T"));
+    assert_eq!(analysis.follow("G"), None);
+
+    // from `T`, the end of the input can only come after a `T`, not
+    // after an `E`
+    let analysis = analyze_grammar("follow-sets-t", EXPRESSIONS, "T");
+    assert_eq!(analysis.follow("E").unwrap(), [r#"")""#, r#""+""#]);
+    assert_eq!(
+        analysis.follow("T").unwrap(),
+        [r#"")""#, r#""*""#, r#""+""#, "Eof"]
+    );
+}
+
+#[test]
+fn grammar_visitor() {
+    use super::analysis::{walk_grammar, walk_production, Grammar, GrammarVisitor};
+    use super::automaton::Production;
+    use std::collections::BTreeMap;
+
+    /// Counts the uses of each symbol, in the productions of `L` only.
+    #[derive(Default)]
+    struct Uses(BTreeMap<String, usize>);
+
+    impl GrammarVisitor for Uses {
+        fn visit_production(&mut self, grammar: &Grammar, production: &Production) {
+            if production.nonterminal() == "L" {
+                walk_production(self, grammar, production);
+            }
+        }
+
+        fn visit_terminal(&mut self, terminal: &str) {
+            *self.0.entry(terminal.to_string()).or_default() += 1;
+        }
+
+        fn visit_nonterminal(&mut self, nonterminal: &str) {
+            *self.0.entry(format!("<{}>", nonterminal)).or_default() += 1;
+        }
+    }
+
+    let analysis = analyze_grammar(
+        "grammar-visitor",
+        r#"
+grammar;
+pub L: () = { L "," I => (), I "x" => () };
+I: () = { "x" => (), "(" L ")" => () };
+"#,
+        "L",
+    );
+    assert_eq!(analysis.productions().len(), 5);
+    let mut uses = Uses::default();
+    walk_grammar(&analysis, &mut uses);
+    let uses: Vec<_> = uses.0.iter().map(|(s, n)| format!("{} {}", s, n)).collect();
+    assert_eq!(uses, [r#""," 1"#, r#""x" 1"#, "<I> 2", "<L> 1"]);
+}
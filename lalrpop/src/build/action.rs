@@ -30,8 +30,10 @@
 //! * -- in this case, those "user-declared" parameters are inserted by
 //! the "internal tokenizer".
 
+use crate::grammar::free_variables::FreeVariables;
 use crate::grammar::repr as r;
 use crate::rust::RustWrite;
+use crate::util::Sep;
 use std::io::{self, Write};
 
 pub fn emit_action_code<W: Write>(grammar: &r::Grammar, rust: &mut RustWrite<W>) -> io::Result<()> {
@@ -85,20 +87,80 @@ fn emit_user_action_code<W: Write>(
 ) -> io::Result<()> {
     let ret_type = ret_type_string(grammar, defn);
 
+    // With `#[action_trait]`, the code goes into the `Actions` trait
+    // instead, and this fn just passes its arguments along.
+    if let Some(ref actions) = grammar.algorithm.action_trait {
+        let arguments = action_arguments(grammar, data, |i| format!("{}{}", grammar.prefix, i));
+        let names: Vec<String> = grammar
+            .parameters
+            .iter()
+            .filter(|parameter| parameter.name != *actions)
+            .map(|parameter| parameter.name.to_string())
+            .chain(
+                arguments
+                    .iter()
+                    .map(|argument| argument.split(':').next().unwrap().to_string()),
+            )
+            .collect();
+
+        rust!(rust, "#[allow(clippy::too_many_arguments, clippy::needless_lifetimes, clippy::just_underscores_and_digits)]");
+        rust.fn_header(
+            &r::Visibility::Priv,
+            format!("{}action{}", grammar.prefix, index),
+        )
+        .with_grammar(grammar)
+        .with_parameters(arguments)
+        .with_return_type(ret_type)
+        .emit()?;
+        rust!(rust, "{{");
+        rust!(rust, "{}.action{}({})", actions, index, Sep(", ", &names));
+        rust!(rust, "}}");
+        return Ok(());
+    }
+
     // For each symbol to be reduced, we will receive
     // a (L, T, L) triple where the Ls are locations and
     // the T is the data. Ignore the locations and bind
     // the data to the name the user gave.
+    let arguments = action_arguments(grammar, data, |i| {
+        format!("(_, {}, _)", data.arg_patterns[i])
+    });
+
+    rust!(rust, "#[allow(clippy::too_many_arguments, clippy::needless_lifetimes, clippy::just_underscores_and_digits)]");
+    rust.fn_header(
+        &r::Visibility::Priv,
+        format!("{}action{}", grammar.prefix, index),
+    )
+    .with_grammar(grammar)
+    .with_parameters(arguments)
+    .with_return_type(ret_type)
+    .emit()?;
+
+    rust!(rust, "{{");
+
+    // The user did not provide any code
+    if data.code != "()" {
+        rust!(rust, "{}", data.code);
+    }
+
+    rust!(rust, "}}");
+    Ok(())
+}
+
+/// The arguments through which a user action fn receives the symbols
+/// being reduced, binding the `i`th one to `pattern(i)`.
+fn action_arguments(
+    grammar: &r::Grammar,
+    data: &r::UserActionFnDefn,
+    pattern: impl Fn(usize) -> String,
+) -> Vec<String> {
     let mut arguments: Vec<String> = data
-        .arg_patterns
+        .arg_types
         .iter()
-        .zip(
-            data.arg_types
-                .iter()
-                .cloned()
-                .map(|t| grammar.types.spanned_type(t)),
-        )
-        .map(|(name, ty)| format!("(_, {}, _): {}", name, ty))
+        .cloned()
+        .map(|t| grammar.types.spanned_type(t))
+        .enumerate()
+        .map(|(i, ty)| format!("{}: {}", pattern(i), ty))
         .collect();
 
     // If this is a reduce of an empty production, we will
@@ -120,24 +182,89 @@ fn emit_user_action_code<W: Write>(
         ]);
     }
 
-    rust!(rust, "#[allow(clippy::too_many_arguments, clippy::needless_lifetimes, clippy::just_underscores_and_digits)]");
-    rust.fn_header(
-        &r::Visibility::Priv,
-        format!("{}action{}", grammar.prefix, index),
-    )
-    .with_grammar(grammar)
-    .with_parameters(arguments)
-    .with_return_type(ret_type)
-    .emit()?;
+    arguments
+}
 
-    rust!(rust, "{{");
+/// Emits the `Actions` trait requested by `#[action_trait]`, with one
+/// method per user action fn, `action{N}` for `__action{N}`. Each
+/// method takes the grammar parameters other than the actions object
+/// and the symbols being reduced, and by default runs the action code
+/// of the grammar. The grammar's type parameters become parameters of
+/// the methods, except those of the actions object itself, so that any
+/// type can implement the trait.
+pub fn emit_actions_trait<W: Write>(
+    grammar: &r::Grammar,
+    visibility: &r::Visibility,
+    rust: &mut RustWrite<W>,
+) -> io::Result<()> {
+    let actions = grammar.algorithm.action_trait.as_ref().unwrap();
+    let actions_ty = &grammar
+        .parameters
+        .iter()
+        .find(|parameter| parameter.name == *actions)
+        .unwrap()
+        .ty;
+    let excluded = actions_ty.free_variables(&grammar.type_parameters);
+    let type_parameters: Vec<_> = grammar
+        .type_parameters
+        .iter()
+        .filter(|tp| !excluded.contains(tp))
+        .collect();
+    let where_clauses: Vec<_> = grammar
+        .where_clauses
+        .iter()
+        .filter(|wc| {
+            wc.free_variables(&grammar.type_parameters)
+                .iter()
+                .all(|tp| !excluded.contains(tp))
+        })
+        .collect();
+    let parameters: Vec<_> = grammar
+        .parameters
+        .iter()
+        .filter(|parameter| parameter.name != *actions)
+        .collect();
 
-    // The user did not provide any code
-    if data.code != "()" {
-        rust!(rust, "{}", data.code);
+    rust!(rust, "");
+    rust!(rust, "{}trait Actions {{", visibility);
+    for (index, defn) in grammar.action_fn_defns.iter().enumerate() {
+        let r::ActionFnDefnKind::User(ref data) = defn.kind else {
+            continue;
+        };
+        let arguments = action_arguments(grammar, data, |i| {
+            format!("(_, {}, _)", data.arg_patterns[i])
+        });
+
+        if let Some(production) = grammar
+            .nonterminals
+            .values()
+            .flat_map(|nt| &nt.productions)
+            .find(|production| production.action.index() == index)
+        {
+            rust!(
+                rust,
+                "/// The action of `{} = {}`.",
+                production.nonterminal,
+                Sep(" ", &production.symbols)
+            );
+        }
+        rust!(rust, "#[allow(unused_variables, clippy::too_many_arguments, clippy::needless_lifetimes, clippy::just_underscores_and_digits)]");
+        rust.fn_header(&r::Visibility::Priv, format!("action{}", index))
+            .with_type_parameters(&type_parameters)
+            .with_where_clauses(&where_clauses)
+            .with_parameters(Some("&self".to_owned()))
+            .with_parameters(parameters.iter().copied())
+            .with_parameters(arguments)
+            .with_return_type(ret_type_string(grammar, defn))
+            .emit()?;
+        rust!(rust, "{{");
+        if data.code != "()" {
+            rust!(rust, "{}", data.code);
+        }
+        rust!(rust, "}}");
     }
-
     rust!(rust, "}}");
+
     Ok(())
 }
 
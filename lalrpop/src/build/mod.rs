@@ -1,6 +1,10 @@
 //! Utilities for running in a build script.
 
+use crate::api::analysis;
+use crate::api::automaton::Automaton;
+use crate::api::Conflict;
 use crate::file_text::FileText;
+use crate::grammar::consts::PARSE_FN;
 use crate::grammar::parse_tree as pt;
 use crate::grammar::repr as r;
 use crate::lexer::intern_token;
@@ -19,6 +23,7 @@ use lalrpop_util::ParseError;
 use tiny_keccak::{Hasher, Sha3};
 use walkdir::WalkDir;
 
+use std::error::Error;
 use std::ffi::OsStr;
 use std::fs;
 use std::io::{self, BufRead, IsTerminal, Read, Write};
@@ -171,6 +176,59 @@ fn process_file_into(
     Ok(())
 }
 
+/// Builds the states of the nonterminal `start` of `lalrpop_file`, for
+/// `Configuration::build_automaton`.
+pub fn build_automaton(
+    session: Rc<Session>,
+    lalrpop_file: &Path,
+    start: &str,
+) -> Result<Automaton, Box<dyn Error>> {
+    with_start_symbol(session, lalrpop_file, start, lr1::build_automaton)
+}
+
+/// Analyzes `lalrpop_file` as seen from its nonterminal `start`, for
+/// `Configuration::analyze_grammar`.
+pub fn analyze_grammar(
+    session: Rc<Session>,
+    lalrpop_file: &Path,
+    start: &str,
+) -> Result<analysis::Grammar, Box<dyn Error>> {
+    with_start_symbol(session, lalrpop_file, start, lr1::analyze_grammar)
+}
+
+/// Finds the conflicts of the nonterminal `start` of `lalrpop_file`,
+/// for `Configuration::check_lr1`.
+pub fn check_lr1(
+    session: Rc<Session>,
+    lalrpop_file: &Path,
+    start: &str,
+) -> Result<Vec<Conflict>, Box<dyn Error>> {
+    with_start_symbol(session, lalrpop_file, start, lr1::check_lr1)
+}
+
+/// Loads `lalrpop_file` and calls `f` with the grammar and a start
+/// symbol for building the states of its nonterminal `start`, as
+/// returned by `augment_start`.
+fn with_start_symbol<T>(
+    session: Rc<Session>,
+    lalrpop_file: &Path,
+    start: &str,
+    f: impl FnOnce(&r::Grammar, r::NonterminalString) -> Result<T, String>,
+) -> Result<T, Box<dyn Error>> {
+    let file_text = Rc::new(FileText::from_path(lalrpop_file.to_path_buf())?);
+    let _tls = Tls::install(session.clone(), file_text.clone());
+    let mut grammar = parse_and_normalize_grammar(&session, &file_text)?;
+    let start = grammar
+        .nonterminals
+        .keys()
+        .find(|nonterminal| nonterminal.0 == *start)
+        .cloned()
+        .ok_or_else(|| format!("no nonterminal `{}`", start))?;
+    let start_nt = grammar.augment_start(&start);
+    let _lr1_tls = lr1::Lr1Tls::install(grammar.terminals.clone());
+    Ok(f(&grammar, start_nt)?)
+}
+
 fn remove_old_file(rs_file: &Path) -> io::Result<()> {
     match fs::remove_file(rs_file) {
         Ok(()) => Ok(()),
@@ -433,6 +491,14 @@ fn emit_recursive_ascent(
     // Find a better visibility for some generated items.
     // This will be the maximum of the visibility of all starting nonterminals.
     let mut max_start_nt_visibility = pt::Visibility::Priv;
+    for recursion in lr1::hidden_left_recursion(grammar) {
+        let _ = report_message(lr1::report_hidden_left_recursion(&recursion));
+    }
+
+    let mut all_states = vec![];
+    let mut snapshot = String::new();
+    let mut json_report = lr1::JsonReport::new(grammar);
+    let json_report_file = report_file.with_extension("report.json");
     for (user_nt, start_nt) in &grammar.start_nonterminals {
         match (
             &max_start_nt_visibility,
@@ -457,20 +523,41 @@ fn emit_recursive_ascent(
 
         let _lr1_tls = lr1::Lr1Tls::install(grammar.terminals.clone());
 
-        let lr1result = lr1::build_states(grammar, start_nt.clone());
+        let lr1result = lr1::build_states_reporting(grammar, start_nt.clone(), |message| {
+            let _ = report_message(message);
+        });
         if session.emit_report {
             let mut output_report_file = fs::File::create(report_file)?;
             lr1::generate_report(&mut output_report_file, &lr1result)?;
         }
+        if session.emit_json_report {
+            let (states, conflicts) = match lr1result {
+                Ok(ref states) => (&states[..], &[][..]),
+                Err(ref error) => (&error.states[..], &error.conflicts[..]),
+            };
+            json_report.add_start(user_nt, states, conflicts)?;
+        }
 
         let states = match lr1result {
             Ok(states) => states,
             Err(error) => {
+                if session.emit_json_report {
+                    // the start symbols up to the one that failed
+                    json_report.write(&mut fs::File::create(&json_report_file)?)?;
+                }
                 let _ = lr1::report_error(grammar, &error, report_message);
                 exit(1) // FIXME -- propagate up instead of calling `exit`
             }
         };
 
+        if session.emit_snapshot {
+            if !snapshot.is_empty() {
+                snapshot.push('\n');
+            }
+            snapshot.push_str(&format!("start {}\n\n", user_nt));
+            snapshot.push_str(&lr1::snapshot_states(grammar, &states));
+        }
+
         match grammar.algorithm.codegen {
             r::LrCodeGeneration::RecursiveAscent => lr1::codegen::ascent::compile(
                 grammar,
@@ -507,6 +594,63 @@ fn emit_recursive_ascent(
             start_nt,
             user_nt
         );
+
+        if grammar.nonterminals[user_nt]
+            .annotations
+            .iter()
+            .any(|annotation| annotation.id == *PARSE_FN)
+        {
+            rust!(rust, "#[allow(unused_imports)]");
+            rust!(
+                rust,
+                "{}use self::{}parse{}::parse;",
+                grammar.nonterminals[user_nt].visibility,
+                grammar.prefix,
+                start_nt
+            );
+        }
+
+        if grammar.algorithm.first_sets {
+            emit_first_set(grammar, user_nt, &mut rust)?;
+        }
+
+        if grammar.algorithm.parser_trait {
+            emit_parser_trait_impl(grammar, user_nt, start_nt, &mut rust)?;
+        }
+
+        if let Some(ref tokenizer) = grammar.algorithm.parse_str {
+            emit_parse_str_fn(grammar, user_nt, start_nt, tokenizer, &mut rust)?;
+        }
+
+        // `parse_partial` hands back the parser's stack, so make the
+        // type of its entries nameable.
+        if grammar.algorithm.partial_results
+            && grammar.algorithm.codegen == r::LrCodeGeneration::TableDriven
+        {
+            rust!(rust, "#[allow(unused_imports)]");
+            rust!(
+                rust,
+                "pub(crate) use self::{0}parse{1}::{0}Symbol as {2}Symbol;",
+                grammar.prefix,
+                start_nt,
+                user_nt
+            );
+        }
+
+        all_states.push(states);
+    }
+
+    if session.emit_snapshot {
+        fs::write(report_file.with_extension("snapshot"), snapshot)?;
+    }
+    if session.emit_json_report {
+        json_report.write(&mut fs::File::create(&json_report_file)?)?;
+    }
+
+    for production in
+        lr1::unreduced_productions(grammar, all_states.iter().map(|states| &states[..]))
+    {
+        let _ = report_message(lr1::report_unreduced_production(production));
     }
 
     if let Some(ref intern_token) = grammar.intern_token {
@@ -520,12 +664,206 @@ fn emit_recursive_ascent(
 
     action::emit_action_code(grammar, &mut rust)?;
 
+    if grammar.algorithm.action_trait.is_some() {
+        action::emit_actions_trait(grammar, &max_start_nt_visibility, &mut rust)?;
+    }
+
+    if grammar.algorithm.token_names {
+        emit_token_name_fn(grammar, &max_start_nt_visibility, &mut rust)?;
+    }
+
     rust!(rust, "#[allow(clippy::type_complexity, dead_code)]");
     emit_to_triple_trait(grammar, max_start_nt_visibility, &mut rust)?;
 
     Ok(rust.into_inner())
 }
 
+/// Emits `FIRST_<NT>`, which names the terminals that can begin
+/// `user_nt` (in uppercase), as they are named in the `expected` lists
+/// of parse errors.
+fn emit_first_set<W: Write>(
+    grammar: &r::Grammar,
+    user_nt: &r::NonterminalString,
+    rust: &mut RustWrite<W>,
+) -> io::Result<()> {
+    let terminals: Vec<String> = lr1::first_terminals(grammar, user_nt)
+        .iter()
+        .map(|terminal| format!("{:?}", terminal.to_string()))
+        .collect();
+
+    rust!(rust, "#[allow(dead_code)]");
+    rust!(
+        rust,
+        "{}static FIRST_{}: &[&str] = &[{}];",
+        grammar.nonterminals[user_nt].visibility,
+        user_nt.to_string().to_uppercase(),
+        Sep(", ", &terminals)
+    );
+
+    Ok(())
+}
+
+/// Implements `lalrpop_util::Parser` for the parser of `user_nt` by
+/// forwarding to its `parse` method.
+fn emit_parser_trait_impl<W: Write>(
+    grammar: &r::Grammar,
+    user_nt: &r::NonterminalString,
+    start_nt: &r::NonterminalString,
+    rust: &mut RustWrite<W>,
+) -> io::Result<()> {
+    #[allow(non_snake_case)]
+    let (L, T, E) = (
+        grammar.types.terminal_loc_type(),
+        grammar.types.terminal_token_type(),
+        grammar.types.error_type(),
+    );
+
+    let where_clauses = &grammar.where_clauses;
+    let trait_where_clauses = Sep(",", where_clauses);
+
+    rust!(rust, "");
+    rust!(
+        rust,
+        "impl<{utp}> {p}lalrpop_util::Parser<{L}, {T}, {E}> for {nt}Parser",
+        utp = Sep(", ", &grammar.type_parameters),
+        p = grammar.prefix,
+        L = L,
+        T = T,
+        E = E,
+        nt = user_nt,
+    );
+    write_where_clause(where_clauses, &trait_where_clauses, rust)?;
+    rust!(rust, "{{");
+    rust!(
+        rust,
+        "type Output = {};",
+        grammar.types.nonterminal_type(start_nt)
+    );
+    rust!(rust, "");
+    rust!(
+        rust,
+        "fn parse(&self, tokens: &mut dyn Iterator<Item = Result<({L}, {T}, {L}), {E}>>) \
+         -> Result<Self::Output, {p}lalrpop_util::ParseError<{L}, {T}, {E}>> {{",
+        p = grammar.prefix,
+        L = L,
+        T = T,
+        E = E,
+    );
+    if grammar.types.opt_terminal_loc_type().is_some() {
+        rust!(rust, "{}Parser::parse(self, tokens)", user_nt);
+    } else {
+        // without locations, the parser takes bare tokens
+        rust!(
+            rust,
+            "{}Parser::parse(self, tokens.map(|t| t.map(|((), t, ())| t)))",
+            user_nt
+        );
+    }
+    rust!(rust, "}}");
+    rust!(rust, "}}");
+
+    Ok(())
+}
+
+/// Adds `parse_str` to the parser of `user_nt`, which passes its input
+/// through `tokenizer` and parses the resulting tokens. The string is
+/// given the grammar's first lifetime, if any, as the tokens usually
+/// borrow from it.
+fn emit_parse_str_fn<W: Write>(
+    grammar: &r::Grammar,
+    user_nt: &r::NonterminalString,
+    start_nt: &r::NonterminalString,
+    tokenizer: &str,
+    rust: &mut RustWrite<W>,
+) -> io::Result<()> {
+    #[allow(non_snake_case)]
+    let (L, T, E) = (
+        grammar.types.terminal_loc_type(),
+        grammar.types.terminal_token_type(),
+        grammar.types.error_type(),
+    );
+    let lifetime = grammar
+        .type_parameters
+        .iter()
+        .find_map(|parameter| match parameter {
+            r::TypeParameter::Lifetime(lifetime) => Some(format!("{} ", lifetime)),
+            r::TypeParameter::Id(_) => None,
+        })
+        .unwrap_or_default();
+
+    let where_clauses = &grammar.where_clauses;
+    let fn_where_clauses = Sep(",", where_clauses);
+
+    rust!(rust, "");
+    rust!(rust, "impl {}Parser {{", user_nt);
+    rust!(rust, "#[allow(dead_code)]");
+    rust!(
+        rust,
+        "pub fn parse_str<{utp}>(&self, input: &{lt}str) \
+         -> Result<{Output}, {p}lalrpop_util::ParseError<{L}, {T}, {E}>>",
+        utp = Sep(", ", &grammar.type_parameters),
+        lt = lifetime,
+        Output = grammar.types.nonterminal_type(start_nt),
+        p = grammar.prefix,
+        L = L,
+        T = T,
+        E = E,
+    );
+    write_where_clause(where_clauses, &fn_where_clauses, rust)?;
+    rust!(rust, "{{");
+    rust!(rust, "self.parse({}(input))", tokenizer);
+    rust!(rust, "}}");
+    rust!(rust, "}}");
+
+    Ok(())
+}
+
+/// Emits `token_name`, which maps a token to the name of its terminal,
+/// as used in the `expected` lists of parse errors. With
+/// `ParseError::map_token`, this turns an error into one whose tokens
+/// can be displayed and outlive the input.
+fn emit_token_name_fn<W: Write>(
+    grammar: &r::Grammar,
+    visibility: &r::Visibility,
+    rust: &mut RustWrite<W>,
+) -> io::Result<()> {
+    // only lifetimes can be inferred from the token alone
+    let lifetimes: Vec<_> = grammar
+        .type_parameters
+        .iter()
+        .filter(|parameter| matches!(parameter, r::TypeParameter::Lifetime(_)))
+        .collect();
+
+    rust!(rust, "");
+    rust!(rust, "#[allow(dead_code, unreachable_patterns)]");
+    rust!(
+        rust,
+        "{vis} fn token_name<{lifetimes}>(token: &{T}) -> &'static str {{",
+        vis = visibility,
+        lifetimes = Sep(", ", &lifetimes),
+        T = grammar.types.terminal_token_type(),
+    );
+    match grammar.algorithm.classify {
+        Some(ref classify) => rust!(rust, "match {}(token) {{", classify),
+        None => rust!(rust, "match *token {{"),
+    }
+    for terminal in grammar.terminals() {
+        if let Some(pattern) = grammar.conversions.get(&terminal) {
+            rust!(
+                rust,
+                "{} => {:?},",
+                pattern.map(&mut |_| "_"),
+                terminal.to_string()
+            );
+        }
+    }
+    rust!(rust, "_ => \"<unknown token>\",");
+    rust!(rust, "}}");
+    rust!(rust, "}}");
+
+    Ok(())
+}
+
 fn write_where_clause<W: Write>(
     where_clauses: &[r::WhereClause],
     to_triple_where_clauses: &Sep<&Vec<r::WhereClause>>,
@@ -6,8 +6,13 @@ pub use std::collections::btree_map::Entry;
 /// but rather build against `util::Map` (and `util::map` to construct
 /// an instance). This should be a deterministic map, such that two
 /// runs of LALRPOP produce the same output, but otherwise it doesn't
-/// matter much. I'd probably prefer to use `HashMap` with an
-/// alternative hasher, but that's not stable.
+/// matter much.
+///
+/// Being a B-tree, it involves no hashing at all. State construction
+/// iterates over maps and sets (and multimaps built on them) in key
+/// order, and the numbering of states, and hence the generated code,
+/// depends on that order, so switching to a hash map, whatever its
+/// hasher, would need every such loop to sort its keys first.
 pub type Map<K, V> = BTreeMap<K, V>;
 
 pub fn map<K: Ord, V>() -> Map<K, V> {
@@ -86,6 +86,21 @@ impl FileText {
         }
     }
 
+    /// Returns the lines of the `///` comment just above the line
+    /// containing `pos`, without the slashes, as used to document a
+    /// rule in the grammar.
+    pub fn doc_comment(&self, pos: usize) -> Vec<&str> {
+        let (line, _) = self.line_col(pos);
+        let mut doc: Vec<&str> = (0..line)
+            .rev()
+            .map(|i| self.line_text(i).trim())
+            .take_while(|text| text.starts_with("///"))
+            .map(|text| text["///".len()..].trim())
+            .collect();
+        doc.reverse();
+        doc
+    }
+
     pub fn highlight(&self, span: pt::Span, out: &mut dyn Write) -> io::Result<()> {
         let (start_line, start_col) = self.line_col(span.0);
         let (end_line, end_col) = self.line_col(span.1);
@@ -140,3 +155,31 @@ impl Display for Repeat {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn doc_comments() {
+        let text = "\
+// not a doc comment
+/// Sums of terms,
+///   added left to right.
+pub Sum: i32 = {
+    <l:Sum> \"+\" <r:Term> => l + r,
+
+    /// A single term.
+    Term,
+};
+";
+        let file_text = FileText::new(PathBuf::from("test.lalrpop"), String::from(text));
+        let pos = |s: &str| text.find(s).unwrap();
+        assert_eq!(
+            file_text.doc_comment(pos("Sum:")),
+            ["Sums of terms,", "added left to right."]
+        );
+        assert!(file_text.doc_comment(pos("<l:Sum>")).is_empty());
+        assert_eq!(file_text.doc_comment(pos("Term,")), ["A single term."]);
+    }
+}
@@ -1,7 +1,8 @@
-//! Generate valid parse trees.
+//! Generate valid parse trees, and random grammars.
 
 use crate::grammar::repr::*;
 use rand::{self, Rng};
+use std::fmt::Write;
 use std::iter::Iterator;
 
 #[derive(PartialEq, Eq)]
@@ -78,3 +79,52 @@ impl ParseTree {
         }
     }
 }
+
+/// Generates the text of a random grammar with up to `max_symbols`
+/// terminals and as many nonterminals, `N0` to `N<n>`, where `N0` is
+/// meant as the start symbol. Each nonterminal has between one and
+/// three alternatives of at most `max_symbols` symbols, some of which
+/// are empty. The grammar always normalizes, but it may be ambiguous,
+/// have unreachable nonterminals, or nonterminals that derive no
+/// string of terminals at all.
+pub fn random_grammar<R: Rng>(rng: &mut R, max_symbols: usize) -> String {
+    let terminals: Vec<String> = (0..rng.gen_range(1..=max_symbols))
+        .map(|i| format!("\"{}\"", (b'a' + i as u8) as char))
+        .collect();
+    let nonterminals: Vec<String> = (0..rng.gen_range(1..=max_symbols))
+        .map(|i| format!("N{}", i))
+        .collect();
+
+    let mut text = String::from("grammar;\n");
+    let conversions: Vec<String> = terminals.iter().map(|t| format!("{} => ..", t)).collect();
+    writeln!(
+        text,
+        "extern {{ enum Tok {{ {} }} }}",
+        conversions.join(", ")
+    )
+    .unwrap();
+    for nonterminal in &nonterminals {
+        let alternatives: Vec<String> = (0..rng.gen_range(1..=3))
+            .map(|_| {
+                let symbols: Vec<&str> = (0..rng.gen_range(0..=max_symbols))
+                    .map(|_| {
+                        let index = rng.gen_range(0..terminals.len() + nonterminals.len());
+                        match terminals.get(index) {
+                            Some(terminal) => &terminal[..],
+                            None => &nonterminals[index - terminals.len()][..],
+                        }
+                    })
+                    .collect();
+                format!("{} => ()", symbols.join(" "))
+            })
+            .collect();
+        writeln!(
+            text,
+            "{}: () = {{ {} }};",
+            nonterminal,
+            alternatives.join(", ")
+        )
+        .unwrap();
+    }
+    text
+}
@@ -16,9 +16,20 @@ pub const INLINE: &str = "inline";
 /// The annotation to request conditional compilation.
 pub const CFG: &str = "cfg";
 
+/// The annotation to box a nonterminal's values while a recursive
+/// ascent parser passes them between states.
+pub const BOXED: &str = "boxed";
+
+/// The annotation to generate a free `parse` function for a public
+/// nonterminal, next to its parser.
+pub const PARSE_FN: &str = "parse_fn";
+
 /// Annotation to request LALR.
 pub const LALR: &str = "LALR";
 
+/// Annotation to request SLR, falling back to LR(1) on conflicts.
+pub const SLR: &str = "SLR";
+
 /// Annotation to request recursive-ascent-style code generation.
 pub const TABLE_DRIVEN: &str = "table_driven";
 
@@ -27,3 +38,132 @@ pub const RECURSIVE_ASCENT: &str = "recursive_ascent";
 
 /// Annotation to request test-all-style code generation.
 pub const TEST_ALL: &str = "test_all";
+
+/// Annotation to request a token-at-a-time `PushParser` constructor.
+pub const PUSH_PARSER: &str = "push_parser";
+
+/// Annotation to request an `IncrementalParser` constructor, which
+/// can parse its input again after an edit.
+pub const INCREMENTAL: &str = "incremental";
+
+/// Annotation to request an `EventParser` constructor, which reports
+/// shifts and reductions instead of running action code.
+pub const EVENT_PARSER: &str = "event_parser";
+
+/// Annotation to request a `parse_partial` method, which returns the
+/// parser stack along with any error.
+pub const PARTIAL_RESULTS: &str = "partial_results";
+
+/// Annotation to request a `parse_prefix` method, which stops at the
+/// first token that cannot continue a complete parse.
+pub const PARSE_PREFIX: &str = "parse_prefix";
+
+/// Annotation to request a `parse_items` method, which parses one value
+/// after another from the same input and yields each as soon as it is
+/// complete.
+pub const PARSE_ITEMS: &str = "parse_items";
+
+/// Annotation to count how often each production is reduced, for
+/// profiling a parser on real input.
+pub const COUNT_REDUCTIONS: &str = "count_reductions";
+
+/// Annotation to add a `parse_str` method to each parser, which
+/// tokenizes a string with the given function and parses the tokens,
+/// e.g. `#[parse_str(tokenizer = "crate::lexer::tokenize")]`.
+pub const PARSE_STR: &str = "parse_str";
+
+/// Annotation to match tokens against the terminal patterns through a
+/// classifying function, e.g. `#[classify(function = "crate::lexer::kind")]`.
+pub const CLASSIFY: &str = "classify";
+
+/// Annotation to implement `lalrpop_util::Parser` for each parser.
+pub const PARSER_TRAIT: &str = "parser_trait";
+
+/// Annotation to generate a `token_name` function, which gives the
+/// name of a token in the grammar.
+pub const TOKEN_NAMES: &str = "token_names";
+
+/// Annotation to make recursive ascent parsers print each shift and
+/// reduction to stderr.
+pub const TRACE: &str = "trace";
+
+/// Annotation to bound the nesting depth of recursive ascent parsers.
+pub const RECURSION_LIMIT: &str = "recursion_limit";
+
+/// Annotation to make table-driven parsers give up once they have
+/// reduced more than the given number of productions, e.g.
+/// `#[reduction_limit(count = "100000")]`.
+pub const REDUCTION_LIMIT: &str = "reduction_limit";
+
+/// Annotation to make table-driven parsers reserve room on their stacks
+/// for the given number of symbols before parsing, e.g.
+/// `#[stack_capacity(depth = "256")]`.
+pub const STACK_CAPACITY: &str = "stack_capacity";
+
+/// Annotation to accept a known number of conflicts, which are then
+/// resolved in favor of shifting (unless `conflict_preference` says
+/// otherwise), e.g. `#[expect_conflicts(count = "1")]`.
+pub const EXPECT_CONFLICTS: &str = "expect_conflicts";
+
+/// Annotation to resolve the shift/reduce conflicts accepted by
+/// `expect_conflicts` in favor of reducing instead, e.g.
+/// `#[conflict_preference(prefer = "reduce")]`.
+pub const CONFLICT_PREFERENCE: &str = "conflict_preference";
+
+/// Annotation to keep the conflicts of a table-driven parser, letting the
+/// given function choose among the actions while parsing, e.g.
+/// `#[disambiguate(function = "crate::choose")]`.
+pub const DISAMBIGUATE: &str = "disambiguate";
+
+/// Annotation to keep the conflicts of a table-driven parser, trying
+/// each action in turn until the given number of tokens can be shifted
+/// after it, e.g. `#[backtrack(window = "2")]`.
+pub const BACKTRACK: &str = "backtrack";
+
+/// Annotation to make `parse` take its tokens as a slice, which is read
+/// through a `lalrpop_util::lookahead::SliceTokens`, e.g. `#[slice_input]`.
+pub const SLICE_INPUT: &str = "slice_input";
+
+/// Annotation to emit, for each public nonterminal `Expr`, a
+/// `FIRST_EXPR` constant naming the terminals that can begin it, e.g.
+/// `#[first_sets]`.
+pub const FIRST_SETS: &str = "first_sets";
+
+/// Annotation to dispatch the action code through the methods of a
+/// generated `Actions` trait, implemented by the type of the given
+/// grammar parameter, e.g. `#[action_trait(parameter = "actions")]`.
+pub const ACTION_TRAIT: &str = "action_trait";
+
+/// Annotation naming a terminal that the tokenizer yields to mark the
+/// end of the input, e.g. `#[eof(terminal = "Eof")]`.
+pub const EOF: &str = "eof";
+
+/// Annotation naming a terminal that the parser silently drops from the
+/// token stream, e.g. `#[skip(terminal = "Whitespace")]`. It may be
+/// repeated to drop several terminals.
+pub const SKIP: &str = "skip";
+
+/// Annotation to parse a `&[u8]` directly, treating every byte as a
+/// token, e.g. `#[bytes]`.
+pub const BYTES: &str = "bytes";
+
+/// Annotation to parse the chars of a `&str` directly, treating every
+/// char as a token, e.g. `#[chars]`.
+pub const CHARS: &str = "chars";
+
+/// Annotation to spread the state functions of a recursive ascent
+/// parser over the given number of modules, e.g.
+/// `#[state_modules(count = "4")]`.
+pub const STATE_MODULES: &str = "state_modules";
+
+/// Annotation to generate a parser that only checks its input, with
+/// every action replaced by `()`, e.g. `#[recognizer]`.
+pub const RECOGNIZER: &str = "recognizer";
+
+/// Annotation to report, for an unexpected token, only the expected
+/// terminals after which that token could be parsed.
+pub const NARROW_EXPECTED: &str = "narrow_expected";
+
+/// Annotation to overlap the sparse rows of a table-driven parser's
+/// action table into a comb vector, e.g. `#[compact_tables]`.
+pub const COMPACT_TABLES: &str = "compact_tables";
@@ -5,4 +5,7 @@ pub mod free_variables;
 pub mod parse_tree;
 pub mod pattern;
 pub mod repr;
+pub mod visit;
 // pub mod token;
+
+mod test;
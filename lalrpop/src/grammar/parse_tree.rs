@@ -1,7 +1,14 @@
 //! The "parse-tree" is what is produced by the parser. We use it do
 //! some pre-expansion and so forth before creating the proper AST.
 
-use crate::grammar::consts::{INPUT_LIFETIME, LALR, RECURSIVE_ASCENT, TABLE_DRIVEN, TEST_ALL};
+use crate::grammar::consts::{
+    ACTION_TRAIT, BACKTRACK, BYTES, CHARS, CLASSIFY, COMPACT_TABLES, CONFLICT_PREFERENCE,
+    COUNT_REDUCTIONS, DISAMBIGUATE, EOF, EVENT_PARSER, EXPECT_CONFLICTS, FIRST_SETS, INCREMENTAL,
+    INPUT_LIFETIME, LALR, NARROW_EXPECTED, PARSER_TRAIT, PARSE_ITEMS, PARSE_PREFIX, PARSE_STR,
+    PARTIAL_RESULTS, PUSH_PARSER, RECOGNIZER, RECURSION_LIMIT, RECURSIVE_ASCENT, REDUCTION_LIMIT,
+    SKIP, SLICE_INPUT, SLR, STACK_CAPACITY, STATE_MODULES, TABLE_DRIVEN, TEST_ALL, TOKEN_NAMES,
+    TRACE,
+};
 use crate::grammar::pattern::Pattern;
 use crate::grammar::repr::{self as r, NominalTypeRepr, TypeRepr};
 use crate::lexer::dfa::Dfa;
@@ -1198,12 +1205,84 @@ pub fn read_algorithm(annotations: &[Annotation], algorithm: &mut r::Algorithm)
     for annotation in annotations {
         if annotation.id == *LALR {
             algorithm.lalr = true;
+        } else if annotation.id == *SLR {
+            algorithm.slr = true;
         } else if annotation.id == *TABLE_DRIVEN {
             algorithm.codegen = r::LrCodeGeneration::TableDriven;
         } else if annotation.id == *RECURSIVE_ASCENT {
             algorithm.codegen = r::LrCodeGeneration::RecursiveAscent;
         } else if annotation.id == *TEST_ALL {
             algorithm.codegen = r::LrCodeGeneration::TestAll;
+        } else if annotation.id == *PUSH_PARSER {
+            algorithm.push_parser = true;
+        } else if annotation.id == *INCREMENTAL {
+            algorithm.incremental = true;
+        } else if annotation.id == *EVENT_PARSER {
+            algorithm.event_parser = true;
+        } else if annotation.id == *PARTIAL_RESULTS {
+            algorithm.partial_results = true;
+        } else if annotation.id == *PARSE_PREFIX {
+            algorithm.parse_prefix = true;
+        } else if annotation.id == *PARSE_ITEMS {
+            algorithm.parse_items = true;
+        } else if annotation.id == *COUNT_REDUCTIONS {
+            algorithm.count_reductions = true;
+        } else if annotation.id == *NARROW_EXPECTED {
+            algorithm.narrow_expected = true;
+        } else if annotation.id == *COMPACT_TABLES {
+            algorithm.compact_tables = true;
+        } else if annotation.id == *PARSER_TRAIT {
+            algorithm.parser_trait = true;
+        } else if annotation.id == *TOKEN_NAMES {
+            algorithm.token_names = true;
+        } else if annotation.id == *PARSE_STR {
+            // the argument is checked during validation
+            algorithm.parse_str = annotation.arg.as_ref().map(|(_, v)| v.clone());
+        } else if annotation.id == *CLASSIFY {
+            // the argument is checked during validation
+            algorithm.classify = annotation.arg.as_ref().map(|(_, v)| v.clone());
+        } else if annotation.id == *TRACE {
+            algorithm.trace = true;
+        } else if annotation.id == *RECURSION_LIMIT {
+            // the argument is checked during validation
+            algorithm.recursion_limit = annotation.arg.as_ref().and_then(|(_, v)| v.parse().ok());
+        } else if annotation.id == *REDUCTION_LIMIT {
+            // the argument is checked during validation
+            algorithm.reduction_limit = annotation.arg.as_ref().and_then(|(_, v)| v.parse().ok());
+        } else if annotation.id == *STACK_CAPACITY {
+            // the argument is checked during validation
+            algorithm.stack_capacity = annotation.arg.as_ref().and_then(|(_, v)| v.parse().ok());
+        } else if annotation.id == *STATE_MODULES {
+            // the argument is checked during validation
+            algorithm.state_modules = annotation.arg.as_ref().and_then(|(_, v)| v.parse().ok());
+        } else if annotation.id == *SLICE_INPUT {
+            algorithm.slice_input = true;
+        } else if annotation.id == *FIRST_SETS {
+            algorithm.first_sets = true;
+        } else if annotation.id == *ACTION_TRAIT {
+            // the argument is checked during validation
+            algorithm.action_trait = annotation.arg.as_ref().map(|(_, v)| Atom::from(&v[..]));
+        } else if annotation.id == *EXPECT_CONFLICTS {
+            // the argument is checked during validation
+            algorithm.expected_conflicts =
+                annotation.arg.as_ref().and_then(|(_, v)| v.parse().ok());
+        } else if annotation.id == *CONFLICT_PREFERENCE {
+            // the argument is checked during validation
+            if annotation.arg.as_ref().is_some_and(|(_, v)| v == "reduce") {
+                algorithm.prefer = r::Prefer::Reduce;
+            }
+        } else if annotation.id == *DISAMBIGUATE {
+            // the argument is checked during validation
+            algorithm.disambiguate = annotation.arg.as_ref().map(|(_, v)| v.clone());
+        } else if annotation.id == *BACKTRACK {
+            // the argument is checked during validation
+            algorithm.backtrack = annotation.arg.as_ref().and_then(|(_, v)| v.parse().ok());
+        } else if annotation.id == *EOF || annotation.id == *SKIP {
+            // not an algorithm setting; handled when lowering
+        } else if annotation.id == *BYTES || annotation.id == *CHARS {
+            // not an algorithm setting; handled when checking tokens
+        } else if annotation.id == *RECOGNIZER {
+            // not an algorithm setting; handled when normalizing
         } else {
             panic!(
                 "validation permitted unknown annotation: {:?}",
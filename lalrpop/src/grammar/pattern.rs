@@ -33,14 +33,21 @@ pub enum PatternKind<T> {
     Choose(T),
     CharLiteral(Atom),
     String(String),
+    /// The inclusive byte ranges that a `#[bytes]` terminal matches.
+    ByteRanges(Vec<(u8, u8)>),
+    /// The inclusive char ranges that a `#[chars]` terminal matches.
+    CharRanges(Vec<(char, char)>),
+    /// The patterns of a terminal declared more than once in an extern
+    /// token enum. They all bind the same values.
+    Or(Vec<Pattern<T>>),
 }
 
 impl<T> Pattern<T> {
-    pub fn for_each_binding<U>(&self, map_fn: &mut dyn FnMut(&T) -> U) {
+    pub fn for_each_binding<U: Clone>(&self, map_fn: &mut dyn FnMut(&T) -> U) {
         self.map(map_fn);
     }
 
-    pub fn map<U>(&self, map_fn: &mut dyn FnMut(&T) -> U) -> Pattern<U> {
+    pub fn map<U: Clone>(&self, map_fn: &mut dyn FnMut(&T) -> U) -> Pattern<U> {
         Pattern {
             span: self.span,
             kind: self.kind.map(map_fn),
@@ -48,8 +55,68 @@ impl<T> Pattern<T> {
     }
 }
 
+impl<T> Pattern<T> {
+    /// Whether some value is certainly matched by both `self` and
+    /// `other`. Only the common cases are recognized: wildcards, and
+    /// equal paths, literals and constructors whose fields overlap.
+    pub fn overlaps(&self, other: &Pattern<T>) -> bool {
+        match (&self.kind, &other.kind) {
+            (PatternKind::Or(a), _) => a.iter().any(|a| a.overlaps(other)),
+            (_, PatternKind::Or(b)) => b.iter().any(|b| self.overlaps(b)),
+            (PatternKind::Underscore, _)
+            | (PatternKind::Choose(_), _)
+            | (_, PatternKind::Underscore)
+            | (_, PatternKind::Choose(_)) => true,
+            (PatternKind::Path(a), PatternKind::Path(b)) => a == b,
+            (PatternKind::Usize(a), PatternKind::Usize(b)) => a == b,
+            (PatternKind::CharLiteral(a), PatternKind::CharLiteral(b)) => a == b,
+            (PatternKind::String(a), PatternKind::String(b)) => a == b,
+            (PatternKind::ByteRanges(a), PatternKind::ByteRanges(b)) => ranges_overlap(a, b),
+            (PatternKind::CharRanges(a), PatternKind::CharRanges(b)) => ranges_overlap(a, b),
+            (PatternKind::Tuple(a), PatternKind::Tuple(b)) => all_overlap(a, b),
+            (
+                PatternKind::Enum(a, a_pats) | PatternKind::TupleStruct(a, a_pats),
+                PatternKind::Enum(b, b_pats) | PatternKind::TupleStruct(b, b_pats),
+            ) => a == b && all_overlap(a_pats, b_pats),
+            (PatternKind::Struct(a, a_fields, _), PatternKind::Struct(b, b_fields, _)) => {
+                a == b
+                    && a_fields.iter().all(|a_field| {
+                        b_fields
+                            .iter()
+                            .filter(|b_field| b_field.field_name == a_field.field_name)
+                            .all(|b_field| a_field.pattern.overlaps(&b_field.pattern))
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether two lists of inclusive byte or char ranges share a value.
+fn ranges_overlap<C: Ord>(a: &[(C, C)], b: &[(C, C)]) -> bool {
+    a.iter()
+        .any(|(a_lo, a_hi)| b.iter().any(|(b_lo, b_hi)| a_lo <= b_hi && b_lo <= a_hi))
+}
+
+/// Whether two lists of field patterns certainly overlap. With a `..`
+/// the fields no longer line up, so then all the others have to be
+/// wildcards.
+fn all_overlap<T>(a: &[Pattern<T>], b: &[Pattern<T>]) -> bool {
+    let is_dotdot = |pattern: &Pattern<T>| matches!(pattern.kind, PatternKind::DotDot);
+    if a.iter().chain(b).any(is_dotdot) {
+        a.iter().chain(b).all(|pattern| {
+            matches!(
+                pattern.kind,
+                PatternKind::DotDot | PatternKind::Underscore | PatternKind::Choose(_)
+            )
+        })
+    } else {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.overlaps(b))
+    }
+}
+
 impl<T> PatternKind<T> {
-    pub fn map<U>(&self, map_fn: &mut dyn FnMut(&T) -> U) -> PatternKind<U> {
+    pub fn map<U: Clone>(&self, map_fn: &mut dyn FnMut(&T) -> U) -> PatternKind<U> {
         match *self {
             PatternKind::Path(ref path) => PatternKind::Path(path.clone()),
             PatternKind::Enum(ref path, ref pats) => PatternKind::Enum(
@@ -73,12 +140,29 @@ impl<T> PatternKind<T> {
             PatternKind::Choose(ref ty) => PatternKind::Choose(map_fn(ty)),
             PatternKind::CharLiteral(ref c) => PatternKind::CharLiteral(c.clone()),
             PatternKind::String(ref s) => PatternKind::String(s.clone()),
+            PatternKind::ByteRanges(ref ranges) => PatternKind::ByteRanges(ranges.clone()),
+            PatternKind::CharRanges(ref ranges) => PatternKind::CharRanges(ranges.clone()),
+            PatternKind::Or(ref pats) => {
+                // the bindings of each alternative must have the same
+                // names, so map those of the first and reuse them
+                let mut bindings = vec![];
+                let first = pats[0].map(&mut |t| {
+                    let binding = map_fn(t);
+                    bindings.push(binding.clone());
+                    binding
+                });
+                let rest = pats[1..].iter().map(|pat| {
+                    let mut bindings = bindings.iter().cloned();
+                    pat.map(&mut |_| bindings.next().unwrap())
+                });
+                PatternKind::Or(std::iter::once(first).chain(rest).collect())
+            }
         }
     }
 }
 
 impl<T> FieldPattern<T> {
-    pub fn map<U>(&self, map_fn: &mut dyn FnMut(&T) -> U) -> FieldPattern<U> {
+    pub fn map<U: Clone>(&self, map_fn: &mut dyn FnMut(&T) -> U) -> FieldPattern<U> {
         FieldPattern {
             field_name: self.field_name.clone(),
             field_span: self.field_span,
@@ -117,10 +201,42 @@ impl<T: Display> Display for PatternKind<T> {
             PatternKind::Choose(ref ty) => write!(fmt, "{}", ty),
             PatternKind::CharLiteral(ref c) => write!(fmt, "'{}'", c),
             PatternKind::String(ref s) => write!(fmt, "{:?}", s),
+            PatternKind::ByteRanges(ref ranges) => {
+                fmt_ranges(fmt, ranges, |b| format!("b'{}'", b.escape_ascii()))
+            }
+            PatternKind::CharRanges(ref ranges) => {
+                fmt_ranges(fmt, ranges, |c| format!("'{}'", c.escape_default()))
+            }
+            // parenthesized for the same reason
+            PatternKind::Or(ref pats) => write!(fmt, "({})", Sep(" | ", pats)),
         }
     }
 }
 
+/// Writes inclusive ranges as alternative range patterns.
+fn fmt_ranges<C: Copy + PartialEq>(
+    fmt: &mut Formatter,
+    ranges: &[(C, C)],
+    literal: impl Fn(C) -> String,
+) -> Result<(), Error> {
+    let ranges: Vec<_> = ranges
+        .iter()
+        .map(|&(lo, hi)| {
+            if lo == hi {
+                literal(lo)
+            } else {
+                format!("{}..={}", literal(lo), literal(hi))
+            }
+        })
+        .collect();
+    // parenthesized, so that the pattern can be bound with `@`
+    if ranges.len() == 1 {
+        write!(fmt, "{}", ranges[0])
+    } else {
+        write!(fmt, "({})", Sep(" | ", &ranges))
+    }
+}
+
 impl<T: Display> Display for FieldPattern<T> {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         write!(fmt, "{}: {}", self.field_name, self.pattern)
@@ -58,6 +58,14 @@ pub struct Grammar {
     pub conversions: Map<TerminalString, Pattern<TypeRepr>>,
     pub types: Types,
     pub module_attributes: Vec<String>,
+
+    // terminal that the tokenizer yields to signal end of input, as
+    // requested by `#[eof(terminal = "...")]`
+    pub eof_terminal: Option<TerminalString>,
+
+    // terminals that the parser drops from the token stream, as
+    // requested by `#[skip(terminal = "...")]`
+    pub skip_terminals: Vec<TerminalString>,
 }
 
 #[allow(clippy::large_enum_variant)] // TODO: verify if this is justified
@@ -93,10 +101,44 @@ pub struct NonterminalData {
     pub productions: Vec<Production>,
 }
 
+/// Which action wins a shift/reduce conflict accepted by
+/// `#[expect_conflicts]`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Prefer {
+    Shift,
+    Reduce,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Algorithm {
     pub lalr: bool,
+    pub slr: bool,
     pub codegen: LrCodeGeneration,
+    pub push_parser: bool,
+    pub incremental: bool,
+    pub event_parser: bool,
+    pub partial_results: bool,
+    pub parse_prefix: bool,
+    pub parse_items: bool,
+    pub count_reductions: bool,
+    pub narrow_expected: bool,
+    pub compact_tables: bool,
+    pub recursion_limit: Option<usize>,
+    pub reduction_limit: Option<usize>,
+    pub stack_capacity: Option<usize>,
+    pub state_modules: Option<usize>,
+    pub trace: bool,
+    pub parser_trait: bool,
+    pub slice_input: bool,
+    pub first_sets: bool,
+    pub action_trait: Option<Atom>,
+    pub expected_conflicts: Option<usize>,
+    pub prefer: Prefer,
+    pub disambiguate: Option<String>,
+    pub backtrack: Option<usize>,
+    pub token_names: bool,
+    pub parse_str: Option<String>,
+    pub classify: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -657,6 +699,23 @@ impl Debug for ActionFnDefn {
 }
 
 impl ActionFnDefn {
+    /// The number of symbols the action fn takes as arguments, which
+    /// must be the length of each production that uses it.
+    pub fn arity(&self) -> usize {
+        match self.kind {
+            ActionFnDefnKind::User(ref data) => data.arg_patterns.len(),
+            ActionFnDefnKind::Inline(ref data) => data
+                .symbols
+                .iter()
+                .map(|symbol| match *symbol {
+                    InlinedSymbol::Original(_) => 1,
+                    InlinedSymbol::Inlined(_, ref symbols) => symbols.len(),
+                })
+                .sum(),
+            ActionFnDefnKind::Lookaround(_) => 0,
+        }
+    }
+
     fn to_fn_string(&self, name: &str) -> String {
         match self.kind {
             ActionFnDefnKind::User(ref data) => data.to_fn_string(self, name),
@@ -710,6 +769,19 @@ impl Grammar {
         &self.conversions[t]
     }
 
+    /// Every terminal the grammar recognizes, in the order in which
+    /// they are numbered in the generated tables.
+    pub fn terminals(&self) -> impl Iterator<Item = TerminalString> + '_ {
+        self.terminals.all.iter().cloned()
+    }
+
+    /// Every nonterminal of the grammar, in sorted order. This
+    /// includes the artificial start symbols introduced for each
+    /// public nonterminal (see `start_nonterminals`).
+    pub fn nonterminals(&self) -> impl Iterator<Item = NonterminalString> + '_ {
+        self.nonterminals.keys().cloned()
+    }
+
     pub fn productions_for(&self, nonterminal: &NonterminalString) -> &[Production] {
         match self.nonterminals.get(nonterminal) {
             Some(v) => &v.productions[..],
@@ -717,6 +789,58 @@ impl Grammar {
         }
     }
 
+    /// Returns a start symbol for building LR states that derives
+    /// `start` and appears in no production, adding `__start = start`
+    /// if needed. With such a symbol, only the reduction of its single
+    /// production on EOF accepts the input. The public nonterminals of
+    /// a lowered grammar already have one (see `start_nonterminals`),
+    /// which is returned as is.
+    pub fn augment_start(&mut self, start: &NonterminalString) -> NonterminalString {
+        if let Some(augmented) = self.start_nonterminals.get(start) {
+            return augmented.clone();
+        }
+        let referenced = self
+            .nonterminals
+            .values()
+            .flat_map(|data| &data.productions)
+            .flat_map(|production| &production.symbols)
+            .any(|symbol| *symbol == Symbol::Nonterminal(start.clone()));
+        if !referenced && self.productions_for(start).len() == 1 {
+            return start.clone();
+        }
+
+        let augmented = NonterminalString(Atom::from(format!("{}{}", self.prefix, start)));
+        let ty = self.types.nonterminal_type(start).clone();
+        let action = ActionFn::new(self.action_fn_defns.len());
+        self.action_fn_defns.push(ActionFnDefn {
+            fallible: false,
+            ret_type: ty.clone(),
+            kind: ActionFnDefnKind::User(UserActionFnDefn {
+                arg_patterns: vec![Name::immut(Atom::from(format!("{}0", self.prefix)))],
+                arg_types: vec![ty.clone()],
+                code: format!("{}0", self.prefix),
+            }),
+        });
+        self.types.add_type(augmented.clone(), ty);
+        let span = self.nonterminals[start].span;
+        self.nonterminals.insert(
+            augmented.clone(),
+            NonterminalData {
+                name: augmented.clone(),
+                visibility: Visibility::Priv,
+                span,
+                annotations: vec![],
+                productions: vec![Production {
+                    nonterminal: augmented.clone(),
+                    symbols: vec![Symbol::Nonterminal(start.clone())],
+                    action,
+                    span,
+                }],
+            },
+        );
+        augmented
+    }
+
     pub fn user_parameter_refs(&self) -> String {
         let mut result = String::new();
         for parameter in &self.parameters {
@@ -744,7 +868,33 @@ impl Default for Algorithm {
     fn default() -> Self {
         Algorithm {
             lalr: false,
+            slr: false,
             codegen: LrCodeGeneration::TableDriven,
+            push_parser: false,
+            incremental: false,
+            event_parser: false,
+            partial_results: false,
+            parse_prefix: false,
+            parse_items: false,
+            count_reductions: false,
+            narrow_expected: false,
+            compact_tables: false,
+            recursion_limit: None,
+            reduction_limit: None,
+            stack_capacity: None,
+            state_modules: None,
+            trace: false,
+            parser_trait: false,
+            slice_input: false,
+            first_sets: false,
+            action_trait: None,
+            expected_conflicts: None,
+            prefer: Prefer::Shift,
+            disambiguate: None,
+            backtrack: None,
+            token_names: false,
+            parse_str: None,
+            classify: None,
         }
     }
 }
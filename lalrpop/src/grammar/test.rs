@@ -0,0 +1,96 @@
+#![cfg(test)]
+
+use crate::grammar::parse_tree::TerminalLiteral;
+use crate::test_util::{expect_debug, normalized_grammar};
+use crate::tls::Tls;
+
+#[test]
+fn terminals_and_nonterminals() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+pub Expr: () = {
+    "let" Id "=" Expr "in" Expr => (),
+    Id => (),
+};
+
+Id: () = r"[a-z]+" => ();
+"#,
+    );
+
+    let keywords: Vec<_> = grammar
+        .terminals()
+        .filter_map(|t| match t.as_literal() {
+            Some(TerminalLiteral::Quoted(s)) => Some(s),
+            _ => None,
+        })
+        .collect();
+    expect_debug(
+        &keywords,
+        r#"[
+    Atom('=' type=inline),
+    Atom('in' type=inline),
+    Atom('let' type=inline),
+]"#,
+    );
+
+    let nonterminals: Vec<_> = grammar.nonterminals().map(|nt| nt.to_string()).collect();
+    assert_eq!(nonterminals, vec!["Expr", "Id", "__Expr"]);
+}
+
+#[test]
+fn visit_terminal_uses() {
+    use crate::collections::Map;
+    use crate::grammar::repr::{NonterminalData, TerminalString};
+    use crate::grammar::visit::{walk_grammar, walk_nonterminal, GrammarVisitor};
+
+    // counts how often each terminal is used, outside of `Id`
+    #[derive(Default)]
+    struct TerminalUses {
+        counts: Map<String, usize>,
+    }
+
+    impl<'grammar> GrammarVisitor<'grammar> for TerminalUses {
+        fn visit_nonterminal(&mut self, data: &'grammar NonterminalData) {
+            if data.name.to_string() != "Id" {
+                walk_nonterminal(self, data);
+            }
+        }
+
+        fn visit_terminal(&mut self, terminal: &'grammar TerminalString) {
+            *self.counts.entry(terminal.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+pub Expr: () = {
+    "let" Id "=" Expr "in" Expr => (),
+    "(" Expr "," Expr ")" => (),
+    "(" Expr ")" => (),
+    Id => (),
+};
+
+Id: () = r"[a-z]+" => ();
+"#,
+    );
+
+    let mut visitor = TerminalUses::default();
+    walk_grammar(&grammar, &mut visitor);
+    expect_debug(
+        &visitor.counts,
+        r#"{
+    "\"(\"": 2,
+    "\")\"": 2,
+    "\",\"": 1,
+    "\"=\"": 1,
+    "\"in\"": 1,
+    "\"let\"": 1,
+}"#,
+    );
+}
@@ -0,0 +1,68 @@
+//! A visitor over the nonterminals, productions and symbols of a
+//! lowered grammar, for analyses that need to look at each of them in
+//! turn. Implementors override the methods for the parts they are
+//! interested in; the default methods walk down into the rest.
+
+use crate::grammar::repr::{
+    Grammar, NonterminalData, NonterminalString, Production, Symbol, TerminalString,
+};
+
+pub trait GrammarVisitor<'grammar> {
+    /// Called for the definition of each nonterminal, in sorted order.
+    fn visit_nonterminal(&mut self, data: &'grammar NonterminalData) {
+        walk_nonterminal(self, data)
+    }
+
+    fn visit_production(&mut self, production: &'grammar Production) {
+        walk_production(self, production)
+    }
+
+    fn visit_symbol(&mut self, symbol: &'grammar Symbol) {
+        walk_symbol(self, symbol)
+    }
+
+    /// Called for each use of a terminal in a production.
+    fn visit_terminal(&mut self, _terminal: &'grammar TerminalString) {}
+
+    /// Called for each use of a nonterminal in a production.
+    fn visit_nonterminal_symbol(&mut self, _nonterminal: &'grammar NonterminalString) {}
+}
+
+/// Visits every nonterminal of `grammar`, and through the default
+/// methods, each of their productions and the symbols in them.
+pub fn walk_grammar<'grammar, V>(grammar: &'grammar Grammar, visitor: &mut V)
+where
+    V: GrammarVisitor<'grammar> + ?Sized,
+{
+    for data in grammar.nonterminals.values() {
+        visitor.visit_nonterminal(data);
+    }
+}
+
+pub fn walk_nonterminal<'grammar, V>(visitor: &mut V, data: &'grammar NonterminalData)
+where
+    V: GrammarVisitor<'grammar> + ?Sized,
+{
+    for production in &data.productions {
+        visitor.visit_production(production);
+    }
+}
+
+pub fn walk_production<'grammar, V>(visitor: &mut V, production: &'grammar Production)
+where
+    V: GrammarVisitor<'grammar> + ?Sized,
+{
+    for symbol in &production.symbols {
+        visitor.visit_symbol(symbol);
+    }
+}
+
+pub fn walk_symbol<'grammar, V>(visitor: &mut V, symbol: &'grammar Symbol)
+where
+    V: GrammarVisitor<'grammar> + ?Sized,
+{
+    match *symbol {
+        Symbol::Terminal(ref terminal) => visitor.visit_terminal(terminal),
+        Symbol::Nonterminal(ref nonterminal) => visitor.visit_nonterminal_symbol(nonterminal),
+    }
+}
@@ -3,10 +3,93 @@ use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::hash::Hash;
 
+/// A queue of work in which each item is processed at most once, for
+/// algorithms that explore a graph (such as an automaton) until no new
+/// nodes turn up.
+///
+/// Items are numbered in the order in which they are first seen, and
+/// `pop` hands the queued ones back out in that same (FIFO) order, so
+/// the numbering does not depend on any hashing.
+///
+/// ```
+/// use lalrpop::WorkList;
+///
+/// let mut work = WorkList::new();
+/// assert_eq!(work.insert("a"), 0);
+/// assert_eq!(work.insert("b"), 1);
+/// assert_eq!(work.insert("a"), 0); // already seen, not queued again
+/// assert_eq!(work.pop(), Some("a"));
+/// assert_eq!(work.pop(), Some("b"));
+/// assert_eq!(work.pop(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct WorkList<T> {
+    queue: VecDeque<T>,
+    seen: Map<T, usize>,
+}
+
+impl<T: Clone + Ord> WorkList<T> {
+    pub fn new() -> WorkList<T> {
+        WorkList {
+            queue: VecDeque::new(),
+            seen: map(),
+        }
+    }
+
+    /// Returns the number of `item`, allocating the next number and
+    /// queueing the item if it has not been seen before.
+    pub fn insert(&mut self, item: T) -> usize {
+        let queue = &mut self.queue;
+        let next = self.seen.len();
+        *self.seen.entry(item.clone()).or_insert_with(|| {
+            queue.push_back(item);
+            next
+        })
+    }
+
+    /// Returns the number of `item`, allocating the next number if it
+    /// has not been seen before, but without queueing it: it is
+    /// treated as already processed.
+    pub fn mark_seen(&mut self, item: T) -> usize {
+        let next = self.seen.len();
+        *self.seen.entry(item).or_insert(next)
+    }
+
+    /// Whether `item` has been inserted or marked as seen.
+    pub fn is_seen(&self, item: &T) -> bool {
+        self.seen.contains_key(item)
+    }
+
+    /// Removes the oldest item that has not yet been processed.
+    pub fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    /// The number of distinct items seen so far.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+impl<T: Clone + Ord> Default for WorkList<T> {
+    fn default() -> Self {
+        WorkList::new()
+    }
+}
+
+/// Assigns indices to kernels and hands them back out for processing.
+///
+/// Kernels are numbered in the order in which they are first added,
+/// and `next` yields them in that same (FIFO) order. As the state
+/// builders add the successors of each state in a fixed order, this
+/// makes the numbering a breadth-first traversal of the automaton
+/// starting from the start state, independent of any hashing.
 pub struct KernelSet<K: Kernel> {
-    counter: usize,
-    kernels: VecDeque<K>,
-    map: Map<K, K::Index>,
+    kernels: WorkList<K>,
 }
 
 pub trait Kernel: Clone + Debug + Hash + Eq + PartialOrd + Ord {
@@ -18,24 +101,38 @@ pub trait Kernel: Clone + Debug + Hash + Eq + PartialOrd + Ord {
 impl<K: Kernel> KernelSet<K> {
     pub fn new() -> KernelSet<K> {
         KernelSet {
-            kernels: VecDeque::new(),
-            map: map(),
-            counter: 0,
+            kernels: WorkList::new(),
         }
     }
 
+    /// Returns the index of `kernel`, allocating the next index and
+    /// queueing the kernel if it has not been seen before.
     pub fn add_state(&mut self, kernel: K) -> K::Index {
-        let kernels = &mut self.kernels;
-        let counter = &mut self.counter;
-        *self.map.entry(kernel.clone()).or_insert_with(|| {
-            let index = *counter;
-            *counter += 1;
-            kernels.push_back(kernel);
-            K::index(index)
-        })
+        K::index(self.kernels.insert(kernel))
     }
 
+    /// Removes the oldest kernel that has not yet been processed.
     pub fn next(&mut self) -> Option<K> {
-        self.kernels.pop_front()
+        self.kernels.pop()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WorkList;
+
+    #[test]
+    fn mark_seen() {
+        let mut work = WorkList::new();
+        assert_eq!(work.mark_seen(3), 0);
+        assert_eq!(work.insert(1), 1);
+        assert_eq!(work.insert(3), 0);
+        assert!(work.is_seen(&3));
+        assert!(!work.is_seen(&2));
+        assert_eq!(work.len(), 2);
+
+        // only the item that was inserted is processed
+        assert_eq!(work.pop(), Some(1));
+        assert_eq!(work.pop(), None);
     }
 }
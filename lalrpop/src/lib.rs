@@ -35,8 +35,12 @@ mod generate;
 #[cfg(test)]
 mod test_util;
 
+pub use crate::api::analysis;
+pub use crate::api::automaton;
 pub use crate::api::process_root;
 #[allow(deprecated)]
 pub use crate::api::process_root_unconditionally;
 pub use crate::api::Configuration;
+pub use crate::api::{Conflict, ConflictResolver, ProductionIndex, ReportEverything, Resolution};
+pub use crate::kernel_set::WorkList;
 use ascii_canvas::style;
@@ -0,0 +1,42 @@
+//! Copies the results of the grammar analyses into the plain data of
+//! `api::analysis`, which does not borrow the grammar.
+
+use crate::api::analysis;
+use crate::grammar::repr::*;
+use crate::lr1::automaton::copy_productions;
+use crate::lr1::first::{nullable_set, FollowSets};
+
+/// Analyzes `grammar` as seen from `start`, as
+/// `Configuration::analyze_grammar` does.
+pub fn analyze_grammar(
+    grammar: &Grammar,
+    start: NonterminalString,
+) -> Result<analysis::Grammar, String> {
+    let follow_sets = FollowSets::new(grammar, &start);
+    Ok(analysis::Grammar {
+        terminals: grammar
+            .terminals()
+            .map(|terminal| terminal.to_string())
+            .collect(),
+        nonterminals: grammar
+            .nonterminals()
+            .map(|nonterminal| nonterminal.to_string())
+            .collect(),
+        productions: copy_productions(grammar),
+        nullable: nullable_set(grammar)
+            .iter()
+            .map(|nonterminal| nonterminal.to_string())
+            .collect(),
+        follow: grammar
+            .nonterminals
+            .keys()
+            .map(|nonterminal| {
+                let tokens = follow_sets.follow(nonterminal).iter();
+                (
+                    nonterminal.to_string(),
+                    tokens.map(|token| token.to_string()).collect(),
+                )
+            })
+            .collect(),
+    })
+}
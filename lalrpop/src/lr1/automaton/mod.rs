@@ -0,0 +1,77 @@
+//! Copies LR(1) states into the plain data of `api::automaton`, which
+//! does not borrow the grammar.
+
+use crate::api::automaton::{self, Automaton};
+use crate::collections::{Map, Set};
+use crate::grammar::repr::*;
+use crate::lr1::build::build_observed_lr1_states;
+use crate::lr1::core::*;
+use crate::lr1::lookahead::TokenSet;
+
+/// Builds the canonical LR(1) states of `start`, as
+/// `Configuration::build_automaton` does. Conflicts do not stop the
+/// construction, so only a malformed state or the progress callback
+/// cancelling it makes this fail.
+pub fn build_automaton(grammar: &Grammar, start: NonterminalString) -> Result<Automaton, String> {
+    let states = build_observed_lr1_states(grammar, start).map_err(|error| error.to_string())?;
+    let ids = ProductionId::all(grammar);
+    Ok(Automaton {
+        productions: copy_productions(grammar),
+        states: states.iter().map(|state| copy_state(&ids, state)).collect(),
+    })
+}
+
+/// Copies the productions of `grammar`, in the order of `ProductionId`.
+pub fn copy_productions(grammar: &Grammar) -> Vec<automaton::Production> {
+    grammar
+        .nonterminals
+        .values()
+        .flat_map(|data| &data.productions)
+        .map(|production| automaton::Production {
+            nonterminal: production.nonterminal.to_string(),
+            symbols: production.symbols.iter().map(Symbol::to_string).collect(),
+        })
+        .collect()
+}
+
+/// Copies `state`, referring to each production by its index in `ids`.
+pub fn copy_state(ids: &Map<&Production, ProductionId>, state: &Lr1State<'_>) -> automaton::State {
+    let kernel: Set<&Lr1Item<'_>> = state.kernel_items().collect();
+    let closure = state.items.vec.iter().filter(|item| !kernel.contains(item));
+    automaton::State {
+        index: state.index.0,
+        items: kernel
+            .iter()
+            .copied()
+            .chain(closure)
+            .map(|item| automaton::Item {
+                production: ids[item.production].0,
+                index: item.index,
+                lookahead: tokens(&item.lookahead),
+            })
+            .collect(),
+        kernel_len: kernel.len(),
+        shifts: state
+            .shifts
+            .iter()
+            .map(|(terminal, next)| (terminal.to_string(), next.0))
+            .collect(),
+        gotos: state
+            .gotos
+            .iter()
+            .map(|(nonterminal, next)| (nonterminal.to_string(), next.0))
+            .collect(),
+        reductions: state
+            .reductions
+            .iter()
+            .map(|(lookahead, production)| automaton::Reduction {
+                production: ids[production].0,
+                lookahead: tokens(lookahead),
+            })
+            .collect(),
+    }
+}
+
+fn tokens(set: &TokenSet) -> Vec<String> {
+    set.iter().map(|token| token.to_string()).collect()
+}
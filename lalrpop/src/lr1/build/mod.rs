@@ -3,12 +3,16 @@
 use crate::collections::{map, Multimap};
 use crate::grammar::repr::*;
 use crate::kernel_set;
+use crate::lr1::automaton::copy_state;
 use crate::lr1::core::*;
 use crate::lr1::first;
 use crate::lr1::lane_table::*;
 use crate::lr1::lookahead::*;
 use crate::tls::Tls;
+use std::cell::{Cell, RefCell};
 use std::env;
+use std::fmt;
+use std::rc::Rc;
 
 #[cfg(test)]
 mod test;
@@ -23,6 +27,76 @@ fn build_lr1_states_legacy<'grammar>(
     lr1.build_states()
 }
 
+/// Builds the canonical (Knuth) LR(1) states, carrying on past any
+/// conflicts so that the full automaton is always available.
+pub fn build_canonical_lr1_states(grammar: &Grammar, start: NonterminalString) -> Lr1Result<'_> {
+    let lr1: Lr<'_, TokenSet> = Lr::new(grammar, start, TokenSet::eof());
+    lr1.build_states()
+}
+
+/// Builds the canonical LR(1) states for `Configuration::build_automaton`,
+/// as `build_canonical_lr1_states` does, reporting each state to the
+/// callbacks set on the configuration.
+pub fn build_observed_lr1_states(
+    grammar: &Grammar,
+    start: NonterminalString,
+) -> Result<Vec<Lr1State<'_>>, CheckError<'_>> {
+    let mut lr1: Lr<'_, TokenSet> = Lr::new(grammar, start, TokenSet::eof());
+    let cancelled = lr1.observe_session();
+    match lr1.build_states() {
+        Ok(states) => Ok(states),
+        Err(TableConstructionError {
+            inconsistency: Some(inconsistency),
+            ..
+        }) => Err(CheckError::Inconsistency(inconsistency)),
+        Err(_) if cancelled.get() => Err(CheckError::Cancelled),
+        Err(error) => Ok(error.states),
+    }
+}
+
+/// Checks whether the grammar is LR(1), returning the conflicts of the
+/// canonical LR(1) automaton, which are none if it is. Each state is
+/// dropped as soon as its conflicts are known, so this needs much less
+/// memory than `build_canonical_lr1_states` for a large grammar. Each
+/// state is reported to the callbacks set on the configuration.
+pub fn check_lr1(
+    grammar: &Grammar,
+    start: NonterminalString,
+) -> Result<Vec<Lr1Conflict<'_>>, CheckError<'_>> {
+    let mut lr1: Lr<'_, TokenSet> = Lr::new(grammar, start, TokenSet::eof());
+    lr1.set_retain_states(false);
+    let cancelled = lr1.observe_session();
+    match lr1.build_states() {
+        Ok(_) => Ok(vec![]),
+        Err(TableConstructionError {
+            inconsistency: Some(inconsistency),
+            ..
+        }) => Err(CheckError::Inconsistency(inconsistency)),
+        Err(_) if cancelled.get() => Err(CheckError::Cancelled),
+        Err(error) => Ok(error.conflicts),
+    }
+}
+
+/// Why `check_lr1` or `build_observed_lr1_states` have no result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckError<'grammar> {
+    Inconsistency(InternalInconsistency<'grammar>),
+
+    /// The progress callback returned `false`.
+    Cancelled,
+}
+
+impl fmt::Display for CheckError<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckError::Inconsistency(inconsistency) => {
+                write!(fmt, "internal inconsistency: {:?}", inconsistency)
+            }
+            CheckError::Cancelled => write!(fmt, "state construction was cancelled"),
+        }
+    }
+}
+
 type ConstructionFunction<'grammar> =
     fn(&'grammar Grammar, NonterminalString) -> Lr1Result<'grammar>;
 
@@ -57,12 +131,44 @@ pub fn build_lr0_states(
     lr1.build_states()
 }
 
+/// Records the shift or goto from `state` on `symbol`. There cannot be
+/// two transitions on the same symbol (a shift/shift conflict, in
+/// effect); if there already is one, the state is malformed.
+fn add_transition<'grammar, L: Lookahead>(
+    state: &mut State<'grammar, L>,
+    symbol: Symbol,
+    next_state: StateIndex,
+) -> Result<(), InternalInconsistency<'grammar>> {
+    let prev = match symbol {
+        Symbol::Terminal(ref s) => state.shifts.insert(s.clone(), next_state),
+        Symbol::Nonterminal(ref s) => state.gotos.insert(s.clone(), next_state),
+    };
+    match prev {
+        None => Ok(()),
+        Some(_) => Err(InternalInconsistency::DuplicateTransition {
+            state: state.index,
+            symbol,
+        }),
+    }
+}
+
+/// Called with the index of each new state and the number of
+/// conflicts found so far; returning `false` cancels construction.
+pub type ProgressCallback<'a> = Box<dyn FnMut(StateIndex, usize) -> bool + 'a>;
+
+/// Called with each state once its items, transitions and reductions
+/// are complete, before the next state is constructed.
+pub type StateObserver<'a, 'grammar, L> = Box<dyn FnMut(&State<'grammar, L>) + 'a>;
+
 pub struct Lr<'grammar, L: LookaheadBuild> {
     grammar: &'grammar Grammar,
     first_sets: first::FirstSets,
     start_nt: NonterminalString,
     start_lookahead: L,
     permit_early_stop: bool,
+    retain_states: bool,
+    progress: Option<RefCell<ProgressCallback<'grammar>>>,
+    observer: Option<RefCell<StateObserver<'grammar, 'grammar, L>>>,
 }
 
 impl<'grammar, L: LookaheadBuild> Lr<'grammar, L> {
@@ -73,6 +179,9 @@ impl<'grammar, L: LookaheadBuild> Lr<'grammar, L> {
             start_nt,
             start_lookahead,
             permit_early_stop: false,
+            retain_states: true,
+            progress: None,
+            observer: None,
         }
     }
 
@@ -80,11 +189,32 @@ impl<'grammar, L: LookaheadBuild> Lr<'grammar, L> {
         self.permit_early_stop = v;
     }
 
+    /// If `false`, states are dropped once their conflicts have been
+    /// found, and construction only yields the conflicts.
+    fn set_retain_states(&mut self, v: bool) {
+        self.retain_states = v;
+    }
+
+    /// Installs a callback that observes each state as it is
+    /// constructed. If it cancels construction, `build_states`
+    /// returns the states built so far as an error.
+    fn set_progress_callback(&mut self, callback: ProgressCallback<'grammar>) {
+        self.progress = Some(RefCell::new(callback));
+    }
+
+    /// Installs a callback that is shown each state as soon as it is
+    /// complete, e.g. to draw the automaton while it grows.
+    fn set_state_observer(&mut self, observer: StateObserver<'grammar, 'grammar, L>) {
+        self.observer = Some(RefCell::new(observer));
+    }
+
     fn build_states(&self) -> Result<Vec<State<'grammar, L>>, TableConstructionError<'grammar, L>> {
         let session = Tls::session();
         let mut kernel_set = kernel_set::KernelSet::new();
         let mut states = vec![];
+        let mut state_count = 0;
         let mut conflicts = vec![];
+        let mut cancelled = false;
 
         // create the starting state
         kernel_set.add_state(Kernel::start(self.items(
@@ -93,80 +223,44 @@ impl<'grammar, L: LookaheadBuild> Lr<'grammar, L> {
             &self.start_lookahead,
         )));
 
-        while let Some(Kernel { items: seed_items }) = kernel_set.next() {
-            let items = self.transitive_closure(seed_items);
-            let index = StateIndex(states.len());
+        while let Some(kernel) = kernel_set.next() {
+            let index = StateIndex(state_count);
+            state_count += 1;
 
             if index.0 % 5000 == 0 && index.0 > 0 {
                 log!(session, Verbose, "{} states created so far.", index.0);
             }
 
-            let mut this_state = State {
-                index,
-                items: items.clone(),
-                shifts: map(),
-                reductions: vec![],
-                gotos: map(),
-            };
-
-            // group the items that we can transition into by shifting
-            // over a term or nonterm
-            let transitions: Multimap<Symbol, Multimap<Lr0Item<'grammar>, L>> = items
-                .vec
-                .iter()
-                .filter_map(Item::shifted_item)
-                .map(
-                    |(
-                        symbol,
-                        Item {
-                            production,
-                            index,
-                            lookahead,
-                        },
-                    )| {
-                        (symbol.clone(), (Item::lr0(production, index), lookahead))
-                    },
-                )
-                .collect();
-
-            for (symbol, shifted_items) in transitions.into_iter() {
-                let shifted_items: Vec<Item<'grammar, L>> = shifted_items
-                    .into_iter()
-                    .map(|(lr0_item, lookahead)| lr0_item.with_lookahead(lookahead))
-                    .collect();
-
-                // Not entirely obvious: if the original set of items
-                // is sorted to begin with (and it is), then this new
-                // set of shifted items is *also* sorted. This is
-                // because it is produced from the old items by simply
-                // incrementing the index by 1.
-                let next_state = kernel_set.add_state(Kernel::shifted(shifted_items));
-
-                match symbol {
-                    Symbol::Terminal(s) => {
-                        let prev = this_state.shifts.insert(s, next_state);
-                        assert!(prev.is_none()); // cannot have a shift/shift conflict
-                    }
-
-                    Symbol::Nonterminal(s) => {
-                        let prev = this_state.gotos.insert(s, next_state);
-                        assert!(prev.is_none());
-                    }
-                }
-            }
-
-            // finally, consider the reductions
-            for item in items.vec.iter().filter(|i| i.can_reduce()) {
-                this_state
-                    .reductions
-                    .push((item.lookahead.clone(), item.production));
+            let mut this_state = self.expand_kernel(index, kernel);
+            if let Err(inconsistency) = self.add_successors(&mut kernel_set, &mut this_state) {
+                states.push(this_state);
+                return Err(TableConstructionError {
+                    states,
+                    conflicts,
+                    inconsistency: Some(inconsistency),
+                    invalid_resolution: None,
+                });
             }
 
             // check for shift-reduce conflicts (reduce-reduce detected above)
             conflicts.extend(L::conflicts(&this_state));
 
+            if let Some(ref observer) = self.observer {
+                (observer.borrow_mut())(&this_state);
+            }
+
             // extract a new state
-            states.push(this_state);
+            if self.retain_states {
+                states.push(this_state);
+            }
+
+            if let Some(ref progress) = self.progress {
+                if !(progress.borrow_mut())(index, conflicts.len()) {
+                    log!(session, Verbose, "State construction cancelled.");
+                    cancelled = true;
+                    break;
+                }
+            }
 
             if self.permit_early_stop && session.stop_after(conflicts.len()) {
                 log!(
@@ -179,13 +273,81 @@ impl<'grammar, L: LookaheadBuild> Lr<'grammar, L> {
             }
         }
 
-        if !conflicts.is_empty() {
-            Err(TableConstructionError { states, conflicts })
+        if !conflicts.is_empty() || cancelled {
+            Err(TableConstructionError {
+                states,
+                conflicts,
+                inconsistency: None,
+                invalid_resolution: None,
+            })
         } else {
             Ok(states)
         }
     }
 
+    /// Creates the state for `kernel`, with the items it expands to
+    /// but without any transitions or reductions yet.
+    fn expand_kernel(&self, index: StateIndex, kernel: Kernel<'grammar, L>) -> State<'grammar, L> {
+        State {
+            index,
+            items: self.transitive_closure(kernel.items),
+            shifts: map(),
+            reductions: vec![],
+            gotos: map(),
+        }
+    }
+
+    /// Adds the transitions and reductions of `this_state`, queueing
+    /// the kernels of its successors in `kernel_set`.
+    fn add_successors(
+        &self,
+        kernel_set: &mut kernel_set::KernelSet<Kernel<'grammar, L>>,
+        this_state: &mut State<'grammar, L>,
+    ) -> Result<(), InternalInconsistency<'grammar>> {
+        // group the items that we can transition into by shifting
+        // over a term or nonterm
+        let transitions: Multimap<Symbol, Multimap<Lr0Item<'grammar>, L>> = this_state
+            .items
+            .vec
+            .iter()
+            .filter_map(Item::shifted_item)
+            .map(
+                |(
+                    symbol,
+                    Item {
+                        production,
+                        index,
+                        lookahead,
+                    },
+                )| { (symbol.clone(), (Item::lr0(production, index), lookahead)) },
+            )
+            .collect();
+
+        for (symbol, shifted_items) in transitions.into_iter() {
+            let shifted_items: Vec<Item<'grammar, L>> = shifted_items
+                .into_iter()
+                .map(|(lr0_item, lookahead)| lr0_item.with_lookahead(lookahead))
+                .collect();
+
+            // Not entirely obvious: if the original set of items
+            // is sorted to begin with (and it is), then this new
+            // set of shifted items is *also* sorted. This is
+            // because it is produced from the old items by simply
+            // incrementing the index by 1.
+            let next_state = kernel_set.add_state(Kernel::shifted(shifted_items));
+
+            add_transition(this_state, symbol, next_state)?;
+        }
+
+        // finally, consider the reductions
+        for item in this_state.items.vec.iter().filter(|i| i.can_reduce()) {
+            this_state
+                .reductions
+                .push((item.lookahead.clone(), item.production));
+        }
+        Ok(())
+    }
+
     fn items(&self, id: &NonterminalString, index: usize, lookahead: &L) -> Vec<Item<'grammar, L>> {
         self.grammar
             .productions_for(id)
@@ -249,7 +411,7 @@ impl<'grammar, L: LookaheadBuild> Lr<'grammar, L> {
 
         let final_items = map
             .into_iter()
-            .map(|(lr0_item, lookahead)| lr0_item.with_lookahead(lookahead))
+            .map(|(lr0_item, lookahead)| lr0_item.with_lookahead(L::intern(lookahead)))
             .collect();
 
         Items { vec: final_items }
@@ -281,6 +443,28 @@ struct Kernel<'grammar, L: LookaheadBuild> {
     items: Vec<Item<'grammar, L>>,
 }
 
+impl<'grammar> Lr<'grammar, TokenSet> {
+    /// Installs the callbacks set on the configuration, if any. The
+    /// returned flag is raised if the progress callback cancels
+    /// construction.
+    fn observe_session(&mut self) -> Rc<Cell<bool>> {
+        let cancelled = Rc::new(Cell::new(false));
+        if let Some(progress) = Tls::session().progress_callback.clone() {
+            let cancelled = cancelled.clone();
+            self.set_progress_callback(Box::new(move |index, conflicts| {
+                let go_on = progress(index.0, conflicts);
+                cancelled.set(!go_on);
+                go_on
+            }));
+        }
+        if let Some(observer) = Tls::session().state_observer.clone() {
+            let ids = ProductionId::all(self.grammar);
+            self.set_state_observer(Box::new(move |state| observer(&copy_state(&ids, state))));
+        }
+        cancelled
+    }
+}
+
 impl<'grammar, L: LookaheadBuild> Kernel<'grammar, L> {
     pub fn start(items: Vec<Item<'grammar, L>>) -> Kernel<'grammar, L> {
         // In start state, kernel should have only items with `index == 0`.
@@ -325,6 +509,10 @@ pub trait LookaheadBuild: Lookahead {
         remainder: &[Symbol],
         lookahead: &Self,
     ) -> Vec<Item<'grammar, Self>>;
+
+    /// Returns a lookahead equal to `lookahead` that may share its
+    /// storage with lookaheads of other states.
+    fn intern(lookahead: Self) -> Self;
 }
 
 impl LookaheadBuild for Nil {
@@ -336,6 +524,10 @@ impl LookaheadBuild for Nil {
     ) -> Vec<Lr0Item<'grammar>> {
         lr.items(nt, 0, lookahead)
     }
+
+    fn intern(lookahead: Nil) -> Nil {
+        lookahead
+    }
 }
 
 impl LookaheadBuild for TokenSet {
@@ -348,4 +540,8 @@ impl LookaheadBuild for TokenSet {
         let first_set = lr.first_sets.first1(remainder, lookahead);
         lr.items(nt, 0, &first_set)
     }
+
+    fn intern(lookahead: TokenSet) -> TokenSet {
+        lookahead.intern()
+    }
 }
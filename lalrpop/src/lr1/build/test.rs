@@ -1,15 +1,19 @@
 use crate::generate;
 use crate::grammar::repr::*;
 use crate::lr1::core::*;
-use crate::lr1::interpret::interpret;
+use crate::lr1::interpret::{interpret, LookaheadInterpret};
 use crate::lr1::lookahead::Token;
-use crate::lr1::lookahead::TokenSet;
+use crate::lr1::lookahead::{Lookahead, TokenSet};
+use crate::lr1::state_graph::StateGraph;
 use crate::lr1::tls::Lr1Tls;
 use crate::test_util::{compare, expect_debug, normalized_grammar};
 use crate::tls::Tls;
 use string_cache::DefaultAtom as Atom;
 
-use super::{build_lr0_states, build_lr1_states, use_lane_table, Lr};
+use super::{
+    add_transition, build_canonical_lr1_states, build_lr0_states, build_lr1_states, check_lr1,
+    use_lane_table, Lr,
+};
 
 fn nt(t: &str) -> NonterminalString {
     NonterminalString(Atom::from(t))
@@ -274,6 +278,58 @@ T: () = {
     build_lr0_states(&grammar, nt("S")).unwrap_err();
 }
 
+#[test]
+fn lr0_state_conflicts() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+S: () = {
+    E,
+    A "x",
+    B "y",
+    C "x",
+    D "y",
+    C "z" "z",
+};
+
+E: () = {
+    E "-" "N",
+    "M",
+};
+
+A: () = "N";
+B: () = "N";
+C: () = "O";
+D: () = { "O", "O" "z" };
+"#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let err = build_lr0_states(&grammar, nt("S")).unwrap_err();
+    let conflicts: Vec<_> = err
+        .state_conflicts()
+        .into_iter()
+        .map(|(index, conflict)| {
+            assert!(err.conflicts.iter().any(|c| c.state == index));
+            conflict.to_string()
+        })
+        .collect();
+    // after "O", the state can shift "z" as well as reduce `C` or `D`
+    expect_debug(
+        &conflicts,
+        r#"[
+    "shift/reduce conflict reducing `S = E`",
+    "reduce/reduce conflict reducing `A = \"N\"`, `B = \"N\"`",
+    "shift/reduce conflict reducing `C = \"O\"`, `D = \"O\"`",
+    "reduce/reduce conflict reducing `C = \"O\"`, `D = \"O\"`"
+]"#,
+    );
+}
+
 /// When we moved to storing items as (lr0 -> TokenSet) pairs, a bug
 /// in the transitive closure routine could cause us to have `(Foo,
 /// S0)` and `(Foo, S1)` as distinct items instead of `(Foo, S0|S1)`.
@@ -354,3 +410,774 @@ pub Query = SELECT r"[a-z]+";
     let states = build_lr0_states(&grammar, nt("Query")).expect("build states");
     println!("states: {:?}", states);
 }
+
+#[test]
+fn kernel_items() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+S: () = E "$";
+
+E: () = {
+    E "-" T,
+    T,
+};
+
+T: () = {
+    "N",
+    "(" E ")",
+};
+"#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let states = build_lr0_states(&grammar, nt("S")).unwrap();
+
+    // the start state has no shifted items, so its kernel is the
+    // item for the start symbol alone; the kernels of the others are
+    // their items where something has been shifted
+    let kernels: Vec<Vec<_>> = states
+        .iter()
+        .map(|state| state.kernel_items().collect())
+        .collect();
+    expect_debug(
+        &kernels,
+        r#"[
+    [
+        S = (*) E "$"
+    ],
+    [
+        E = E (*) "-" T,
+        S = E (*) "$"
+    ],
+    [
+        E = T (*)
+    ],
+    [
+        T = "(" (*) E ")"
+    ],
+    [
+        T = "N" (*)
+    ],
+    [
+        S = E "$" (*)
+    ],
+    [
+        E = E "-" (*) T
+    ],
+    [
+        E = E (*) "-" T,
+        T = "(" E (*) ")"
+    ],
+    [
+        E = E "-" T (*)
+    ],
+    [
+        T = "(" E ")" (*)
+    ]
+]"#,
+    );
+}
+
+#[test]
+fn shortest_path_to_conflict() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+S: () = {
+    "if" "E" "then" S,
+    "if" "E" "then" S "else" S,
+    "X",
+};
+"#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let err = build_lr1_states(&grammar, nt("S")).unwrap_err();
+    let graph = StateGraph::new(&err.states);
+    let conflict = &err.conflicts[0];
+    let prefix = graph.shortest_path(conflict.state).unwrap();
+    let prefix: Vec<_> = prefix.iter().map(|s| s.to_string()).collect();
+    assert_eq!(prefix, vec![r#""if""#, r#""E""#, r#""then""#, "S"]);
+
+    // the start state is reached by the empty prefix
+    assert_eq!(graph.shortest_path(StateIndex(0)), Some(vec![]));
+}
+
+#[test]
+fn inlining_reduces_states() {
+    let _tls = Tls::test();
+
+    // `#[inline]` is expanded during normalization, before any states
+    // are built; inlining `Pair` saves the goto state that would
+    // otherwise follow its reduction.
+
+    let grammar_text = |attr: &str| {
+        format!(
+            r#"
+grammar;
+
+S: () = "(" Pair<"N"> ")";
+
+{}
+Pair<T>: () = T "," T;
+"#,
+            attr
+        )
+    };
+
+    let count_states = |text: &str| {
+        let grammar = normalized_grammar(text);
+        let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+        build_lr1_states(&grammar, nt("S")).unwrap().len()
+    };
+
+    let outlined = count_states(&grammar_text(""));
+    let inlined = count_states(&grammar_text("#[inline]"));
+    assert!(
+        inlined < outlined,
+        "inlining produced {} states, expected fewer than {}",
+        inlined,
+        outlined
+    );
+}
+
+#[test]
+fn breadth_first_state_numbering() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+S: () = E;
+
+E: () = {
+    E "-" T,
+    T,
+};
+
+T: () = {
+    "N",
+    "(" E ")",
+};
+"#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let states = build_lr1_states(&grammar, nt("S")).unwrap();
+
+    // Replay a breadth-first walk from the start state, visiting the
+    // successors of each state in symbol order: every state must be
+    // numbered in the order in which the walk first reaches it.
+    let mut next_index = 1;
+    for state in &states {
+        let mut successors: Vec<(Symbol, StateIndex)> = state
+            .shifts
+            .iter()
+            .map(|(t, &s)| (Symbol::Terminal(t.clone()), s))
+            .chain(
+                state
+                    .gotos
+                    .iter()
+                    .map(|(nt, &s)| (Symbol::Nonterminal(nt.clone()), s)),
+            )
+            .collect();
+        successors.sort();
+        for (_, successor) in successors {
+            if successor.0 >= next_index {
+                assert_eq!(successor.0, next_index);
+                next_index += 1;
+            }
+        }
+    }
+    assert_eq!(next_index, states.len());
+
+    // and building again produces exactly the same automaton
+    let again = build_lr1_states(&grammar, nt("S")).unwrap();
+    assert_eq!(format!("{:?}", states), format!("{:?}", again));
+}
+
+#[test]
+fn item_display() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+
+S: () = E;
+
+E: () = {
+    E "-" "N",
+    "N",
+};
+"#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    // LR(0) items have no lookahead to show
+    let lr0_states = build_lr0_states(&grammar, nt("S")).unwrap_err().states;
+    let lr0_items: Vec<_> = lr0_states[0]
+        .items
+        .vec
+        .iter()
+        .map(|i| i.display())
+        .collect();
+    assert_eq!(
+        lr0_items,
+        vec![
+            "E = \u{2022} E \"-\" \"N\"",
+            "E = \u{2022} \"N\"",
+            "S = \u{2022} E"
+        ]
+    );
+
+    // while LR(1) items end with their lookahead
+    let lr0_item = lr0_states
+        .iter()
+        .flat_map(|s| &s.items.vec)
+        .find(|i| i.index == 2)
+        .unwrap();
+    let mut lookahead = TokenSet::eof();
+    lookahead.insert(Token::Terminal(TerminalString::quoted(Atom::from("-"))));
+    assert_eq!(
+        lr0_item.with_lookahead(lookahead).display(),
+        "E = E \"-\" \u{2022} \"N\" [\"-\", Eof]"
+    );
+}
+
+/// A second transition on the same symbol is reported as an error
+/// rather than asserted against.
+#[test]
+fn duplicate_transition() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+pub S: () = { "(" S ")" => (), "x" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let mut states = build_lr0_states(&grammar, nt("__S")).unwrap();
+    let state = &mut states[0];
+
+    let open = TerminalString::quoted(Atom::from("("));
+    let target = state.shifts[&open];
+    let err = add_transition(state, Symbol::Terminal(open.clone()), target).unwrap_err();
+    assert_eq!(
+        err,
+        InternalInconsistency::DuplicateTransition {
+            state: StateIndex(0),
+            symbol: Symbol::Terminal(open),
+        }
+    );
+
+    let s = nt("S");
+    let target = state.gotos[&s];
+    let err = add_transition(state, Symbol::Nonterminal(s.clone()), target).unwrap_err();
+    assert_eq!(
+        err,
+        InternalInconsistency::DuplicateTransition {
+            state: StateIndex(0),
+            symbol: Symbol::Nonterminal(s),
+        }
+    );
+}
+
+/// Equal lookahead sets are shared between the items of all states.
+#[test]
+fn lookaheads_are_shared() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "-" => .., "N" => .., "(" => .., ")" => .. } }
+    S: () = E => ();
+    E: () = { E "-" T => (), T => () };
+    T: () = { "N" => (), "(" E ")" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    let states = lr1.build_states().unwrap();
+
+    let lookaheads: Vec<&TokenSet> = states
+        .iter()
+        .flat_map(|state| state.items.vec.iter().map(|item| &item.lookahead))
+        .collect();
+    for a in &lookaheads {
+        for b in &lookaheads {
+            assert_eq!(a == b, a.shares_bits_with(b));
+        }
+    }
+}
+
+#[test]
+fn progress_callback() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "-" => .., "N" => .., "(" => .., ")" => .. } }
+    S: () = E => ();
+    E: () = { E "-" T => (), T => () };
+    T: () = { "N" => (), "(" E ")" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    // every state is reported, in order
+    let seen = std::cell::RefCell::new(vec![]);
+    let mut lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    lr1.set_progress_callback(Box::new(|index, conflicts| {
+        assert_eq!(conflicts, 0);
+        seen.borrow_mut().push(index.0);
+        true
+    }));
+    let states = lr1.build_states().unwrap();
+    assert_eq!(*seen.borrow(), (0..states.len()).collect::<Vec<_>>());
+
+    // returning false stops construction
+    let mut lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    lr1.set_progress_callback(Box::new(|index, _| index.0 < 2));
+    let err = lr1.build_states().unwrap_err();
+    assert_eq!(err.states.len(), 3);
+    assert!(err.conflicts.is_empty());
+}
+
+#[test]
+fn state_observer() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "-" => .., "N" => .., "(" => .., ")" => .. } }
+    S: () = E => ();
+    E: () = { E "-" T => (), T => () };
+    T: () = { "N" => (), "(" E ")" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    // each state is shown complete, in the order it is built
+    let seen = std::cell::RefCell::new(vec![]);
+    let mut lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    lr1.set_state_observer(Box::new(|state| {
+        seen.borrow_mut().push(format!("{:?}", state));
+    }));
+    let states = lr1.build_states().unwrap();
+    let built: Vec<_> = states.iter().map(|state| format!("{:?}", state)).collect();
+    assert_eq!(*seen.borrow(), built);
+
+    // states are shown even when they are not kept
+    let count = std::cell::Cell::new(0);
+    let mut lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    lr1.set_retain_states(false);
+    lr1.set_state_observer(Box::new(|_| count.set(count.get() + 1)));
+    assert!(lr1.build_states().unwrap().is_empty());
+    assert_eq!(count.get(), states.len());
+}
+
+/// Alternatives with a common prefix share the states for that prefix,
+/// since their items advance together; factoring the prefix out into a
+/// helper nonterminal only adds states for the helper.
+#[test]
+fn common_prefixes_share_states() {
+    let _tls = Tls::test();
+    let count_states = |text: &str| {
+        let grammar = normalized_grammar(text);
+        let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+        build_lr1_states(&grammar, nt("S")).unwrap().len()
+    };
+
+    let unfactored = count_states(
+        r#"
+grammar;
+    extern { enum Tok { "x" => .., "y" => .., "z" => .., "w" => .. } }
+    S: () = A => ();
+    A: () = { "x" "y" "z" => (), "x" "y" "w" => () };
+"#,
+    );
+    let factored = count_states(
+        r#"
+grammar;
+    extern { enum Tok { "x" => .., "y" => .., "z" => .., "w" => .. } }
+    S: () = A => ();
+    A: () = "x" "y" B => ();
+    B: () = { "z" => (), "w" => () };
+"#,
+    );
+    assert_eq!((unfactored, factored), (6, 7));
+}
+
+#[test]
+fn conflicts_by_state() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "+" => .., "*" => .., "N" => .. } }
+    S: () = E => ();
+    E: () = { E "+" E => (), E "*" E => (), "N" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    let err = lr1.build_states().unwrap_err();
+
+    let by_state = err.conflicts_by_state();
+    let expected: Vec<_> = err
+        .states
+        .iter()
+        .map(|state| (state.index, TokenSet::conflicts(state).len()))
+        .filter(|&(_, len)| len > 0)
+        .collect();
+    let actual: Vec<_> = by_state
+        .iter()
+        .map(|(&index, conflicts)| {
+            assert!(conflicts.iter().all(|conflict| conflict.state == index));
+            (index, conflicts.len())
+        })
+        .collect();
+    assert_eq!(actual, expected);
+
+    // both operators conflict with each other after either one
+    assert_eq!(actual.len(), 2);
+    assert!(actual.iter().all(|&(_, len)| len == 2));
+}
+
+#[test]
+fn reductions_merge_lookahead_by_production() {
+    // `A = "c"` is reduced with lookahead "x" or "y" in the same state.
+    // Items are keyed by their LR(0) core, so the lookaheads are
+    // unioned into a single reduction rather than one per context.
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "c" => .., "x" => .., "y" => .. } }
+    S: () = { A "x" => (), A "y" => () };
+    A: () = "c" => ();
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let mut expected = TokenSet::new();
+    for terminal in tokens!["x", "y"] {
+        expected.insert(Token::Terminal(terminal));
+    }
+    for states in [
+        build_lr1_states(&grammar, nt("S")).unwrap(),
+        build_canonical_lr1_states(&grammar, nt("S")).unwrap(),
+    ] {
+        for state in &states {
+            let mut productions: Vec<_> = state.reductions.iter().map(|&(_, p)| p).collect();
+            productions.sort();
+            productions.dedup();
+            assert_eq!(productions.len(), state.reductions.len());
+        }
+        let lookaheads: Vec<_> = states
+            .iter()
+            .flat_map(|state| &state.reductions)
+            .filter(|&&(_, p)| p.nonterminal == nt("A"))
+            .map(|(lookahead, _)| lookahead.clone())
+            .collect();
+        assert_eq!(lookaheads, vec![expected.clone()]);
+    }
+}
+
+#[test]
+fn check_lr1_matches_canonical_conflicts() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "+" => .., "*" => .., "N" => .. } }
+    S: () = E => ();
+    E: () = { E "+" E => (), E "*" E => (), "N" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let expected = build_canonical_lr1_states(&grammar, nt("S"))
+        .unwrap_err()
+        .conflicts;
+    assert!(!expected.is_empty());
+    assert_eq!(check_lr1(&grammar, nt("S")), Ok(expected));
+
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "+" => .., "N" => .. } }
+    S: () = E => ();
+    E: () = { E "+" "N" => (), "N" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    assert_eq!(check_lr1(&grammar, nt("S")), Ok(vec![]));
+}
+
+/// The recursive ascent code generator gives each state fn a fixed stack
+/// suffix, which relies on the prefix of every item in a state being a
+/// suffix of `max_prefix`, whichever way the states were built.
+#[test]
+fn item_prefixes_share_max_prefix() {
+    fn check<L: Lookahead>(states: &[State<'_, L>]) {
+        for state in states {
+            let max_prefix = state.max_prefix();
+            assert!(max_prefix.ends_with(state.will_pop()));
+            for item in &state.items.vec {
+                assert!(
+                    max_prefix.ends_with(item.prefix()),
+                    "{} does not end with {:?}",
+                    item.display(),
+                    max_prefix,
+                );
+            }
+        }
+    }
+
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "a" => .., "b" => .., "c" => .., "d" => .. } }
+    S: () = { X => (), "d" Y => () };
+    X: () = { "a" "b" "c" "c" => (), "a" Y => () };
+    Y: () = "b" "d" => ();
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    check(&build_canonical_lr1_states(&grammar, nt("S")).unwrap());
+    check(&build_lr1_states(&grammar, nt("S")).unwrap());
+    check(&build_lr0_states(&grammar, nt("S")).unwrap());
+
+    // some state mixes items with prefixes of different lengths
+    let states = build_lr1_states(&grammar, nt("S")).unwrap();
+    assert!(states
+        .iter()
+        .any(|state| state.max_prefix().len() > state.will_pop().len()));
+}
+
+#[test]
+fn degenerate_start_symbols() {
+    fn check_empty<L: LookaheadInterpret>(states: &[State<'_, L>]) {
+        assert_eq!(states.len(), 1);
+        assert!(states[0].shifts.is_empty());
+        let tree = interpret(states, tokens![]).unwrap();
+        assert_eq!(&format!("{}", tree)[..], "[Empty: ]");
+        assert!(interpret(states, tokens!["x"]).is_err());
+    }
+
+    fn check_single<L: LookaheadInterpret>(states: &[State<'_, L>]) {
+        assert_eq!(states.len(), 2);
+        let tree = interpret(states, tokens!["x"]).unwrap();
+        assert_eq!(&format!("{}", tree)[..], r#"[Single: "x"]"#);
+        assert!(interpret(states, tokens![]).is_err());
+        assert!(interpret(states, tokens!["x", "x"]).is_err());
+    }
+
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "x" => .. } }
+    Empty: () = () => ();
+    Single: () = "x" => ();
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    check_empty(&build_canonical_lr1_states(&grammar, nt("Empty")).unwrap());
+    check_empty(&build_lr1_states(&grammar, nt("Empty")).unwrap());
+    check_empty(&build_lr0_states(&grammar, nt("Empty")).unwrap());
+
+    check_single(&build_canonical_lr1_states(&grammar, nt("Single")).unwrap());
+    check_single(&build_lr1_states(&grammar, nt("Single")).unwrap());
+    check_single(&build_lr0_states(&grammar, nt("Single")).unwrap());
+}
+
+#[test]
+fn token_set_order() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "a" => .., "b" => .. } }
+    S: () = { "a" => (), "b" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    // the room reserved for the bits makes no difference
+    let mut small = TokenSet::from(Token::Terminal(TerminalString::quoted("b".into())));
+    let mut large = TokenSet::new();
+    large.reserve(1000);
+    large.insert(Token::Terminal(TerminalString::quoted("b".into())));
+    assert_eq!(small, large);
+    assert_eq!(small.cmp(&large), std::cmp::Ordering::Equal);
+    let hash = |set: &TokenSet| {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        std::hash::Hash::hash(set, &mut hasher);
+        std::hash::Hasher::finish(&hasher)
+    };
+    assert_eq!(hash(&small), hash(&large));
+
+    // sets compare by their members, in order, like sorted lists
+    let a = TokenSet::from(Token::Terminal(TerminalString::quoted("a".into())));
+    let mut a_b = a.clone();
+    a_b.union_with(&small);
+    small.insert_eof();
+    assert!(a < a_b);
+    assert!(a_b < large);
+    assert!(large < small);
+}
+
+#[test]
+fn token_set_operations() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    // enough terminals that every set spans several words of bits
+    let all: Vec<_> = (0..150)
+        .map(|i| TerminalString::quoted(format!("t{}", i).into()))
+        .collect();
+    let bits = all
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, t)| (t, i))
+        .collect();
+    let _lr1_tls = Lr1Tls::install(TerminalSet {
+        all: all.clone(),
+        bits,
+    });
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut random_set = || {
+        let mut set = TokenSet::new();
+        let mut members = BTreeSet::new();
+        for (i, t) in all.iter().enumerate() {
+            if rng.gen_bool(0.1) {
+                set.insert(Token::Terminal(t.clone()));
+                members.insert(i);
+            }
+        }
+        if rng.gen_bool(0.5) {
+            set.insert_eof();
+            members.insert(all.len());
+        }
+        (set, members)
+    };
+    let index = |token: Token| match token {
+        Token::Terminal(t) => all.iter().position(|u| *u == t).unwrap(),
+        Token::Eof => all.len(),
+        Token::Error => unreachable!(),
+    };
+
+    // every operation gives the same answer as it would on sorted
+    // sets of terminal indices
+    for _ in 0..100 {
+        let (a, a_members) = random_set();
+        let (b, b_members) = random_set();
+        assert_eq!(a.iter().map(index).collect::<BTreeSet<_>>(), a_members);
+        assert_eq!(a.len(), a_members.len());
+        assert_eq!(a.is_disjoint(&b), a_members.is_disjoint(&b_members));
+        assert_eq!(a.cmp(&b), a_members.cmp(&b_members));
+
+        let mut union = a.clone();
+        assert_eq!(union.union_with(&b), !b_members.is_subset(&a_members));
+        assert_eq!(
+            union.iter().map(index).collect::<BTreeSet<_>>(),
+            &a_members | &b_members
+        );
+
+        let mut difference = a.clone();
+        difference.difference_with(&b);
+        assert_eq!(
+            difference.iter().map(index).collect::<BTreeSet<_>>(),
+            &a_members - &b_members
+        );
+
+        assert_eq!(
+            a.intersection(&b)
+                .iter()
+                .map(index)
+                .collect::<BTreeSet<_>>(),
+            &a_members & &b_members
+        );
+    }
+}
+
+#[test]
+fn transitive_closure_ignores_item_order() {
+    use rand::seq::SliceRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+    extern { enum Tok { "-" => .., "N" => .., "(" => .., ")" => .. } }
+    S: () = E => ();
+    E: () = { E "-" T => (), T => () };
+    T: () = { "N" => (), "(" E ")" => () };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let lr1: Lr<TokenSet> = Lr::new(&grammar, nt("S"), TokenSet::eof());
+    let states = lr1.build_states().unwrap();
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for state in &states {
+        // feed the closure every item of the state, each lookahead
+        // split into single tokens, in a random order
+        let mut items: Vec<_> = state
+            .items
+            .vec
+            .iter()
+            .flat_map(|item| {
+                item.lookahead
+                    .iter()
+                    .map(move |token| item.with_lookahead(TokenSet::from(token)))
+            })
+            .collect();
+        for _ in 0..10 {
+            items.shuffle(&mut rng);
+            assert_eq!(lr1.transitive_closure(items.clone()), state.items);
+        }
+    }
+}
+
+#[test]
+fn random_grammars() {
+    use crate::generate::random_grammar;
+    use crate::lr1::build_lalr::build_lalr_states;
+    use crate::lr1::build_slr::build_slr_states;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // the grammars are often ambiguous, so construction may fail with
+    // conflicts, but it must never panic
+    for seed in 0..300 {
+        let text = random_grammar(&mut StdRng::seed_from_u64(seed), 4);
+        println!("seed {}:\n{}", seed, text);
+
+        let _tls = Tls::test();
+        let grammar = normalized_grammar(&text);
+        let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+        let _ = build_canonical_lr1_states(&grammar, nt("N0"));
+        let _ = build_lr1_states(&grammar, nt("N0"));
+        let _ = build_lalr_states(&grammar, nt("N0"));
+        let _ = build_slr_states(&grammar, nt("N0"));
+        let _ = build_lr0_states(&grammar, nt("N0"));
+    }
+}
@@ -6,7 +6,6 @@ use crate::lr1::build;
 use crate::lr1::core::*;
 use crate::lr1::lookahead::*;
 use crate::tls::Tls;
-use itertools::Itertools;
 
 #[cfg(test)]
 mod test;
@@ -44,20 +43,14 @@ pub fn build_lalr_states(grammar: &Grammar, start: NonterminalString) -> Lr1Resu
 }
 
 pub fn collapse_to_lalr_states<'grammar>(lr_states: &[Lr1State<'grammar>]) -> Lr1Result<'grammar> {
-    // Now compress them. This vector stores, for each state, the
+    // Now compress them. This map stores, for each state, the
     // LALR(1) state to which we will remap it.
-    let mut remap: Vec<_> = (0..lr_states.len()).map(|_| StateIndex(0)).collect();
+    let mut remap = StateMap::new(lr_states.len(), StateIndex(0));
     let mut lalr1_map: Map<Vec<Lr0Item>, StateIndex> = map();
     let mut lalr1_states: Vec<Lalr1State> = vec![];
 
-    for (lr1_index, lr1_state) in lr_states.iter().enumerate() {
-        let lr0_kernel: Vec<_> = lr1_state
-            .items
-            .vec
-            .iter()
-            .map(Item::to_lr0)
-            .dedup()
-            .collect();
+    for lr1_state in lr_states {
+        let lr0_kernel = lr1_state.lr0_core();
 
         let lalr1_index = *lalr1_map.entry(lr0_kernel).or_insert_with(|| {
             let index = StateIndex(lalr1_states.len());
@@ -75,7 +68,7 @@ pub fn collapse_to_lalr_states<'grammar>(lr_states: &[Lr1State<'grammar>]) -> Lr
             .items
             .extend(lr1_state.items.vec.iter().cloned());
 
-        remap[lr1_index] = lalr1_index;
+        remap[lr1_state.index] = lalr1_index;
     }
 
     // The reduction process can leave us with multiple
@@ -109,18 +102,18 @@ pub fn collapse_to_lalr_states<'grammar>(lr_states: &[Lr1State<'grammar>]) -> Lr
     }
 
     // Now that items are fully built, create the actions
-    for (lr1_index, lr1_state) in lr_states.iter().enumerate() {
-        let lalr1_index = remap[lr1_index];
+    for lr1_state in lr_states {
+        let lalr1_index = remap[lr1_state.index];
         let lalr1_state = &mut lalr1_states[lalr1_index.0];
 
         for (terminal, &lr1_state) in &lr1_state.shifts {
-            let target_state = remap[lr1_state.0];
+            let target_state = remap[lr1_state];
             let prev = lalr1_state.shifts.insert(terminal.clone(), target_state);
             assert!(prev.unwrap_or(target_state) == target_state);
         }
 
         for (nt, lr1_state) in &lr1_state.gotos {
-            let target_state = remap[lr1_state.0];
+            let target_state = remap[*lr1_state];
             let prev = lalr1_state.gotos.insert(nt.clone(), target_state);
             assert!(prev.unwrap_or(target_state) == target_state); // as above
         }
@@ -148,6 +141,8 @@ pub fn collapse_to_lalr_states<'grammar>(lr_states: &[Lr1State<'grammar>]) -> Lr
         Err(TableConstructionError {
             states: lr1_states,
             conflicts,
+            inconsistency: None,
+            invalid_resolution: None,
         })
     } else {
         Ok(lr1_states)
@@ -1,6 +1,8 @@
 use super::super::interpret::interpret;
 use super::build_lalr_states;
+use crate::collections::Set;
 use crate::grammar::repr::*;
+use crate::lr1::build;
 use crate::lr1::tls::Lr1Tls;
 use crate::test_util::normalized_grammar;
 use crate::tls::Tls;
@@ -47,3 +49,46 @@ fn figure9_23() {
         r#"[S: [E: [E: [T: "N"]], "-", [T: "(", [E: [E: [T: "N"]], "-", [T: "N"]], ")"]]]"#
     );
 }
+
+#[test]
+fn lr0_cores() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "-" => .., "N" => .., "(" => .., ")" => .. } }
+        S: () = E => ();
+        E: () = {
+            E "-" T => (),
+            T => ()
+        };
+        T: () = {
+            "N" => (),
+            "(" E ")" => ()
+        };
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let lr1_states = build::build_canonical_lr1_states(&grammar, nt("S")).unwrap();
+    let lalr_states = build_lalr_states(&grammar, nt("S")).unwrap();
+
+    // `T = "N" (*)` is reached both outside and inside of parentheses,
+    // with different lookahead
+    let n = [Symbol::Terminal(TerminalString::quoted(Atom::from("N")))];
+    let shifted_n: Vec<_> = lr1_states
+        .iter()
+        .filter(|state| {
+            state
+                .lr0_core()
+                .iter()
+                .any(|item| item.production.symbols == n)
+        })
+        .collect();
+    assert_eq!(shifted_n.len(), 2);
+    assert_ne!(shifted_n[0].items, shifted_n[1].items);
+    assert_eq!(shifted_n[0].lr0_core(), shifted_n[1].lr0_core());
+
+    // LALR(1) merges exactly the states with equal cores
+    let cores: Set<_> = lr1_states.iter().map(|state| state.lr0_core()).collect();
+    assert_eq!(cores.len(), lalr_states.len());
+}
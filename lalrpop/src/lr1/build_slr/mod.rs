@@ -0,0 +1,71 @@
+//! SLR(1) generation algorithm: LR(0) states whose reductions are
+//! restricted to the FOLLOW set of the reduced nonterminal.
+
+use crate::grammar::repr::*;
+use crate::lr1::build;
+use crate::lr1::core::*;
+use crate::lr1::first::FollowSets;
+use crate::lr1::lookahead::*;
+use crate::tls::Tls;
+
+#[cfg(test)]
+mod test;
+
+pub fn build_slr_states(grammar: &Grammar, start: NonterminalString) -> Lr1Result<'_> {
+    let (lr0_states, inconsistency) = match build::build_lr0_states(grammar, start.clone()) {
+        Ok(states) => (states, None),
+        Err(error) => {
+            for (index, conflict) in error.state_conflicts() {
+                log!(
+                    Tls::session(),
+                    Verbose,
+                    "LR(0) state {} has a {}, left to the FOLLOW sets",
+                    index.0,
+                    conflict
+                );
+            }
+            (error.states, error.inconsistency)
+        }
+    };
+
+    let follow_sets = FollowSets::new(grammar, &start);
+    let mut conflicts = vec![];
+    let states: Vec<Lr1State<'_>> = lr0_states
+        .into_iter()
+        .map(|state| {
+            let follow =
+                |production: &Production| follow_sets.follow(&production.nonterminal).clone();
+            let state = State {
+                index: state.index,
+                items: Items {
+                    vec: state
+                        .items
+                        .vec
+                        .iter()
+                        .map(|item| item.with_lookahead(follow(item.production)))
+                        .collect(),
+                },
+                shifts: state.shifts,
+                reductions: state
+                    .reductions
+                    .into_iter()
+                    .map(|(Nil, production)| (follow(production), production))
+                    .collect(),
+                gotos: state.gotos,
+            };
+            conflicts.extend(TokenSet::conflicts(&state));
+            state
+        })
+        .collect();
+
+    if conflicts.is_empty() && inconsistency.is_none() {
+        Ok(states)
+    } else {
+        Err(TableConstructionError {
+            states,
+            conflicts,
+            inconsistency,
+            invalid_resolution: None,
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use super::build_slr_states;
+use crate::grammar::repr::*;
+use crate::lr1::build::build_lr1_states;
+use crate::lr1::interpret::interpret;
+use crate::lr1::tls::Lr1Tls;
+use crate::test_util::{expect_debug, normalized_grammar};
+use crate::tls::Tls;
+use string_cache::DefaultAtom as Atom;
+
+fn nt(t: &str) -> NonterminalString {
+    NonterminalString(Atom::from(t))
+}
+
+macro_rules! tokens {
+    ($($x:expr),*) => {
+        vec![$(TerminalString::quoted(Atom::from($x))),*]
+    }
+}
+
+#[test]
+fn expr_grammar() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "-" => .., "N" => .., "(" => .., ")" => .. } }
+        S: () = E       => ();
+        E: () = {
+            E "-" T     => (),
+            T           => ()
+        };
+        T: () = {
+            "N"         => (),
+            "(" E ")"   => ()
+        };
+   "#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let slr_states = build_slr_states(&grammar, nt("S")).unwrap();
+    let lr1_states = build_lr1_states(&grammar, nt("S")).unwrap();
+    assert!(slr_states.len() <= lr1_states.len());
+
+    let tree = interpret(&slr_states, tokens!["N", "-", "(", "N", "-", "N", ")"]).unwrap();
+    assert_eq!(
+        &format!("{:?}", tree)[..],
+        r#"[S: [E: [E: [T: "N"]], "-", [T: "(", [E: [E: [T: "N"]], "-", [T: "N"]], ")"]]]"#
+    );
+
+    // the reduction of `T = "N"` is only permitted on FOLLOW(T)
+    let reduce_n = slr_states
+        .iter()
+        .flat_map(|s| &s.reductions)
+        .find(|(_, p)| p.symbols == vec![Symbol::Terminal(TerminalString::quoted(Atom::from("N")))])
+        .unwrap();
+    expect_debug(
+        &reduce_n.0,
+        r#"[
+    ")",
+    "-",
+    Eof
+]"#,
+    );
+}
+
+/// The classic grammar which is LALR(1) but not SLR(1): `"="` is in
+/// FOLLOW(R), so after `L` we cannot decide whether to reduce.
+#[test]
+fn lalr_but_not_slr() {
+    let _tls = Tls::test();
+
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "=" => .., "*" => .., "id" => .. } }
+        S: () = {
+            L "=" R,
+            R,
+        };
+        L: () = {
+            "*" R,
+            "id",
+        };
+        R: () = L;
+   "#,
+    );
+
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let err = build_slr_states(&grammar, nt("S")).unwrap_err();
+    assert_eq!(err.conflicts.len(), 1);
+    expect_debug(
+        &err.conflicts[0].lookahead,
+        r#"[
+    "="
+]"#,
+    );
+
+    assert!(build_lr1_states(&grammar, nt("S")).is_ok());
+}
@@ -2,9 +2,10 @@
 //!
 //! [recursive ascent]: https://en.wikipedia.org/wiki/Recursive_ascent_parser
 
-use crate::collections::Multimap;
+use crate::collections::{Multimap, Set};
+use crate::grammar::consts::BOXED;
 use crate::grammar::repr::{
-    Grammar, NonterminalString, Production, Symbol, TerminalString, TypeParameter, TypeRepr,
+    Grammar, NonterminalString, Path, Production, Symbol, TerminalString, TypeParameter, TypeRepr,
     Visibility, WhereClause,
 };
 use crate::lr1::core::*;
@@ -14,6 +15,7 @@ use crate::rust::RustWrite;
 use crate::tls::Tls;
 use crate::util::{Escape, Sep};
 use std::io::{self, Write};
+use string_cache::DefaultAtom as Atom;
 
 use super::base::CodeGenerator;
 
@@ -49,6 +51,13 @@ struct RecursiveAscent<'ascent, 'grammar> {
     nonterminal_type_params: Vec<TypeParameter>,
 
     nonterminal_where_clauses: Vec<WhereClause>,
+
+    /// for each state, the module its function goes in, if the
+    /// functions are spread over several modules
+    state_modules: Option<Vec<usize>>,
+
+    /// the module whose state functions are being written, if any
+    current_module: Option<usize>,
 }
 
 /// Tracks the suffix of the stack (that is, top-most elements) that any
@@ -135,6 +144,15 @@ impl<'ascent, 'grammar, W: Write>
             .map(|state| Self::state_input_for(state))
             .collect();
 
+        // consecutive states go in the same module, with the same
+        // number of states in each module, give or take one
+        let state_modules = grammar.algorithm.state_modules.map(|count| {
+            let count = count.min(states.len());
+            (0..states.len())
+                .map(|index| index * count / states.len())
+                .collect()
+        });
+
         CodeGenerator::new(
             grammar,
             user_start_symbol,
@@ -148,6 +166,8 @@ impl<'ascent, 'grammar, W: Write>
                 state_inputs,
                 nonterminal_type_params,
                 nonterminal_where_clauses,
+                state_modules,
+                current_module: None,
             },
         )
     }
@@ -167,13 +187,64 @@ impl<'ascent, 'grammar, W: Write>
             this.write_start_fn()?;
             rust!(this.out, "");
             this.write_return_type_defn()?;
-            for i in 0..this.states.len() {
-                this.write_state_fn(StateIndex(i))?;
+            match this.custom.state_modules.clone() {
+                None => {
+                    for i in 0..this.states.len() {
+                        this.write_state_fn(StateIndex(i))?;
+                    }
+                }
+                Some(state_modules) => this.write_state_modules(&state_modules)?,
             }
             Ok(())
         })
     }
 
+    /// Writes the state functions into submodules of the parse module,
+    /// so that `rustc` can work on them in parallel. The functions call
+    /// one another through paths relative to the calling module.
+    fn write_state_modules(&mut self, state_modules: &[usize]) -> io::Result<()> {
+        let action_module = self.action_module.clone();
+        self.action_module = format!("super::{}", action_module);
+        let count = state_modules.last().map_or(0, |&module| module + 1);
+        for module in 0..count {
+            self.custom.current_module = Some(module);
+            rust!(self.out, "");
+            rust!(self.out, "mod {}states_{} {{", self.prefix, module);
+            rust!(self.out, "use super::*;");
+            for (index, _) in state_modules
+                .iter()
+                .enumerate()
+                .filter(|&(_, &m)| m == module)
+            {
+                self.write_state_fn(StateIndex(index))?;
+            }
+            rust!(self.out, "}}");
+        }
+        self.custom.current_module = None;
+        self.action_module = action_module;
+        Ok(())
+    }
+
+    /// The path to the function of the given state, from the module
+    /// being written.
+    fn state_fn_path(&self, index: StateIndex) -> String {
+        let fn_name = format!("{}state{}", self.prefix, index.0);
+        let module = match self.custom.state_modules {
+            Some(ref state_modules) => state_modules[index.0],
+            None => return fn_name,
+        };
+        match self.custom.current_module {
+            Some(current) if current == module => fn_name,
+            Some(_) => format!("super::{}states_{}::{}", self.prefix, module, fn_name),
+            None => format!("{}states_{}::{}", self.prefix, module, fn_name),
+        }
+    }
+
+    /// Defines the enum that the state functions use to return a
+    /// reduced nonterminal. It is private to the parse module: the start
+    /// function unwraps the start symbol's variant, so `parse` returns
+    /// that symbol's own type and the enum never appears in the public
+    /// API.
     fn write_return_type_defn(&mut self) -> io::Result<()> {
         // sometimes some of the variants are not used, particularly
         // if we are generating multiple parsers from the same file:
@@ -203,13 +274,27 @@ impl<'ascent, 'grammar, W: Write>
             let ty = self
                 .types
                 .spanned_type(self.types.nonterminal_type(nt).clone());
-            rust!(self.out, "{}({}),", Escape(nt), ty);
+            if self.is_boxed(nt) {
+                rust!(self.out, "{}(alloc::boxed::Box<{}>),", Escape(nt), ty);
+            } else {
+                rust!(self.out, "{}({}),", Escape(nt), ty);
+            }
         }
 
         rust!(self.out, "}}");
         Ok(())
     }
 
+    /// Whether the variant of `nt` in the `Nonterminal` enum holds a
+    /// box, as requested by a `#[boxed]` annotation, so that one large
+    /// type does not make every state function return a large value.
+    fn is_boxed(&self, nt: &NonterminalString) -> bool {
+        self.grammar.nonterminals[nt]
+            .annotations
+            .iter()
+            .any(|annotation| annotation.id == *BOXED)
+    }
+
     // Generates a function `parse_Foo` that will parse an entire
     // input as `Foo`. An error is reported if the entire input is not
     // consumed.
@@ -219,12 +304,18 @@ impl<'ascent, 'grammar, W: Write>
         self.define_tokens()?;
 
         self.next_token("lookahead", "tokens")?;
+        let depth = if self.grammar.algorithm.recursion_limit.is_some() {
+            "0, "
+        } else {
+            ""
+        };
         rust!(
             self.out,
-            "match {}state0({}&mut {}tokens, {}lookahead, {})? {{",
-            self.prefix,
+            "match {}({}&mut {}tokens, {}{}lookahead, {})? {{",
+            self.state_fn_path(StateIndex(0)),
             self.grammar.user_parameter_refs(),
             self.prefix,
+            depth,
             self.prefix,
             phantom_data
         );
@@ -290,22 +381,43 @@ impl<'ascent, 'grammar, W: Write>
                 this_state.will_produce()
             );
             rust!(self.out, "//");
-            for item in this_state.items.vec.iter() {
-                rust!(self.out, "//     {:?}", item);
-            }
+            self.write_item_comments(&this_state.items.vec)?;
             rust!(self.out, "//");
-            for (terminal, action) in &this_state.shifts {
+            for (terminal, action) in this_state.shift_entries() {
                 rust!(self.out, "//   {:?} -> {:?}", terminal, action);
             }
-            for &(ref tokens, action) in &this_state.reductions {
+            for &(ref tokens, action) in this_state.reduction_entries() {
                 rust!(self.out, "//   {:?} -> {:?}", tokens, action);
             }
+            if this_state.is_accept(&self.start_symbol) {
+                rust!(self.out, "//   Eof -> Accept");
+            }
             rust!(self.out, "//");
-            for (nt, state) in &this_state.gotos {
+            for (nt, state) in this_state.goto_entries() {
                 rust!(self.out, "//     {:?} -> {:?}", nt, state);
             }
         }
 
+        // The terminals which would have resulted in a successful parse
+        // in this state, reported when the lookahead is not one of them
+        let successful_terminals = self.grammar.terminals().filter(|terminal| {
+            this_state.shifts.contains_key(terminal)
+                || this_state
+                    .reductions
+                    .iter()
+                    .any(|(t, _)| t.contains(&Token::Terminal(terminal.clone())))
+        });
+        rust!(
+            self.out,
+            "static {}EXPECTED_{}: &[&str] = &[",
+            self.prefix,
+            this_index.0
+        );
+        for terminal in successful_terminals {
+            rust!(self.out, "r###\"{}\"###,", terminal);
+        }
+        rust!(self.out, "];");
+
         self.emit_state_fn_header("state", this_index.0, inputs)?;
 
         // possibly move some fixed inputs into optional stack slots
@@ -316,10 +428,16 @@ impl<'ascent, 'grammar, W: Write>
 
         rust!(self.out, "match {}lookahead {{", self.prefix);
 
-        // first emit shifts:
-        for (terminal, &next_index) in &this_state.shifts {
+        // first emit shifts. `shifts` (like `gotos`) is a `Map`, so
+        // terminals are visited in sorted order and the generated code
+        // is the same from one run to the next.
+        for (terminal, &next_index) in this_state.shift_entries() {
             let sym_name = format!("{}sym{}", self.prefix, inputs.len());
             self.consume_terminal(terminal, sym_name)?;
+            if self.grammar.algorithm.trace {
+                let text = format!("shift {}", terminal);
+                rust!(self.out, "eprintln!(r###\"{}\"###);", escape_braces(&text));
+            }
 
             // transition to the new state
             if self.transition("result", stack_suffix, next_index, &["tokens"])? {
@@ -333,7 +451,7 @@ impl<'ascent, 'grammar, W: Write>
         // trigger the same reduction, so group these by the
         // production that we are going to be reducing.
         let reductions: Multimap<_, Vec<_>> = this_state
-            .reductions
+            .reduction_entries()
             .iter()
             .flat_map(|&(ref tokens, production)| tokens.iter().map(move |t| (production, t)))
             .collect();
@@ -367,20 +485,12 @@ impl<'ascent, 'grammar, W: Write>
 
         // if we hit this, the next token is not recognized, so generate an error
         rust!(self.out, "_ => {{");
-        // The terminals which would have resulted in a successful parse in this state
-        let successful_terminals = self.grammar.terminals.all.iter().filter(|&terminal| {
-            this_state.shifts.contains_key(terminal)
-                || this_state
-                    .reductions
-                    .iter()
-                    .any(|(t, _)| t.contains(&Token::Terminal(terminal.clone())))
-        });
-
-        rust!(self.out, "let {}expected = alloc::vec![", self.prefix);
-        for terminal in successful_terminals {
-            rust!(self.out, "r###\"{}\"###.to_string(),", terminal);
-        }
-        rust!(self.out, "];");
+        rust!(
+            self.out,
+            "let {p}expected = {p}EXPECTED_{}.iter().map(|terminal| terminal.to_string()).collect();",
+            this_index.0,
+            p = self.prefix,
+        );
 
         // check if we've found an unrecognized token or EOF
         rust!(self.out, "return Err(");
@@ -498,7 +608,7 @@ impl<'ascent, 'grammar, W: Write>
             );
 
             rust!(self.out, "match {}nt {{", self.prefix);
-            for (ref nt, &next_index) in &this_state.gotos {
+            for (ref nt, &next_index) in this_state.goto_entries() {
                 // The nonterminal we are shifting becomes symN, where
                 // N is the number of inputs to this state (which are
                 // numbered sym0..sym(N-1)). It is never optional
@@ -512,14 +622,41 @@ impl<'ascent, 'grammar, W: Write>
                     self.prefix,
                     stack_suffix.len()
                 );
+                if self.is_boxed(nt) {
+                    rust!(
+                        self.out,
+                        "let {p}sym{n} = *{p}sym{n};",
+                        p = self.prefix,
+                        n = stack_suffix.len()
+                    );
+                }
                 self.transition("result", stack_suffix, next_index, &["tokens", "lookahead"])?;
                 rust!(self.out, "}}");
             }
 
-            // Errors are not possible in the goto phase; a missing entry
-            // indicates parse successfully completed, so just bail out.
-            if this_state.gotos.len() != self.grammar.nonterminals.keys().len() {
-                rust!(self.out, "_ => {{");
+            // Errors are not possible in the goto phase. Any other
+            // nonterminal was produced by a reduction that began in an
+            // earlier state, so it is passed back to that state; the
+            // start symbol, which only reaches the start state, means
+            // that the parse is complete.
+            let returned: Set<&NonterminalString> = this_state
+                .items
+                .vec
+                .iter()
+                .filter(|item| item.index > 0 || item.production.nonterminal == self.start_symbol)
+                .map(|item| &item.production.nonterminal)
+                .filter(|nt| !this_state.gotos.contains_key(*nt))
+                .collect();
+            for nt in &returned {
+                if **nt == self.start_symbol {
+                    rust!(self.out, "// accept");
+                }
+                rust!(
+                    self.out,
+                    "{}Nonterminal::{}(..) => {{",
+                    self.prefix,
+                    Escape(nt)
+                );
                 rust!(
                     self.out,
                     "return Ok(({}lookahead, {}nt));",
@@ -528,6 +665,9 @@ impl<'ascent, 'grammar, W: Write>
                 );
                 rust!(self.out, "}}");
             }
+            if this_state.gotos.len() + returned.len() != self.grammar.nonterminals.keys().len() {
+                rust!(self.out, "_ => unreachable!(),");
+            }
 
             rust!(self.out, "}}"); // match
 
@@ -555,9 +695,15 @@ impl<'ascent, 'grammar, W: Write>
 
         let (fn_args, starts_with_terminal) = self.fn_args(optional_prefix, fixed_prefix);
 
+        // the other modules call into the module of this function
+        let visibility = match self.custom.current_module {
+            Some(_) => Visibility::Pub(Some(Path::from_id(Atom::from("super")))),
+            None => Visibility::Priv,
+        };
+
         self.out
             .fn_header(
-                &Visibility::Priv,
+                &visibility,
                 format!("{}{}{}", self.prefix, fn_kind, fn_index),
             )
             .with_grammar(self.grammar)
@@ -586,6 +732,25 @@ impl<'ascent, 'grammar, W: Write>
             Sep(", ", &self.custom.nonterminal_type_params),
         );
 
+        // every state but the start state is entered with at least one
+        // fixed symbol on the stack; bail out at its end if we are
+        // nested too deeply
+        if let (Some(limit), false) = (
+            self.grammar.algorithm.recursion_limit,
+            fixed_prefix.is_empty(),
+        ) {
+            rust!(self.out, "if {}depth > {} {{", self.prefix, limit);
+            // the conversion is a no-op when the error type is
+            // `LimitExceeded` itself
+            rust!(
+                self.out,
+                "#[allow(clippy::useless_conversion)]return Err({p}lalrpop_util::ParseError::User {{ error: core::convert::From::from({p}lalrpop_util::LimitExceeded::Recursion {{ location: {p}sym{n}.2 }}) }});",
+                p = self.prefix,
+                n = optional_prefix.len() + fixed_prefix.len() - 1,
+            );
+            rust!(self.out, "}}");
+        }
+
         // shift lookahead is necessary; see `starts_with_terminal` above
         if starts_with_terminal {
             self.next_token("lookahead", "tokens")?;
@@ -626,6 +791,9 @@ impl<'ascent, 'grammar, W: Write>
             .unwrap_or(false);
 
         let mut base_args = vec![format!("{}tokens: &mut {}TOKENS", self.prefix, self.prefix)];
+        if self.grammar.algorithm.recursion_limit.is_some() {
+            base_args.push(format!("{}depth: usize", self.prefix));
+        }
         if !starts_with_terminal {
             base_args.push(format!(
                 "{}lookahead: core::option::Option<{}>",
@@ -762,6 +930,13 @@ impl<'ascent, 'grammar, W: Write>
     /// state returns, or `false` if `transition` will just return
     /// afterwards.
     ///
+    /// The target only receives its `state_inputs`, i.e., the symbols
+    /// before the cursor in its longest item, and each of them is
+    /// consumed by one of its reductions (or by a state it transitions
+    /// to in turn). Symbols it may leave alone are already passed as
+    /// `&mut Option` (see `adjust_inputs`), so there are no symbols
+    /// that could be lent rather than moved.
+    ///
     /// # Arguments
     ///
     /// - `into_result`: name of variable to store result from target state into
@@ -791,12 +966,16 @@ impl<'ascent, 'grammar, W: Write>
 
         let transfer_syms = self.pop_syms(optional, fixed, next_inputs)?;
 
-        let other_args = other_args
-            .iter()
-            .map(|s| format!("{}{}", self.prefix, s))
-            .collect();
+        let mut args = vec![];
+        for &arg in other_args {
+            args.push(format!("{}{}", self.prefix, arg));
+            if arg == "tokens" && self.grammar.algorithm.recursion_limit.is_some() {
+                // the depth goes right after `tokens` (see `fn_args`)
+                args.push(format!("{}depth + 1", self.prefix));
+            }
+        }
 
-        let fn_name = format!("{}state{}", self.prefix, next_index.0);
+        let fn_name = self.state_fn_path(next_index);
 
         // invoke next state, transferring the top `m` tokens
         let phantom_data_expr = self.phantom_data_expr();
@@ -807,7 +986,7 @@ impl<'ascent, 'grammar, W: Write>
             into_result,
             fn_name,
             self.grammar.user_parameter_refs(),
-            Sep(", ", &other_args),
+            Sep(", ", &args),
             Sep(", ", &transfer_syms),
             phantom_data_expr
         );
@@ -888,7 +1067,18 @@ impl<'ascent, 'grammar, W: Write>
             args.push(format!("&{}end", self.prefix));
         }
 
-        // invoke the action code
+        // invoke the action code, noting which production it belongs
+        // to (as the table-driven reductions do)
+        rust!(self.out, "// {:?}", production);
+        if self.grammar.algorithm.trace {
+            let rule = format!(
+                "{} = {}",
+                production.nonterminal,
+                Sep(" ", &production.symbols)
+            );
+            let text = format!("reduce {}", rule.trim_end());
+            rust!(self.out, "eprintln!(r###\"{}\"###);", escape_braces(&text));
+        }
         let is_fallible = self.grammar.action_is_fallible(production.action);
         if is_fallible {
             rust!(
@@ -917,17 +1107,19 @@ impl<'ascent, 'grammar, W: Write>
         }
 
         // wrap up the produced value into `Nonterminal` along with
+        let boxed = self.is_boxed(&production.nonterminal);
         rust!(
             self.out,
-            "let {}nt = {}Nonterminal::{}((",
+            "let {}nt = {}Nonterminal::{}({}(",
             self.prefix,
             self.prefix,
-            Escape(&production.nonterminal)
+            Escape(&production.nonterminal),
+            if boxed { "alloc::boxed::Box::new(" } else { "" }
         );
         rust!(self.out, "{}start,", self.prefix);
         rust!(self.out, "{}nt,", self.prefix);
         rust!(self.out, "{}end,", self.prefix);
-        rust!(self.out, "));");
+        rust!(self.out, "){});", if boxed { ")" } else { "" });
 
         // wrap up the result along with the (unused) lookahead
         rust!(
@@ -1001,3 +1193,8 @@ impl<'ascent, 'grammar, W: Write>
         Ok(())
     }
 }
+
+/// Escapes `text` for use as a format string.
+fn escape_braces(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
@@ -1,12 +1,16 @@
 //! Base helper routines for a code generator.
 
 use crate::collections::Set;
+use crate::grammar::consts::PARSE_FN;
 use crate::grammar::free_variables::FreeVariables;
 use crate::grammar::repr::*;
 use crate::lr1::core::*;
+use crate::lr1::lookahead::Lookahead;
 use crate::rust::RustWrite;
+use crate::tls::Tls;
 use crate::util::Sep;
 use std::io::{self, Write};
+use string_cache::DefaultAtom as Atom;
 
 /// Base struct for various kinds of code generator. The flavor of
 /// code generator is customized by supplying distinct types for `C`
@@ -69,6 +73,22 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
         }
     }
 
+    /// Writes a comment line for each item, each followed by the `///`
+    /// comment on the item's production in the grammar, if any.
+    pub fn write_item_comments<L: Lookahead>(
+        &mut self,
+        items: &[Item<'grammar, L>],
+    ) -> io::Result<()> {
+        let file_text = Tls::file_text();
+        for item in items {
+            rust!(self.out, "//     {:?}", item);
+            for line in file_text.doc_comment(item.production.span.0) {
+                rust!(self.out, "//         /// {}", line);
+            }
+        }
+        Ok(())
+    }
+
     /// We often create meta types that pull together a bunch of
     /// user-given types -- basically describing (e.g.) the full set
     /// of return values from any nonterminal (and, in some cases,
@@ -179,6 +199,12 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
         self.out
             .write_uses(&format!("{}::", self.action_module), self.grammar)?;
 
+        // the bounds of the grammar's type parameters name the trait
+        if self.grammar.algorithm.action_trait.is_some() {
+            rust!(self.out, "#[allow(unused_imports)]");
+            rust!(self.out, "use {}::Actions;", self.action_module);
+        }
+
         if self.grammar.intern_token.is_some() {
             rust!(
                 self.out,
@@ -197,9 +223,9 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
         Ok(())
     }
 
-    pub fn start_parser_fn(&mut self) -> io::Result<()> {
-        let parse_error_type = self.types.parse_error_type();
-
+    /// Returns the type parameters, parameters and where clauses
+    /// through which a `parse` fn receives its input.
+    fn parser_fn_inputs(&self) -> (Vec<String>, Vec<String>, Vec<String>) {
         let (type_parameters, parameters, mut where_clauses);
 
         let intern_token = self.grammar.intern_token.is_some();
@@ -216,24 +242,41 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
             for type_parameter in &self.grammar.type_parameters {
                 user_type_parameters.push_str(&format!("{}, ", type_parameter));
             }
-            type_parameters = vec![
-                format!(
-                    "{}TOKEN: {}ToTriple<{}>",
-                    self.prefix, self.prefix, user_type_parameters,
-                ),
-                format!(
-                    "{}TOKENS: IntoIterator<Item={}TOKEN>",
-                    self.prefix, self.prefix
-                ),
-            ];
-            parameters = vec![format!("{}tokens0: {}TOKENS", self.prefix, self.prefix)];
+            let token_type_parameter = format!(
+                "{}TOKEN: {}ToTriple<{}>",
+                self.prefix, self.prefix, user_type_parameters,
+            );
             where_clauses = vec![];
 
-            if self.repeatable {
-                where_clauses.push(format!("{}TOKENS: Clone", self.prefix));
+            if self.grammar.algorithm.slice_input {
+                // with `#[slice_input]`, a slice of tokens, which are
+                // cloned as they are read
+                type_parameters = vec![format!("{} + Clone", token_type_parameter)];
+                parameters = vec![format!("{}tokens0: &[{}TOKEN]", self.prefix, self.prefix)];
+            } else {
+                type_parameters = vec![
+                    token_type_parameter,
+                    format!(
+                        "{}TOKENS: IntoIterator<Item={}TOKEN>",
+                        self.prefix, self.prefix
+                    ),
+                ];
+                parameters = vec![format!("{}tokens0: {}TOKENS", self.prefix, self.prefix)];
+
+                if self.repeatable {
+                    where_clauses.push(format!("{}TOKENS: Clone", self.prefix));
+                }
             }
         }
 
+        (type_parameters, parameters, where_clauses)
+    }
+
+    pub fn start_parser_fn(&mut self) -> io::Result<()> {
+        let parse_error_type = self.types.parse_error_type();
+        let (type_parameters, parameters, where_clauses) = self.parser_fn_inputs();
+        let intern_token = self.grammar.intern_token.is_some();
+
         rust!(
             self.out,
             "{}struct {}Parser {{",
@@ -258,6 +301,14 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
             self.user_start_symbol
         );
 
+        if self.grammar.nonterminals[&self.user_start_symbol]
+            .annotations
+            .iter()
+            .any(|annotation| annotation.id == *PARSE_FN)
+        {
+            self.write_parse_fn()?;
+        }
+
         // Start parser impl
         rust!(self.out, "impl {}Parser {{", self.user_start_symbol);
         rust!(
@@ -305,6 +356,73 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
         Ok(())
     }
 
+    /// Emits the free `parse` function requested by `#[parse_fn]`,
+    /// which takes the same input as the parser's `parse` method and
+    /// parses it with a new parser.
+    fn write_parse_fn(&mut self) -> io::Result<()> {
+        let (type_parameters, parameters, where_clauses) = self.parser_fn_inputs();
+        let mut arguments: Vec<String> = self
+            .grammar
+            .parameters
+            .iter()
+            .map(|parameter| parameter.name.to_string())
+            .collect();
+        if self.grammar.intern_token.is_none() {
+            arguments.push(format!("{}tokens0", self.prefix));
+        }
+
+        rust!(self.out, "#[allow(dead_code)]");
+        self.out
+            .fn_header(
+                &self.grammar.nonterminals[&self.start_symbol].visibility,
+                "parse".to_owned(),
+            )
+            .with_grammar(self.grammar)
+            .with_type_parameters(type_parameters)
+            .with_parameters(parameters)
+            .with_return_type(format!(
+                "Result<{}, {}>",
+                self.types.nonterminal_type(&self.start_symbol),
+                self.types.parse_error_type()
+            ))
+            .with_where_clauses(where_clauses)
+            .emit()?;
+        rust!(self.out, "{{");
+        rust!(
+            self.out,
+            "{}Parser::new().parse({})",
+            self.user_start_symbol,
+            Sep(", ", &arguments)
+        );
+        rust!(self.out, "}}");
+        rust!(self.out, "");
+
+        Ok(())
+    }
+
+    /// Starts an additional `pub(crate)` method `name` on the parser
+    /// struct, taking the same input as `parse`. Like
+    /// `start_parser_fn`, this must be followed by `end_parser_fn`.
+    pub fn start_extra_parser_fn(&mut self, name: &str, return_type: String) -> io::Result<()> {
+        let (type_parameters, parameters, where_clauses) = self.parser_fn_inputs();
+        let visibility = Visibility::Pub(Some(Path::from_id(Atom::from("crate"))));
+
+        rust!(self.out, "impl {}Parser {{", self.user_start_symbol);
+        rust!(self.out, "#[allow(dead_code)]");
+        self.out
+            .fn_header(&visibility, name.to_owned())
+            .with_parameters(Some("&self".to_owned()))
+            .with_grammar(self.grammar)
+            .with_type_parameters(type_parameters)
+            .with_parameters(parameters)
+            .with_return_type(return_type)
+            .with_where_clauses(where_clauses)
+            .emit()?;
+        rust!(self.out, "{{");
+
+        Ok(())
+    }
+
     pub fn define_tokens(&mut self) -> io::Result<()> {
         if self.grammar.intern_token.is_some() {
             // if we are generating the tokenizer, create a matcher as our input iterator
@@ -314,25 +432,84 @@ impl<'codegen, 'grammar, W: Write, C> CodeGenerator<'codegen, 'grammar, W, C> {
                 self.prefix
             );
         } else {
-            // otherwise, convert one from the `IntoIterator`
-            // supplied, using the `ToTriple` trait which inserts
-            // errors/locations etc if none are given
-            let clone_call = if self.repeatable { ".clone()" } else { "" };
-            rust!(
-                self.out,
-                "let {}tokens = {}tokens0{}.into_iter();",
-                self.prefix,
-                self.prefix,
-                clone_call
-            );
+            // otherwise, convert one from the input supplied, using
+            // the `ToTriple` trait which inserts errors/locations etc
+            // if none are given
+            if self.grammar.algorithm.slice_input {
+                rust!(
+                    self.out,
+                    "let mut {p}tokens = {p}lalrpop_util::lookahead::SliceTokens::new(\
+                     {p}tokens0, |t: &{p}TOKEN| {p}ToTriple::to_triple(t.clone()));",
+                    p = self.prefix,
+                );
+            } else {
+                let clone_call = if self.repeatable { ".clone()" } else { "" };
+                rust!(
+                    self.out,
+                    "let {}tokens = {}tokens0{}.into_iter();",
+                    self.prefix,
+                    self.prefix,
+                    clone_call
+                );
 
-            rust!(
-                self.out,
-                "let mut {}tokens = {}tokens.map(|t| {}ToTriple::to_triple(t));",
-                self.prefix,
-                self.prefix,
-                self.prefix
-            );
+                rust!(
+                    self.out,
+                    "let mut {}tokens = {}tokens.map(|t| {}ToTriple::to_triple(t));",
+                    self.prefix,
+                    self.prefix,
+                    self.prefix
+                );
+            }
+
+            // drop the terminals that the grammar asked to skip before
+            // the parser ever sees them
+            if !self.grammar.skip_terminals.is_empty() {
+                let pattern = self
+                    .grammar
+                    .skip_terminals
+                    .iter()
+                    .map(|terminal| self.grammar.pattern(terminal).map(&mut |_| "_").to_string())
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                match self.grammar.algorithm.classify {
+                    Some(ref classify) => rust!(
+                        self.out,
+                        "let mut {p}tokens = {p}tokens.filter(|t| \
+                         !matches!(t, Ok((_, {p}tok, _)) if matches!({classify}({p}tok), {pattern})));",
+                        p = self.prefix,
+                        classify = classify,
+                        pattern = pattern,
+                    ),
+                    None => rust!(
+                        self.out,
+                        "let mut {p}tokens = {p}tokens.filter(|t| !matches!(t, Ok((_, {pattern}, _))));",
+                        p = self.prefix,
+                        pattern = pattern,
+                    ),
+                }
+            }
+
+            // if the tokenizer marks the end of input with a token of
+            // its own, stop there, as if the iterator had run dry
+            if let Some(ref eof) = self.grammar.eof_terminal {
+                let pattern = self.grammar.pattern(eof).map(&mut |_| "_");
+                match self.grammar.algorithm.classify {
+                    Some(ref classify) => rust!(
+                        self.out,
+                        "let mut {p}tokens = {p}tokens.take_while(|t| \
+                         !matches!(t, Ok((_, {p}tok, _)) if matches!({classify}({p}tok), {pattern})));",
+                        p = self.prefix,
+                        classify = classify,
+                        pattern = pattern,
+                    ),
+                    None => rust!(
+                        self.out,
+                        "let mut {p}tokens = {p}tokens.take_while(|t| !matches!(t, Ok((_, {pattern}, _))));",
+                        p = self.prefix,
+                        pattern = pattern,
+                    ),
+                }
+            }
         }
 
         Ok(())
@@ -11,6 +11,7 @@ use itertools::Itertools;
 use std::fmt;
 use std::io::{self, Write};
 use std::rc::Rc;
+use string_cache::DefaultAtom as Atom;
 
 use super::base::CodeGenerator;
 
@@ -39,6 +40,7 @@ enum Comment<'a, T> {
     Goto(T, usize),
     Error(T),
     Reduce(T, &'a Production),
+    Accept(T),
 }
 
 impl<'a, T: fmt::Display> fmt::Display for Comment<'a, T> {
@@ -51,6 +53,7 @@ impl<'a, T: fmt::Display> fmt::Display for Comment<'a, T> {
             Comment::Reduce(ref token, production) => {
                 write!(f, " // on {}, reduce `{:?}`", token, production)
             }
+            Comment::Accept(ref token) => write!(f, " // on {}, accept", token),
         }
     }
 }
@@ -84,14 +87,20 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         action_module: &str,
         out: &'ascent mut RustWrite<W>,
     ) -> Self {
+        // only the types of terminals that appear in the grammar become
+        // variants of `__Symbol`; the error recovery type is always
+        // registered, and would otherwise leave a grammar without any
+        // terminals with an unused lifetime parameter
         let (symbol_type_params, symbol_where_clauses) =
             Self::filter_type_parameters_and_where_clauses(
                 grammar,
-                grammar
-                    .types
-                    .nonterminal_types()
-                    .into_iter()
-                    .chain(grammar.types.terminal_types()),
+                grammar.types.nonterminal_types().into_iter().chain(
+                    grammar
+                        .terminals
+                        .all
+                        .iter()
+                        .map(|term| grammar.types.terminal_type(term).clone()),
+                ),
             );
 
         let machine = Rc::new(MachineParameters::new(grammar));
@@ -130,7 +139,7 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
                 symbol_type_params,
                 symbol_where_clauses,
                 machine,
-                all_nonterminals: grammar.nonterminals.keys().cloned().collect(),
+                all_nonterminals: grammar.nonterminals().collect(),
                 reduce_indices,
                 state_type,
                 variant_names: Map::new(),
@@ -149,7 +158,32 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
             this.write_token_to_symbol_fn()?;
             this.write_simulate_reduce_fn()?;
             this.write_parser_fn()?;
+            this.write_symbol_tables()?;
+            if this.grammar.algorithm.push_parser {
+                this.write_driver_fn("push_parser", "PushParser")?;
+            }
+            if this.grammar.algorithm.incremental {
+                this.write_driver_fn("incremental_parser", "IncrementalParser")?;
+            }
+            if this.grammar.algorithm.event_parser {
+                this.write_driver_fn("event_parser", "EventParser")?;
+            }
+            if this.grammar.algorithm.partial_results {
+                this.write_partial_parser_fn()?;
+            }
+            if this.grammar.algorithm.parse_prefix {
+                this.write_prefix_parser_fn()?;
+            }
+            if this.grammar.algorithm.parse_items {
+                this.write_items_parser_fn()?;
+            }
+            if this.grammar.algorithm.count_reductions {
+                this.write_reduction_counts()?;
+            }
             this.write_accepts_fn()?;
+            if this.grammar.algorithm.narrow_expected {
+                this.write_expected_tokens_before_fn()?;
+            }
             this.emit_reduce_actions()?;
             this.emit_downcast_fns()?;
             this.emit_reduce_action_functions()?;
@@ -157,6 +191,24 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         })
     }
 
+    /// Emits the fields of a `__StateMachine { .. }` expression, for a
+    /// parse that starts from scratch.
+    fn write_state_machine_fields(&mut self) -> io::Result<()> {
+        for Parameter { name, .. } in &self.grammar.parameters {
+            rust!(self.out, "{},", name);
+        }
+        if self.grammar.algorithm.reduction_limit.is_some() {
+            rust!(self.out, "{p}reductions: 0,", p = self.prefix);
+        }
+        rust!(
+            self.out,
+            "{p}phantom: {phantom},",
+            p = self.prefix,
+            phantom = self.phantom_data_expr(),
+        );
+        Ok(())
+    }
+
     fn write_machine_definition(&mut self) -> io::Result<()> {
         let error_type = self.types.error_type();
         let token_type = self.types.terminal_token_type();
@@ -170,9 +222,25 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         let machine_type_parameters = Sep(", ", &machine.type_parameters);
         let machine_where_clauses = Sep(", ", &machine.where_clauses);
 
+        // The push and event parsers hand the state machine out to the
+        // user, so it has to be nameable from outside of this module.
+        let machine_visibility = if self.grammar.algorithm.push_parser
+            || self.grammar.algorithm.event_parser
+            || self.grammar.algorithm.incremental
+        {
+            "pub(crate) "
+        } else {
+            ""
+        };
+        // an incremental parser keeps copies of itself, and an item
+        // parser starts each item from a copy
+        if self.grammar.algorithm.incremental || self.grammar.algorithm.parse_items {
+            rust!(self.out, "#[derive(Clone)]");
+        }
         rust!(
             self.out,
-            "struct {p}StateMachine<{mtp}>",
+            "{v}struct {p}StateMachine<{mtp}>",
+            v = machine_visibility,
             p = self.prefix,
             mtp = machine_type_parameters,
         );
@@ -181,6 +249,9 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         for param in &machine.fields {
             rust!(self.out, "{name}: {ty},", name = param.name, ty = param.ty,);
         }
+        if self.grammar.algorithm.reduction_limit.is_some() {
+            rust!(self.out, "{p}reductions: usize,", p = self.prefix);
+        }
         rust!(
             self.out,
             "{p}phantom: {phantom},",
@@ -224,6 +295,14 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         rust!(self.out, "  0");
         rust!(self.out, "}}");
 
+        if let Some(depth) = self.grammar.algorithm.stack_capacity {
+            rust!(self.out, "");
+            rust!(self.out, "#[inline]");
+            rust!(self.out, "fn stack_capacity(&self) -> usize {{");
+            rust!(self.out, "  {}", depth);
+            rust!(self.out, "}}");
+        }
+
         rust!(self.out, "");
         rust!(self.out, "#[inline]");
         rust!(
@@ -258,11 +337,12 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         rust!(
             self.out,
             "{p}action(state, {})",
-            // Avoid needless 1 subtract by 1
-            if self.grammar.terminals.all.len() == 1 {
-                "0".to_string()
-            } else {
-                format!("{} - 1", self.grammar.terminals.all.len())
+            // Avoid needless 1 subtract by 1, and an overflowing one when
+            // there are no terminals (then the row is empty, and the error
+            // action is never looked up)
+            match self.grammar.terminals.all.len() {
+                0 | 1 => "0".to_string(),
+                len => format!("{} - 1", len),
             },
             p = self.prefix,
         );
@@ -278,6 +358,13 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         rust!(self.out, "{p}EOF_ACTION[state as usize]", p = self.prefix,);
         rust!(self.out, "}}");
 
+        if let Some(ref disambiguate) = self.grammar.algorithm.disambiguate {
+            self.write_lookahead_action_fn(disambiguate)?;
+        }
+        if let Some(window) = self.grammar.algorithm.backtrack {
+            self.write_conflicting_actions_fn(window)?;
+        }
+
         rust!(self.out, "");
         rust!(self.out, "#[inline]");
         rust!(
@@ -324,6 +411,26 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         );
         rust!(self.out, "}}");
 
+        if self.grammar.algorithm.narrow_expected {
+            rust!(self.out, "");
+            rust!(self.out, "fn expected_tokens_before(");
+            rust!(self.out, "&self,");
+            rust!(
+                self.out,
+                "states: &[{state_type}],",
+                state_type = state_type
+            );
+            rust!(self.out, "opt_token_index: Option<usize>,");
+            rust!(self.out, ") -> alloc::vec::Vec<alloc::string::String> {{");
+            rust!(
+                self.out,
+                "{p}expected_tokens_before(states, opt_token_index, {pde})",
+                p = self.prefix,
+                pde = phantom_data_expr,
+            );
+            rust!(self.out, "}}");
+        }
+
         rust!(self.out, "");
         rust!(self.out, "#[inline]");
         rust!(self.out, "fn uses_error_recovery(&self) -> bool {{");
@@ -377,6 +484,40 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
             ") -> Option<{p}state_machine::ParseResult<Self>> {{",
             p = self.prefix,
         );
+        if self.grammar.algorithm.count_reductions {
+            rust!(
+                self.out,
+                "{p}REDUCTION_COUNTS.with(|counts| counts.borrow_mut()[action as usize] += 1);",
+                p = self.prefix,
+            );
+        }
+        if let Some(limit) = self.grammar.algorithm.reduction_limit {
+            rust!(self.out, "self.{p}reductions += 1;", p = self.prefix);
+            rust!(
+                self.out,
+                "if self.{p}reductions > {limit} {{",
+                p = self.prefix,
+                limit = limit,
+            );
+            // report the lookahead, or at the end of the input, the end
+            // of the last symbol
+            rust!(
+                self.out,
+                "let location = start_location.or_else(|| symbols.last().map(|s| &s.2));"
+            );
+            rust!(
+                self.out,
+                "let location = location.cloned().unwrap_or_default();"
+            );
+            // the conversion is a no-op when the error type is
+            // `LimitExceeded` itself
+            rust!(
+                self.out,
+                "#[allow(clippy::useless_conversion)]return Some(Err({p}lalrpop_util::ParseError::User {{ error: core::convert::From::from({p}lalrpop_util::LimitExceeded::Reductions {{ location }}) }}));",
+                p = self.prefix,
+            );
+            rust!(self.out, "}}");
+        }
         rust!(self.out, "{p}reduce(", p = self.prefix);
         for Parameter { name, .. } in self.grammar.parameters.iter() {
             rust!(self.out, "self.{},", name);
@@ -413,6 +554,9 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         // sometimes some of the variants are not used, particularly
         // if we are generating multiple parsers from the same file:
         rust!(self.out, "#[allow(dead_code)]");
+        if self.grammar.algorithm.incremental {
+            rust!(self.out, "#[derive(Clone)]");
+        }
         rust!(
             self.out,
             "pub(crate) enum {}Symbol<{}>",
@@ -472,7 +616,9 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         Ok(())
     }
 
-    fn write_parse_table(&mut self) -> io::Result<()> {
+    /// The action table, as a two-dimensional matrix with one row per
+    /// state and one column per terminal.
+    fn write_action_table(&mut self) -> io::Result<()> {
         let state_type = self.custom.state_type;
 
         // The table is a two-dimensional matrix indexed first by state
@@ -488,9 +634,7 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
             rust!(self.out, "// State {}", index);
 
             if Tls::session().emit_comments {
-                for item in state.items.vec.iter() {
-                    rust!(self.out, "//     {:?}", item);
-                }
+                self.write_item_comments(&state.items.vec)?;
             }
 
             // Write an action for each terminal (either shift, reduce, or error).
@@ -519,18 +663,120 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
 
         rust!(
             self.out,
-            "{p}ACTION[(state as usize) {} + integer]",
-            // Leads to multliplication by 1
-            if self.grammar.terminals.all.len() == 1 {
-                "".to_string()
-            } else {
-                format!("* {}", self.grammar.terminals.all.len())
+            "{p}ACTION[{}integer]",
+            // Leads to multliplication by 1, or by 0 when there are no
+            // terminals, and so no rows
+            match self.grammar.terminals.all.len() {
+                0 => "".to_string(),
+                1 => "(state as usize) + ".to_string(),
+                len => format!("(state as usize) * {} + ", len),
             },
             p = self.prefix,
         );
 
         rust!(self.out, "}}");
 
+        Ok(())
+    }
+
+    /// The action table with its rows overlapped, as by `comb_vector`,
+    /// for `#[compact_tables]`. An action is looked up at the state's
+    /// displacement plus the terminal index, and is an error unless
+    /// `CHECK` names the state there.
+    fn write_compact_action_table(&mut self) -> io::Result<()> {
+        let state_type = self.custom.state_type;
+
+        let rows: Vec<Vec<i32>> = self
+            .states
+            .iter()
+            .map(|state| {
+                self.grammar
+                    .terminals
+                    .all
+                    .iter()
+                    .map(|terminal| match state.shifts.get(terminal) {
+                        Some(new_state) => new_state.0 as i32 + 1,
+                        None => {
+                            Self::write_reduction(
+                                &self.custom,
+                                state,
+                                &Token::Terminal(terminal.clone()),
+                            )
+                            .0
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let (base, check, next) = comb_vector(&rows);
+
+        let max_base = base.iter().copied().max().unwrap_or(0);
+        let base_type = if max_base <= u8::MAX as usize {
+            "u8"
+        } else if max_base <= u16::MAX as usize {
+            "u16"
+        } else {
+            "u32"
+        };
+
+        rust!(
+            self.out,
+            "const {}ACTION_BASE: &[{}] = &[",
+            self.prefix,
+            base_type
+        );
+        self.out
+            .write_table_row(base.iter().map(|&base| (base as i32, "")))?;
+        rust!(self.out, "];");
+        rust!(
+            self.out,
+            "const {}ACTION_CHECK: &[{}] = &[",
+            self.prefix,
+            state_type
+        );
+        self.out
+            .write_table_row(check.iter().map(|&state| (state, "")))?;
+        rust!(self.out, "];");
+        rust!(
+            self.out,
+            "const {}ACTION_NEXT: &[{}] = &[",
+            self.prefix,
+            state_type
+        );
+        self.out
+            .write_table_row(next.iter().map(|&action| (action, "")))?;
+        rust!(self.out, "];");
+
+        rust!(
+            self.out,
+            "fn {p}action(state: {state_type}, integer: usize) -> {state_type} {{",
+            p = self.prefix,
+            state_type = state_type,
+        );
+        rust!(
+            self.out,
+            "let index = {p}ACTION_BASE[state as usize] as usize + integer;",
+            p = self.prefix
+        );
+        rust!(
+            self.out,
+            "if {p}ACTION_CHECK.get(index) == Some(&state) {{ {p}ACTION_NEXT[index] }} else {{ 0 }}",
+            p = self.prefix
+        );
+        rust!(self.out, "}}");
+
+        Ok(())
+    }
+
+    fn write_parse_table(&mut self) -> io::Result<()> {
+        let state_type = self.custom.state_type;
+
+        if self.grammar.algorithm.compact_tables {
+            self.write_compact_action_table()?;
+        } else {
+            self.write_action_table()?;
+        }
+
         // Actions on EOF. Indexed just by state.
         rust!(
             self.out,
@@ -540,7 +786,15 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         );
         for (index, state) in self.states.iter().enumerate() {
             rust!(self.out, "// State {}", index);
-            let reduction = Self::write_reduction(&self.custom, state, &Token::Eof);
+            // reducing the start symbol is how the parser accepts
+            let reduction = match Self::write_reduction(&self.custom, state, &Token::Eof) {
+                (action, Comment::Reduce(token, production))
+                    if production.nonterminal == self.start_symbol =>
+                {
+                    (action, Comment::Accept(token))
+                }
+                reduction => reduction,
+            };
             self.out.write_table_row(Some(reduction))?;
         }
         rust!(self.out, "];");
@@ -703,9 +957,175 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         }
     }
 
-    fn write_parser_fn(&mut self) -> io::Result<()> {
-        let phantom_data_expr = self.phantom_data_expr();
+    /// The actions of `state` on `token`, for `#[disambiguate]` and
+    /// `#[backtrack]`: the shift first, if any, then the reductions.
+    /// The first one is the action stored in the tables.
+    fn alternatives(
+        custom: &TableDriven<'grammar>,
+        state: &Lr1State<'grammar>,
+        token: &Token,
+    ) -> Vec<(i32, String)> {
+        let shift = match *token {
+            Token::Terminal(ref terminal) => state.shifts.get(terminal),
+            _ => None,
+        };
+        let shift = shift.map(|new_state| (new_state.0 as i32 + 1, "Shift".to_string()));
+        let reductions = state
+            .reductions
+            .iter()
+            .filter(|&(t, _)| t.contains(token))
+            .map(|&(_, production)| {
+                let text = format!(
+                    "{} = {}",
+                    production.nonterminal,
+                    Sep(" ", &production.symbols)
+                );
+                (
+                    -(custom.reduce_indices[production] as i32 + 1),
+                    format!("Reduce({:?})", text),
+                )
+            });
+        shift.into_iter().chain(reductions).collect()
+    }
+
+    /// Emits `lookahead_action` for `#[disambiguate]`, which passes the
+    /// alternatives of each conflict to the user's function along with
+    /// the lookahead, and takes the action at the index it returns, or
+    /// reports an error if there is no such action. Other lookaheads are
+    /// looked up in the tables.
+    fn write_lookahead_action_fn(&mut self, disambiguate: &str) -> io::Result<()> {
+        let state_type = self.custom.state_type;
+        let eof_index = self.grammar.terminals.all.len();
+
+        rust!(self.out, "");
+        rust!(
+            self.out,
+            "fn lookahead_action(&self, state: {state_type}, lookahead: core::option::Option<(usize, &Self::Token)>) -> {state_type} {{",
+            state_type = state_type,
+        );
+        rust!(self.out, "let integer = match lookahead {{");
+        rust!(
+            self.out,
+            "core::option::Option::Some((integer, _)) => integer,"
+        );
+        rust!(self.out, "core::option::Option::None => {},", eof_index);
+        rust!(self.out, "}};");
+        rust!(
+            self.out,
+            "let (actions, alternatives): (&[{state_type}], &[{p}state_machine::Alternative]) = match (state, integer) {{",
+            state_type = state_type,
+            p = self.prefix,
+        );
+        let tokens: Vec<(usize, Token)> = self
+            .grammar
+            .terminals
+            .all
+            .iter()
+            .map(|terminal| Token::Terminal(terminal.clone()))
+            .chain(Some(Token::Eof))
+            .enumerate()
+            .collect();
+        for (index, state) in self.states.iter().enumerate() {
+            for (integer, token) in &tokens {
+                let alternatives = Self::alternatives(&self.custom, state, token);
+                if alternatives.len() < 2 {
+                    continue;
+                }
+                rust!(
+                    self.out,
+                    "({}, {}) => (&[{}], &[{}]),",
+                    index,
+                    integer,
+                    alternatives.iter().map(|(action, _)| action).format(", "),
+                    alternatives
+                        .iter()
+                        .map(|(_, alternative)| format!(
+                            "{}state_machine::Alternative::{}",
+                            self.prefix, alternative
+                        ))
+                        .format(", "),
+                );
+            }
+        }
+        rust!(self.out, "_ => return match lookahead {{");
+        rust!(
+            self.out,
+            "core::option::Option::Some((integer, _)) => {p}action(state, integer),",
+            p = self.prefix,
+        );
+        rust!(
+            self.out,
+            "core::option::Option::None => {p}EOF_ACTION[state as usize],",
+            p = self.prefix,
+        );
+        rust!(self.out, "}},");
+        rust!(self.out, "}};");
+        // an index out of range takes the error action, `0`
+        rust!(
+            self.out,
+            "let index = {}(alternatives, lookahead.map(|(_, token)| token));",
+            disambiguate,
+        );
+        rust!(self.out, "actions.get(index).copied().unwrap_or(0)");
+        rust!(self.out, "}}");
+        Ok(())
+    }
+
+    /// Emits `conflicting_actions` and `backtrack_window` for
+    /// `#[backtrack]`, listing the alternatives of each conflict in the
+    /// order in which the parser tries them.
+    fn write_conflicting_actions_fn(&mut self, window: usize) -> io::Result<()> {
+        let state_type = self.custom.state_type;
+        let eof_index = self.grammar.terminals.all.len();
+
+        rust!(self.out, "");
+        rust!(
+            self.out,
+            "fn conflicting_actions(&self, state: {state_type}, opt_token_index: core::option::Option<usize>) -> &[{state_type}] {{",
+            state_type = state_type,
+        );
+        rust!(
+            self.out,
+            "match (state, opt_token_index.unwrap_or({})) {{",
+            eof_index
+        );
+        let tokens: Vec<(usize, Token)> = self
+            .grammar
+            .terminals
+            .all
+            .iter()
+            .map(|terminal| Token::Terminal(terminal.clone()))
+            .chain(Some(Token::Eof))
+            .enumerate()
+            .collect();
+        for (index, state) in self.states.iter().enumerate() {
+            for (integer, token) in &tokens {
+                let alternatives = Self::alternatives(&self.custom, state, token);
+                if alternatives.len() < 2 {
+                    continue;
+                }
+                rust!(
+                    self.out,
+                    "({}, {}) => &[{}],",
+                    index,
+                    integer,
+                    alternatives.iter().map(|(action, _)| action).format(", "),
+                );
+            }
+        }
+        rust!(self.out, "_ => &[],");
+        rust!(self.out, "}}");
+        rust!(self.out, "}}");
 
+        rust!(self.out, "");
+        rust!(self.out, "#[inline]");
+        rust!(self.out, "fn backtrack_window(&self) -> usize {{");
+        rust!(self.out, "{}", window);
+        rust!(self.out, "}}");
+        Ok(())
+    }
+
+    fn write_parser_fn(&mut self) -> io::Result<()> {
         self.start_parser_fn()?;
 
         self.define_tokens()?;
@@ -716,15 +1136,202 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
             p = self.prefix,
         );
         rust!(self.out, "{p}StateMachine {{", p = self.prefix);
-        for Parameter { name, .. } in &self.grammar.parameters {
-            rust!(self.out, "{},", name);
-        }
+        self.write_state_machine_fields()?;
+        rust!(self.out, "}},");
+        rust!(self.out, "{p}tokens,", p = self.prefix);
+        rust!(self.out, ")");
+
+        self.end_parser_fn()
+    }
+
+    /// Emits `parse_partial`, which behaves like `parse` but on
+    /// failure also returns the symbols on the parser stack (see
+    /// `state_machine::Parser::drive_partial`).
+    fn write_partial_parser_fn(&mut self) -> io::Result<()> {
+        let return_type = format!(
+            "Result<{start}, {p}state_machine::PartialParse<{loc}, {tok}, {err}, {sym}>>",
+            start = self.types.nonterminal_type(&self.start_symbol),
+            p = self.prefix,
+            loc = self.types.terminal_loc_type(),
+            tok = self.types.terminal_token_type(),
+            err = self.types.error_type(),
+            sym = self.symbol_type(),
+        );
+
+        self.write_extra_parser_fn("parse_partial", return_type, "drive_partial")
+    }
+
+    /// Emits `parse_prefix`, which parses only as much of the input
+    /// as it can and returns the token it stopped at (see
+    /// `state_machine::Parser::drive_prefix`).
+    fn write_prefix_parser_fn(&mut self) -> io::Result<()> {
+        let return_type = format!(
+            "Result<({start}, core::option::Option<({loc}, {tok}, {loc})>), {p}lalrpop_util::ParseError<{loc}, {tok}, {err}>>",
+            start = self.types.nonterminal_type(&self.start_symbol),
+            p = self.prefix,
+            loc = self.types.terminal_loc_type(),
+            tok = self.types.terminal_token_type(),
+            err = self.types.error_type(),
+        );
+
+        self.write_extra_parser_fn("parse_prefix", return_type, "drive_prefix")
+    }
+
+    /// Emits `parse_items`, which returns an iterator that parses one
+    /// value after another from the same input (see
+    /// `state_machine::ItemParser`).
+    fn write_items_parser_fn(&mut self) -> io::Result<()> {
+        let return_type = format!(
+            "impl Iterator<Item = Result<{start}, {p}lalrpop_util::ParseError<{loc}, {tok}, {err}>>>",
+            start = self.types.nonterminal_type(&self.start_symbol),
+            p = self.prefix,
+            loc = self.types.terminal_loc_type(),
+            tok = self.types.terminal_token_type(),
+            err = self.types.error_type(),
+        );
+
+        self.start_extra_parser_fn("parse_items", return_type)?;
+        self.define_tokens()?;
+
         rust!(
             self.out,
-            "{p}phantom: {phantom},",
+            "{p}state_machine::ItemParser::new(",
             p = self.prefix,
-            phantom = phantom_data_expr,
         );
+        rust!(self.out, "{p}StateMachine {{", p = self.prefix);
+        self.write_state_machine_fields()?;
+        rust!(self.out, "}},");
+        rust!(self.out, "{p}tokens,", p = self.prefix);
+        rust!(self.out, ")");
+
+        self.end_parser_fn()
+    }
+
+    /// Emits the names of the terminals and nonterminals in the order
+    /// of the columns of the action table and the `nt` argument of the
+    /// goto function, along with the index of the start state, so that
+    /// tools can translate table entries back to the grammar.
+    fn write_symbol_tables(&mut self) -> io::Result<()> {
+        let terminals: Vec<String> = self
+            .grammar
+            .terminals
+            .all
+            .iter()
+            .map(|terminal| format!("{:?}", terminal.to_string()))
+            .collect();
+        let nonterminals: Vec<String> = self
+            .custom
+            .all_nonterminals
+            .iter()
+            .map(|nonterminal| format!("{:?}", nonterminal.to_string()))
+            .collect();
+
+        rust!(self.out, "impl {}Parser {{", self.user_start_symbol);
+        rust!(self.out, "#[allow(dead_code)]");
+        rust!(
+            self.out,
+            "pub(crate) const TERMINALS: &'static [&'static str] = &[{}];",
+            Sep(", ", &terminals),
+        );
+        rust!(self.out, "#[allow(dead_code)]");
+        rust!(
+            self.out,
+            "pub(crate) const NONTERMINALS: &'static [&'static str] = &[{}];",
+            Sep(", ", &nonterminals),
+        );
+        rust!(self.out, "#[allow(dead_code)]");
+        rust!(self.out, "pub(crate) const START_STATE: usize = 0;");
+        rust!(self.out, "}}");
+        Ok(())
+    }
+
+    /// Emits the thread-local counters bumped by each reduction, and a
+    /// `take_reduction_counts` method on the parser struct which
+    /// returns how often each production was reduced since the last
+    /// call, in the order of the `__reduce` actions.
+    fn write_reduction_counts(&mut self) -> io::Result<()> {
+        let productions: Vec<String> = self
+            .grammar
+            .nonterminals
+            .values()
+            .flat_map(|nt| &nt.productions)
+            .map(|production| {
+                format!(
+                    "{:?}",
+                    format!(
+                        "{} = {}",
+                        production.nonterminal,
+                        Sep(" ", &production.symbols)
+                    )
+                )
+            })
+            .collect();
+
+        rust!(
+            self.out,
+            "const {p}PRODUCTIONS: &[&str] = &[{}];",
+            Sep(", ", &productions),
+            p = self.prefix,
+        );
+        rust!(self.out, "std::thread_local! {{");
+        rust!(
+            self.out,
+            "static {p}REDUCTION_COUNTS: core::cell::RefCell<alloc::vec::Vec<usize>> = \
+             core::cell::RefCell::new(alloc::vec![0; {n}]);",
+            p = self.prefix,
+            n = productions.len(),
+        );
+        rust!(self.out, "}}");
+
+        let visibility = Visibility::Pub(Some(Path::from_id(Atom::from("crate"))));
+        rust!(self.out, "impl {}Parser {{", self.user_start_symbol);
+        rust!(self.out, "#[allow(dead_code)]");
+        self.out
+            .fn_header(&visibility, "take_reduction_counts".to_owned())
+            .with_parameters(Some("&self".to_owned()))
+            .with_return_type("alloc::vec::Vec<(&'static str, usize)>")
+            .emit()?;
+        rust!(self.out, "{{");
+        rust!(
+            self.out,
+            "{p}REDUCTION_COUNTS.with(|counts| {{",
+            p = self.prefix
+        );
+        rust!(
+            self.out,
+            "let counts = core::mem::replace(&mut *counts.borrow_mut(), alloc::vec![0; {n}]);",
+            n = productions.len(),
+        );
+        rust!(
+            self.out,
+            "{p}PRODUCTIONS.iter().copied().zip(counts).collect()",
+            p = self.prefix,
+        );
+        rust!(self.out, "}})");
+        rust!(self.out, "}}"); // fn
+        rust!(self.out, "}}"); // impl
+        Ok(())
+    }
+
+    /// Emits a method `name` on the parser struct which passes the
+    /// input to `state_machine::Parser::{drive}`.
+    fn write_extra_parser_fn(
+        &mut self,
+        name: &str,
+        return_type: String,
+        drive: &str,
+    ) -> io::Result<()> {
+        self.start_extra_parser_fn(name, return_type)?;
+        self.define_tokens()?;
+
+        rust!(
+            self.out,
+            "{p}state_machine::Parser::{drive}(",
+            p = self.prefix,
+            drive = drive,
+        );
+        rust!(self.out, "{p}StateMachine {{", p = self.prefix);
+        self.write_state_machine_fields()?;
         rust!(self.out, "}},");
         rust!(self.out, "{p}tokens,", p = self.prefix);
         rust!(self.out, ")");
@@ -732,6 +1339,44 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         self.end_parser_fn()
     }
 
+    /// Emits a constructor `fn_name` on the parser struct which wraps
+    /// the state machine in `state_machine::{driver}`: either a
+    /// `PushParser`, which the caller drives one token at a time, or
+    /// an `EventParser`, which reports shifts and reductions rather
+    /// than running action code.
+    fn write_driver_fn(&mut self, fn_name: &str, driver: &str) -> io::Result<()> {
+        let machine = self.custom.machine.clone();
+        let visibility = Visibility::Pub(Some(Path::from_id(Atom::from("crate"))));
+
+        rust!(self.out, "impl {}Parser {{", self.user_start_symbol);
+        rust!(self.out, "#[allow(dead_code)]");
+        self.out
+            .fn_header(&visibility, fn_name.to_owned())
+            .with_parameters(Some("&self".to_owned()))
+            .with_type_parameters(&machine.type_parameters)
+            .with_where_clauses(&machine.where_clauses)
+            .with_parameters(&machine.fields)
+            .with_return_type(format!(
+                "{p}state_machine::{driver}<{p}StateMachine<{mtp}>>",
+                p = self.prefix,
+                driver = driver,
+                mtp = Sep(", ", &machine.type_parameters),
+            ))
+            .emit()?;
+        rust!(self.out, "{{");
+        rust!(
+            self.out,
+            "{p}state_machine::{driver}::new({p}StateMachine {{",
+            p = self.prefix,
+            driver = driver,
+        );
+        self.write_state_machine_fields()?;
+        rust!(self.out, "}})");
+        rust!(self.out, "}}"); // fn
+        rust!(self.out, "}}"); // impl
+        Ok(())
+    }
+
     fn write_token_to_integer_fn(&mut self) -> io::Result<()> {
         let token_type = self.types.terminal_token_type();
 
@@ -756,7 +1401,10 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
             .emit()?;
         rust!(self.out, "{{");
 
-        rust!(self.out, "match *{p}token {{", p = self.prefix);
+        match self.grammar.algorithm.classify {
+            Some(ref classify) => rust!(self.out, "match {}({}token) {{", classify, self.prefix),
+            None => rust!(self.out, "match *{p}token {{", p = self.prefix),
+        }
 
         for (terminal, index) in self.grammar.terminals.all.iter().zip(0..) {
             if *terminal == TerminalString::Error {
@@ -853,15 +1501,21 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
                 .collect::<Vec<_>>();
 
             if !pattern_names.is_empty() {
+                // a classifying function borrows the token, so the
+                // values it binds cannot borrow from the token itself
+                let scrutinee = match self.grammar.algorithm.classify {
+                    Some(ref classify) => format!("{}(&{}token)", classify, self.prefix),
+                    None => format!("{}token", self.prefix),
+                };
                 rust!(
                     self.out,
-                    "{} => match {}token {{",
+                    "{} => match {} {{",
                     indices
                         .iter()
                         .map(|(index, _)| index)
                         .format(" | ")
                         .to_string(),
-                    self.prefix
+                    scrutinee
                 );
                 rust!(
                     self.out,
@@ -1522,6 +2176,158 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TableDrive
         Ok(())
     }
 
+    /// With `#[narrow_expected]`, the expected tokens of an error are
+    /// narrowed down to those after which the unexpected token (or EOF)
+    /// would be accepted, so that inserting any one of them would let
+    /// the parse go on. If there are none, every expected token is
+    /// reported as usual. This emits
+    ///
+    /// ```ignore
+    /// fn __expected_tokens_before(
+    ///     __states: &[i32],
+    ///     __opt_integer: Option<usize>,
+    /// ) -> Vec<String> { ... }
+    /// ```
+    ///
+    /// along with `__simulate_shift`, which returns the state stack
+    /// after the reductions triggered by a token and its shift, or
+    /// `None` if the token is an error.
+    fn write_expected_tokens_before_fn(&mut self) -> io::Result<()> {
+        let phantom_data_expr = self.phantom_data_expr();
+        let state_type = self.custom.state_type;
+
+        let parameters = vec![
+            format!("{p}states: &[{typ}]", p = self.prefix, typ = state_type),
+            format!("{p}integer: usize", p = self.prefix),
+            format!("_: {}", self.phantom_data_type()),
+        ];
+        self.out
+            .fn_header(&Visibility::Priv, format!("{}simulate_shift", self.prefix))
+            .with_type_parameters(&self.custom.machine.type_parameters)
+            .with_where_clauses(&self.custom.machine.where_clauses)
+            .with_parameters(parameters)
+            .with_return_type(format!("Option<alloc::vec::Vec<{}>>", state_type))
+            .emit()?;
+        rust!(self.out, "{{");
+        rust!(
+            self.out,
+            "let mut {p}states = {p}states.to_vec();",
+            p = self.prefix
+        );
+        rust!(self.out, "loop {{");
+        rust!(
+            self.out,
+            "let {p}top = {p}states[{p}states.len() - 1];",
+            p = self.prefix
+        );
+        rust!(
+            self.out,
+            "let {p}action = {p}action({p}top, {p}integer);",
+            p = self.prefix
+        );
+        rust!(
+            self.out,
+            "if {p}action == 0 {{ return None; }}",
+            p = self.prefix
+        );
+        rust!(self.out, "if {p}action > 0 {{", p = self.prefix);
+        rust!(self.out, "{p}states.push({p}action - 1);", p = self.prefix);
+        rust!(self.out, "return Some({p}states);", p = self.prefix);
+        rust!(self.out, "}}");
+        rust!(
+            self.out,
+            "let ({p}to_pop, {p}nt) = match {p}simulate_reduce(-({p}action + 1), {pde}) {{",
+            p = self.prefix,
+            pde = phantom_data_expr,
+        );
+        rust!(
+            self.out,
+            "{p}state_machine::SimulatedReduce::Reduce {{",
+            p = self.prefix,
+        );
+        rust!(self.out, "states_to_pop, nonterminal_produced",);
+        rust!(self.out, "}} => (states_to_pop, nonterminal_produced),",);
+        rust!(
+            self.out,
+            "{p}state_machine::SimulatedReduce::Accept => return None,",
+            p = self.prefix,
+        );
+        rust!(self.out, "}};");
+        rust!(
+            self.out,
+            "{p}states.truncate({p}states.len() - {p}to_pop);",
+            p = self.prefix
+        );
+        rust!(
+            self.out,
+            "let {p}top = {p}states[{p}states.len() - 1];",
+            p = self.prefix
+        );
+        rust!(
+            self.out,
+            "{p}states.push({p}goto({p}top, {p}nt));",
+            p = self.prefix
+        );
+        rust!(self.out, "}}"); // end loop
+        rust!(self.out, "}}"); // end fn
+
+        let parameters = vec![
+            format!("{p}states: &[{typ}]", p = self.prefix, typ = state_type),
+            format!("{p}opt_integer: Option<usize>", p = self.prefix),
+            format!("_: {}", self.phantom_data_type()),
+        ];
+        self.out
+            .fn_header(
+                &Visibility::Priv,
+                format!("{}expected_tokens_before", self.prefix),
+            )
+            .with_type_parameters(&self.custom.machine.type_parameters)
+            .with_where_clauses(&self.custom.machine.where_clauses)
+            .with_parameters(parameters)
+            .with_return_type("alloc::vec::Vec<alloc::string::String>")
+            .emit()?;
+        rust!(self.out, "{{");
+        rust!(
+            self.out,
+            "let {p}expected: alloc::vec::Vec<alloc::string::String> = \
+             {p}TERMINAL.iter().enumerate().filter_map(|(index, terminal)| {{",
+            p = self.prefix,
+        );
+        rust!(
+            self.out,
+            "let {p}states = {p}simulate_shift({p}states, index, {pde})?;",
+            p = self.prefix,
+            pde = phantom_data_expr,
+        );
+        rust!(
+            self.out,
+            "if {p}accepts(None, &{p}states, {p}opt_integer, {pde}) {{",
+            p = self.prefix,
+            pde = phantom_data_expr,
+        );
+        rust!(
+            self.out,
+            "Some(alloc::string::ToString::to_string(terminal))"
+        );
+        rust!(self.out, "}} else {{");
+        rust!(self.out, "None");
+        rust!(self.out, "}}");
+        rust!(self.out, "}}).collect();");
+        rust!(self.out, "if {p}expected.is_empty() {{", p = self.prefix);
+        rust!(
+            self.out,
+            "{p}expected_tokens_from_states({p}states, {pde})",
+            p = self.prefix,
+            pde = phantom_data_expr,
+        );
+        rust!(self.out, "}} else {{");
+        rust!(self.out, "{p}expected", p = self.prefix);
+        rust!(self.out, "}}");
+        rust!(self.out, "}}"); // end fn
+
+        Ok(())
+    }
+
     fn symbol_type(&self) -> String {
         format!(
             "{p}Symbol<{stp}>",
@@ -1676,3 +2482,53 @@ impl MachineParameters {
         }
     }
 }
+
+/// Overlaps the rows of a sparse table, in which 0 is the common
+/// value, into one vector, returning the displacement of each row and
+/// the `CHECK` and `NEXT` vectors. Column `c` of row `r` is at
+/// `displacement[r] + c`, if `CHECK` there is `r`; otherwise it is 0.
+/// Free slots are marked with -1 in `CHECK`.
+///
+/// Each row takes the lowest displacement at which its nonzero columns
+/// fit into free slots, trying the fullest rows first.
+fn comb_vector(rows: &[Vec<i32>]) -> (Vec<usize>, Vec<i32>, Vec<i32>) {
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|&row| std::cmp::Reverse(rows[row].iter().filter(|&&v| v != 0).count()));
+
+    let mut displacements = vec![0; rows.len()];
+    let (mut check, mut next) = (vec![], vec![]);
+    let mut first_free: usize = 0;
+    for row in order {
+        let columns: Vec<usize> = (0..rows[row].len())
+            .filter(|&column| rows[row][column] != 0)
+            .collect();
+        let Some(&first_column) = columns.first() else {
+            continue;
+        };
+
+        let free = |check: &Vec<i32>, slot: usize| check.get(slot).map_or(true, |&c| c == -1);
+        let mut displacement = first_free.saturating_sub(first_column);
+        while !columns
+            .iter()
+            .all(|&column| free(&check, displacement + column))
+        {
+            displacement += 1;
+        }
+
+        for &column in &columns {
+            let slot = displacement + column;
+            if slot >= check.len() {
+                check.resize(slot + 1, -1);
+                next.resize(slot + 1, 0);
+            }
+            check[slot] = row as i32;
+            next[slot] = rows[row][column];
+        }
+        displacements[row] = displacement;
+        while !free(&check, first_free) {
+            first_free += 1;
+        }
+    }
+
+    (displacements, check, next)
+}
@@ -125,6 +125,8 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TestAll> {
             String::new()
         } else if self.grammar.intern_token.is_some() {
             format!("::<{}>", Sep(", ", &non_lifetimes))
+        } else if self.grammar.algorithm.slice_input {
+            format!("::<{}, _>", Sep(", ", &non_lifetimes))
         } else {
             format!("::<{}, _, _>", Sep(", ", &non_lifetimes))
         };
@@ -141,7 +143,10 @@ impl<'ascent, 'grammar, W: Write> CodeGenerator<'ascent, 'grammar, W, TestAll> {
         for parameter in &self.grammar.parameters {
             rust!(self.out, "{},", parameter.name);
         }
-        if self.grammar.intern_token.is_none() {
+        if self.grammar.algorithm.slice_input {
+            // a slice can simply be read twice
+            rust!(self.out, "{}tokens0,", self.prefix);
+        } else if self.grammar.intern_token.is_none() {
             rust!(self.out, "{}tokens0.clone(),", self.prefix);
         }
         rust!(self.out, ");");
@@ -1,13 +1,20 @@
 //! Core LR(1) types.
 
-use crate::collections::Map;
+use crate::api;
+use crate::collections::{Map, Set};
 use crate::grammar::repr::*;
 use crate::util::Prefix;
 use itertools::Itertools;
 use std::fmt::{Debug, Display, Error, Formatter};
+use std::ops::{Index, IndexMut};
 
 use super::lookahead::*;
 
+/// Items are ordered by production, then dot position, then lookahead.
+/// Productions are compared by value rather than by address, and no two
+/// share a span, so the order is total and the same in every run; the
+/// items of a state are kept sorted in this order, which makes them
+/// canonical.
 #[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Item<'grammar, L: Lookahead> {
     pub production: &'grammar Production,
@@ -43,6 +50,13 @@ impl<'grammar, L: Lookahead> Item<'grammar, L> {
         &self.production.symbols[..self.index]
     }
 
+    /// Renders the item for use in messages, with a bullet marking
+    /// the position of the dot and followed by the lookahead, if this
+    /// kind of item has one: `A = b • c ["d"]`.
+    pub fn display(&self) -> String {
+        format!("{}", DisplayItem(self))
+    }
+
     pub fn symbol_sets(&self) -> SymbolSets<'grammar> {
         let symbols = &self.production.symbols;
         if self.can_shift() {
@@ -113,6 +127,44 @@ impl<'grammar, L: Lookahead> Item<'grammar, L> {
 #[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct StateIndex(pub usize);
 
+/// A value for each state of an automaton, such as scratch data for an
+/// algorithm over the states. It is a `Vec` indexed by `StateIndex`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateMap<T> {
+    values: Vec<T>,
+}
+
+impl<T> StateMap<T> {
+    /// A map giving each of `len` states the value `value`.
+    pub fn new(len: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        StateMap {
+            values: vec![value; len],
+        }
+    }
+
+    /// Replaces the value of the state `index`, returning the old one.
+    pub fn set(&mut self, index: StateIndex, value: T) -> T {
+        std::mem::replace(&mut self.values[index.0], value)
+    }
+}
+
+impl<T> Index<StateIndex> for StateMap<T> {
+    type Output = T;
+
+    fn index(&self, index: StateIndex) -> &T {
+        &self.values[index.0]
+    }
+}
+
+impl<T> IndexMut<StateIndex> for StateMap<T> {
+    fn index_mut(&mut self, index: StateIndex) -> &mut T {
+        &mut self.values[index.0]
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Items<'grammar, L: Lookahead> {
     pub vec: Vec<Item<'grammar, L>>,
@@ -141,7 +193,9 @@ pub enum Action<'grammar> {
     Reduce(&'grammar Production),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+// Conflicts are ordered by state, then lookahead, then production, so
+// sorting a list of them gives the same report on every run.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Conflict<'grammar, L> {
     // when in this state...
     pub state: StateIndex,
@@ -160,14 +214,132 @@ pub struct Conflict<'grammar, L> {
 pub type Lr0Conflict<'grammar> = Conflict<'grammar, Nil>;
 pub type Lr1Conflict<'grammar> = Conflict<'grammar, TokenSet>;
 
+impl<'grammar> Lr1Conflict<'grammar> {
+    /// A token on which both actions are possible. For a shift/reduce
+    /// conflict this is the terminal being shifted; for a
+    /// reduce/reduce conflict, the first token in the lookahead of
+    /// both reductions.
+    pub fn witness(&self) -> Token {
+        match self.action {
+            Action::Shift(ref terminal, _) => Token::Terminal(terminal.clone()),
+            Action::Reduce(_) => self
+                .lookahead
+                .iter()
+                .next()
+                .expect("reduce/reduce conflict without a common lookahead"),
+        }
+    }
+}
+
+/// Renders the conflict along with its witness, for example: on
+/// lookahead `"else"`, shift to state 7 or reduce by `S = "if" E "then" S`.
+impl<'grammar> Display for Lr1Conflict<'grammar> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "on lookahead `{}`, ", self.witness())?;
+        match self.action {
+            Action::Shift(_, next_state) => write!(fmt, "shift to state {}", next_state)?,
+            Action::Reduce(production) => {
+                write!(fmt, "reduce by `{}`", DisplayProduction(production))?
+            }
+        }
+        write!(
+            fmt,
+            " or reduce by `{}`",
+            DisplayProduction(self.production)
+        )
+    }
+}
+
+pub struct DisplayProduction<'grammar>(pub &'grammar Production);
+
+impl<'grammar> Display for DisplayProduction<'grammar> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(
+            fmt,
+            "{} ={}",
+            self.0.nonterminal,
+            Prefix(" ", &self.0.symbols[..])
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct TableConstructionError<'grammar, L: Lookahead> {
     // LR(1) state set, possibly incomplete if construction is
     // configured to terminate early.
     pub states: Vec<State<'grammar, L>>,
 
-    // Conflicts (non-empty) found in those states.
+    // Conflicts found in those states. These are non-empty unless
+    // `inconsistency` is set or construction was cancelled.
     pub conflicts: Vec<Conflict<'grammar, L>>,
+
+    // Set if construction was abandoned because it produced a
+    // malformed state.
+    pub inconsistency: Option<InternalInconsistency<'grammar>>,
+
+    // Set if the `ConflictResolver` answered a conflict with a
+    // resolution that does not apply to it.
+    pub invalid_resolution: Option<Box<InvalidResolution<'grammar>>>,
+}
+
+impl<'grammar, L: Lookahead> TableConstructionError<'grammar, L> {
+    /// The conflicts, grouped by the state they occur in, so that
+    /// related conflicts can be shown together.
+    pub fn conflicts_by_state(&self) -> Map<StateIndex, Vec<&Conflict<'grammar, L>>> {
+        let mut map: Map<_, Vec<_>> = Map::new();
+        for conflict in &self.conflicts {
+            map.entry(conflict.state).or_default().push(conflict);
+        }
+        map
+    }
+}
+
+/// A malformed state. Construction never produces one from a
+/// well-formed grammar, so this points to a bug in LALRPOP rather than
+/// to a problem with the user's grammar.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InternalInconsistency<'grammar> {
+    /// The state has two transitions on the same symbol. Construction
+    /// stops at such a state, which is the last one built.
+    DuplicateTransition { state: StateIndex, symbol: Symbol },
+
+    /// The state reduces `production` on no lookahead at all, so the
+    /// reduction can never happen. Resolving a conflict drops the
+    /// reductions that lose every token, so this means a lookahead
+    /// was emptied somewhere else.
+    EmptyReduceLookahead {
+        state: StateIndex,
+        production: &'grammar Production,
+    },
+}
+
+impl<'grammar> Display for InternalInconsistency<'grammar> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            InternalInconsistency::DuplicateTransition { state, ref symbol } => {
+                write!(fmt, "State {} has two transitions on {}", state, symbol)
+            }
+            InternalInconsistency::EmptyReduceLookahead { state, production } => write!(
+                fmt,
+                "State {} reduces `{}` on an empty lookahead",
+                state,
+                DisplayProduction(production)
+            ),
+        }
+    }
+}
+
+/// A resolution that a `ConflictResolver` gave for a conflict it does
+/// not apply to: shifting in a reduce/reduce conflict, or reducing a
+/// production that the conflict does not name. This is an error in the
+/// user's build script, so it is reported like a grammar error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidResolution<'grammar> {
+    pub conflict: api::Conflict,
+    pub resolution: api::Resolution,
+
+    /// The first production of the conflict, to point the error at.
+    pub production: &'grammar Production,
 }
 
 pub type Lr0TableConstructionError<'grammar> = TableConstructionError<'grammar, Nil>;
@@ -176,6 +348,65 @@ pub type LrResult<'grammar, L> =
     Result<Vec<State<'grammar, L>>, TableConstructionError<'grammar, L>>;
 pub type Lr1Result<'grammar> = LrResult<'grammar, TokenSet>;
 
+/// Summary of why a single LR(0) state is inconsistent. As LR(0)
+/// items carry no lookahead, every reduction in the state competes
+/// with every other action in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lr0StateConflict<'grammar> {
+    /// The state can shift some terminal and also reduce the given
+    /// productions (of which there may be more than one).
+    ShiftReduce(Vec<&'grammar Production>),
+
+    /// The state can reduce more than one of the given productions.
+    ReduceReduce(Vec<&'grammar Production>),
+}
+
+impl Display for Lr0StateConflict<'_> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        let (kind, productions) = match self {
+            Lr0StateConflict::ShiftReduce(productions) => ("shift/reduce", productions),
+            Lr0StateConflict::ReduceReduce(productions) => ("reduce/reduce", productions),
+        };
+        write!(fmt, "{} conflict reducing ", kind)?;
+        for (i, production) in productions.iter().enumerate() {
+            if i > 0 {
+                write!(fmt, ", ")?;
+            }
+            write!(
+                fmt,
+                "`{} ={}`",
+                production.nonterminal,
+                Prefix(" ", &production.symbols[..])
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'grammar> Lr0TableConstructionError<'grammar> {
+    /// Groups the conflicts by state, listing every production that
+    /// an inconsistent state can reduce. States are reported in
+    /// increasing order. A state that can shift and reduce two or more
+    /// productions has both kinds of conflict, shift/reduce first.
+    pub fn state_conflicts(&self) -> Vec<(StateIndex, Lr0StateConflict<'grammar>)> {
+        let mut indices: Vec<StateIndex> = self.conflicts.iter().map(|c| c.state).collect();
+        indices.sort();
+        indices.dedup();
+        let mut result = vec![];
+        for index in indices {
+            let state = &self.states[index.0];
+            let productions: Vec<_> = state.reductions.iter().map(|&(_, p)| p).collect();
+            if !state.shifts.is_empty() {
+                result.push((index, Lr0StateConflict::ShiftReduce(productions.clone())));
+            }
+            if productions.len() > 1 {
+                result.push((index, Lr0StateConflict::ReduceReduce(productions)));
+            }
+        }
+        result
+    }
+}
+
 impl<'grammar, L: Lookahead> Debug for Item<'grammar, L> {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         write!(
@@ -190,6 +421,22 @@ impl<'grammar, L: Lookahead> Debug for Item<'grammar, L> {
     }
 }
 
+struct DisplayItem<'item, 'grammar, L: Lookahead>(&'item Item<'grammar, L>);
+
+impl<'item, 'grammar, L: Lookahead> Display for DisplayItem<'item, 'grammar, L> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        let item = self.0;
+        write!(
+            fmt,
+            "{} ={} \u{2022}{}",
+            item.production.nonterminal,
+            Prefix(" ", &item.production.symbols[..item.index]),
+            Prefix(" ", &item.production.symbols[item.index..])
+        )?;
+        item.lookahead.fmt_as_item_suffix(fmt)
+    }
+}
+
 impl Display for Token {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         match *self {
@@ -219,9 +466,77 @@ impl Display for StateIndex {
 }
 
 impl<'grammar, L: Lookahead> State<'grammar, L> {
+    /// Returns the kernel of this state: the items that are not the
+    /// result of an epsilon move from another item in the state. For
+    /// every state other than the start state, these are exactly the
+    /// items where something has been shifted.
+    pub fn kernel_items(&self) -> impl Iterator<Item = &Item<'grammar, L>> {
+        // the nonterminals that some item can shift, whose productions
+        // are added to the state by epsilon moves
+        let expanded: Set<&NonterminalString> = self
+            .items
+            .vec
+            .iter()
+            .filter_map(|item| match item.shift_symbol() {
+                Some((Symbol::Nonterminal(nt), _)) => Some(nt),
+                _ => None,
+            })
+            .collect();
+        self.items
+            .vec
+            .iter()
+            .filter(move |item| item.index > 0 || !expanded.contains(&item.production.nonterminal))
+    }
+
+    /// Returns the kernel items with their lookahead erased, in sorted
+    /// order and without duplicates. Two states with the same LR(0)
+    /// core differ only in lookahead, and LALR(1) merges them into one.
+    pub fn lr0_core(&self) -> Vec<Lr0Item<'grammar>> {
+        // the items are sorted by production and index before
+        // lookahead, so equal LR(0) items are adjacent
+        self.kernel_items().map(Item::to_lr0).dedup().collect()
+    }
+
+    pub fn shift_entries(&self) -> &Map<TerminalString, StateIndex> {
+        &self.shifts
+    }
+
+    pub fn goto_entries(&self) -> &Map<NonterminalString, StateIndex> {
+        &self.gotos
+    }
+
+    pub fn reduction_entries(&self) -> &[(L, &'grammar Production)] {
+        &self.reductions
+    }
+
+    /// Whether this is the accept state for the augmented start
+    /// symbol `start`: reducing `start` here, at the end of the input,
+    /// completes the parse. No state has a goto on `start`.
+    pub fn is_accept(&self, start: &NonterminalString) -> bool {
+        self.reductions
+            .iter()
+            .any(|(_, production)| production.nonterminal == *start)
+    }
+
     /// Returns the set of symbols which must appear on the stack to
     /// be in this state. This is the *maximum* prefix of any item,
     /// basically.
+    ///
+    /// The prefix of every other item in the state is a suffix of the
+    /// one returned. This holds for any state built from the grammar,
+    /// whether canonical LR(1), lane table, or LR(0): every item of a
+    /// state was reached by the same path of symbols from the start
+    /// state, and an item's prefix is the tail of that path, so items
+    /// can only disagree about how far back their prefix reaches.
+    /// Merging states with the same LR(0) core keeps this, since their
+    /// items have the same prefixes. The recursive ascent code
+    /// generator relies on it to give each state fn a fixed stack
+    /// suffix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state has no items, which is never the case for a
+    /// state built from a grammar.
     pub fn max_prefix(&self) -> &'grammar [Symbol] {
         // Each state fn takes as argument the longest prefix of any
         // item. Note that all items must have compatible prefixes.
@@ -253,7 +568,9 @@ impl<'grammar, L: Lookahead> State<'grammar, L> {
     ///
     /// This would return `[B, C]`. For every state other than the
     /// start state, this will return a list of length at least 1.
-    /// For the start state, returns `[]`.
+    /// For the start state, returns `[]`. The result is always a
+    /// suffix of [`max_prefix`](State::max_prefix), for the reason
+    /// given there.
     pub fn will_pop(&self) -> &'grammar [Symbol] {
         let prefix = self
             .items
@@ -324,3 +641,22 @@ impl<'grammar> SymbolSets<'grammar> {
         }
     }
 }
+
+/// Identifies a production by its position in the grammar, counting
+/// the productions of each nonterminal in turn. This is also the order
+/// of the reduce actions in the generated tables.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProductionId(pub usize);
+
+impl ProductionId {
+    /// The index of every production in `grammar`.
+    pub fn all(grammar: &Grammar) -> Map<&Production, ProductionId> {
+        grammar
+            .nonterminals
+            .values()
+            .flat_map(|data| &data.productions)
+            .enumerate()
+            .map(|(index, production)| (production, ProductionId(index)))
+            .collect()
+    }
+}
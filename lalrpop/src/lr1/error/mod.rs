@@ -1,15 +1,18 @@
 //! Error reporting. For now very stupid and simplistic.
 
-use crate::collections::{set, Set};
+use crate::api;
+use crate::collections::{map, set, Map, Set};
 use crate::grammar::repr::*;
 use crate::lr1::core::*;
 use crate::lr1::example::{Example, ExampleStyles, ExampleSymbol};
 use crate::lr1::first::FirstSets;
 use crate::lr1::lookahead::{Token, TokenSet};
 use crate::lr1::trace::Tracer;
-use crate::message::builder::{BodyCharacter, Builder, Character, MessageBuilder};
+use crate::lr1::{conflict_count, HiddenLeftRecursion};
+use crate::message::builder::{BodyCharacter, Builder, Character, InlineBuilder, MessageBuilder};
 use crate::message::Message;
 use crate::tls::Tls;
+use crate::util::Sep;
 use itertools::Itertools;
 
 #[cfg(test)]
@@ -18,12 +21,216 @@ mod test;
 pub fn report_error<E>(
     grammar: &Grammar,
     error: &Lr1TableConstructionError,
-    reporter: impl FnMut(Message) -> Result<(), E>,
+    mut reporter: impl FnMut(Message) -> Result<(), E>,
 ) -> Result<(), E> {
+    if let Some(ref inconsistency) = error.inconsistency {
+        return reporter(report_inconsistency(grammar, error, inconsistency));
+    }
+    if let Some(ref invalid) = error.invalid_resolution {
+        return reporter(report_invalid_resolution(invalid));
+    }
+    if let Some(expected) = grammar.algorithm.expected_conflicts {
+        reporter(report_conflict_count(grammar, error, expected))?;
+    }
     let mut cx = ErrorReportingCx::new(grammar, &error.states, &error.conflicts);
     cx.report_errors(reporter)
 }
 
+fn report_conflict_count(
+    grammar: &Grammar,
+    error: &Lr1TableConstructionError,
+    expected: usize,
+) -> Message {
+    let span = error
+        .conflicts
+        .first()
+        .map(|conflict| conflict.production.span)
+        .unwrap_or(grammar.token_span);
+    MessageBuilder::new(span)
+        .heading()
+        .text("Unexpected number of conflicts")
+        .end()
+        .body()
+        .begin_wrap()
+        .text(format!(
+            "The grammar expects {} conflicts, but {} were found.",
+            expected,
+            conflict_count(&error.conflicts)
+        ))
+        .end()
+        .end()
+        .end()
+}
+
+fn report_invalid_resolution(invalid: &InvalidResolution) -> Message {
+    let problem = match invalid.resolution {
+        api::Resolution::Reduce(api::ProductionIndex(i)) => format!(
+            "chose to reduce production {}, which the conflict does not have.",
+            i
+        ),
+        _ => "chose to shift, but there is no token to shift.".to_string(),
+    };
+    MessageBuilder::new(invalid.production.span)
+        .heading()
+        .text("Invalid conflict resolution")
+        .end()
+        .body()
+        .begin_wrap()
+        .text("For the conflict in")
+        .text(invalid.conflict.to_string())
+        .punctuated(",")
+        .text("the conflict resolver")
+        .text(problem)
+        .end()
+        .end()
+        .end()
+}
+
+/// A warning about a production found by `unreduced_productions`.
+pub fn report_unreduced_production(production: &Production) -> Message {
+    MessageBuilder::new(production.span)
+        .heading()
+        .text("Warning: production is never reduced")
+        .end()
+        .body()
+        .begin_wrap()
+        .text("The production")
+        .text(format!(
+            "{} = {}",
+            production.nonterminal,
+            Sep(" ", &production.symbols)
+        ))
+        .verbatimed()
+        .text(
+            "loses every conflict it is involved in, so the parser never reduces it. \
+             It is probably shadowed by another alternative.",
+        )
+        .end()
+        .end()
+        .end()
+}
+
+/// A warning that `start` is not SLR(1), although the grammar asks for
+/// `#[SLR]`, so that its states are built as LR(1) instead.
+pub fn report_slr_fallback(grammar: &Grammar, start: &NonterminalString) -> Message {
+    // name the nonterminal that the user declared, rather than the
+    // augmented start symbol
+    let user_nt = grammar
+        .start_nonterminals
+        .iter()
+        .find(|&(_, start_nt)| start_nt == start)
+        .map_or(start, |(user_nt, _)| user_nt);
+    let span = grammar
+        .nonterminals
+        .get(user_nt)
+        .map_or(grammar.token_span, |data| data.span);
+    MessageBuilder::new(span)
+        .heading()
+        .text("Warning: grammar is not SLR(1)")
+        .end()
+        .body()
+        .begin_wrap()
+        .text("The states for")
+        .text(user_nt.to_string())
+        .verbatimed()
+        .text(
+            "have conflicts when built as SLR(1), as `#[SLR]` asks, \
+             so they are built as LR(1) instead.",
+        )
+        .end()
+        .end()
+        .end()
+}
+
+/// A warning about a production found by `hidden_left_recursion`.
+pub fn report_hidden_left_recursion(recursion: &HiddenLeftRecursion<'_>) -> Message {
+    let production = recursion.production;
+    let mut chain = vec![&production.nonterminal];
+    chain.extend(&recursion.chain);
+    MessageBuilder::new(production.span)
+        .heading()
+        .text("Warning: hidden left recursion")
+        .end()
+        .body()
+        .begin_wrap()
+        .text("In the production")
+        .text(format!(
+            "{} = {}",
+            production.nonterminal,
+            Sep(" ", &production.symbols)
+        ))
+        .verbatimed()
+        .punctuated(",")
+        .text(production.symbols[..recursion.prefix].iter().join(" "))
+        .verbatimed()
+        .text("can be empty, so")
+        .text(production.nonterminal.to_string())
+        .verbatimed()
+        .text("can start with itself through")
+        .text(format!("{}", Sep(" -> ", &chain)))
+        .verbatimed()
+        .punctuated(".")
+        .text(
+            "The parser cannot know how many times to reduce the empty symbols \
+             before it sees how deeply this is nested, so this causes conflicts.",
+        )
+        .end()
+        .end()
+        .end()
+}
+
+fn report_inconsistency(
+    grammar: &Grammar,
+    error: &Lr1TableConstructionError,
+    inconsistency: &InternalInconsistency,
+) -> Message {
+    let builder = match *inconsistency {
+        InternalInconsistency::DuplicateTransition { state, ref symbol } => {
+            // point at a production that the malformed state is
+            // working on, if we have it
+            let span = error
+                .states
+                .get(state.0)
+                .and_then(|state| state.items.vec.first())
+                .map(|item| item.production.span)
+                .unwrap_or(grammar.token_span);
+            MessageBuilder::new(span)
+                .heading()
+                .text("Internal error")
+                .end()
+                .body()
+                .begin_wrap()
+                .text(format!(
+                    "LALRPOP constructed a state ({}) with two transitions on",
+                    state.0
+                ))
+                .push(symbol.clone())
+                .verbatimed()
+                .punctuated(".")
+        }
+        InternalInconsistency::EmptyReduceLookahead { state, production } => {
+            MessageBuilder::new(production.span)
+                .heading()
+                .text("Internal error")
+                .end()
+                .body()
+                .begin_wrap()
+                .text(format!(
+                    "LALRPOP constructed a state ({}) that reduces",
+                    state.0
+                ))
+                .text(DisplayProduction(production).to_string())
+                .verbatimed()
+                .text("but on no lookahead, so the reduction can never happen.")
+        }
+    };
+    builder
+        .text("This is a bug in LALRPOP; please report it.")
+        .end()
+        .end()
+        .end()
+}
+
 struct ErrorReportingCx<'cx, 'grammar: 'cx> {
     grammar: &'grammar Grammar,
     first_sets: FirstSets,
@@ -94,8 +301,23 @@ impl<'cx, 'grammar> ErrorReportingCx<'cx, 'grammar> {
         &mut self,
         mut reporter: impl FnMut(Message) -> Result<(), E>,
     ) -> Result<(), E> {
-        for conflict in &token_conflicts(self.conflicts) {
-            reporter(self.report_error(conflict))?
+        for cluster in cluster_conflicts(self.conflicts) {
+            let message = self.report_error(&cluster.representative);
+            let message = match cluster.states.len() {
+                1 => message,
+                n => message.with_note(
+                    InlineBuilder::new()
+                        .begin_wrap()
+                        .text(format!(
+                            "The same conflict occurs in {} other state{}.",
+                            n - 1,
+                            if n == 2 { "" } else { "s" }
+                        ))
+                        .end()
+                        .end(),
+                ),
+            };
+            reporter(message)?
         }
         Ok(())
     }
@@ -417,7 +639,7 @@ impl<'cx, 'grammar> ErrorReportingCx<'cx, 'grammar> {
             .wrap_text("when in this state:")
             .indented();
         for item in self.states[conflict.state.0].items.vec.iter() {
-            builder = builder.text(format!("{:?}", item));
+            builder = builder.text(item.display());
         }
         let mut builder = builder
             .end()
@@ -784,6 +1006,50 @@ fn token_conflicts<'grammar>(
         .collect()
 }
 
+/// Conflicts that differ only in the state where they occur: the same
+/// reduction clashes with the same action on the same lookahead token.
+#[derive(Debug)]
+pub struct ConflictCluster<'grammar> {
+    /// The conflict in the first of `states`, reported for all of them.
+    pub representative: TokenConflict<'grammar>,
+    pub states: Vec<StateIndex>,
+}
+
+/// Groups the per-token conflicts of `conflicts` into clusters, so that
+/// each distinct conflict is reported once no matter how many states it
+/// was copied into. Clusters are in order of their first state.
+pub fn cluster_conflicts<'grammar>(
+    conflicts: &[Conflict<'grammar, TokenSet>],
+) -> Vec<ConflictCluster<'grammar>> {
+    let mut clusters: Vec<ConflictCluster<'grammar>> = vec![];
+    let mut indices: Map<_, usize> = map();
+    for conflict in token_conflicts(conflicts) {
+        // A shift always shifts the lookahead token itself, so only the
+        // production of a reduction is needed to tell actions apart.
+        let reduce = match conflict.action {
+            Action::Shift(..) => None,
+            Action::Reduce(production) => Some(production),
+        };
+        let key = (conflict.production, reduce, conflict.lookahead.clone());
+        match indices.get(&key) {
+            Some(&index) => {
+                let cluster: &mut ConflictCluster<'_> = &mut clusters[index];
+                if !cluster.states.contains(&conflict.state) {
+                    cluster.states.push(conflict.state);
+                }
+            }
+            None => {
+                indices.insert(key, clusters.len());
+                clusters.push(ConflictCluster {
+                    states: vec![conflict.state],
+                    representative: conflict,
+                });
+            }
+        }
+    }
+    clusters
+}
+
 //fn choose_example<'grammar>(states: &[State<'grammar>],
 //                            lookahead: Token,
 //                            conflict: &TokenConflict<'grammar>)
@@ -185,3 +185,30 @@ VarDecl = "let";
         cx.classify(conflict);
     }
 }
+
+#[test]
+fn cluster_repeated_conflicts() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+pub S: () = {
+    "a" E "c" => (),
+    "b" E "d" => (),
+};
+E: () = {
+    E "+" E => (),
+    "x" => (),
+};
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let err = build_states(&grammar, nt("S")).unwrap_err();
+    let clusters = super::cluster_conflicts(&err.conflicts);
+    for cluster in &clusters {
+        println!("{:?}", cluster);
+    }
+    assert_eq!(clusters.len(), 1);
+    assert_eq!(clusters[0].states.len(), 2);
+    assert_eq!(super::token_conflicts(&err.conflicts).len(), 2);
+}
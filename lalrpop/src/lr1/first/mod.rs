@@ -1,6 +1,6 @@
 //! First set construction and computation.
 
-use crate::collections::{map, Map};
+use crate::collections::{map, set, Map, Set};
 use crate::grammar::repr::*;
 use crate::lr1::lookahead::{Token, TokenSet};
 
@@ -9,6 +9,34 @@ use super::Lr1Tls;
 #[cfg(test)]
 mod test;
 
+/// Returns the nonterminals that can derive the empty string. A
+/// nonterminal is nullable if all the symbols of one of its
+/// productions are nullable nonterminals, which is vacuously true of
+/// an empty production; we iterate until no more are found.
+pub fn nullable_set(grammar: &Grammar) -> Set<NonterminalString> {
+    let mut nullable = set();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for nt in grammar.nonterminals.keys() {
+            if nullable.contains(nt) {
+                continue;
+            }
+            let is_nullable = grammar.productions_for(nt).iter().any(|production| {
+                production.symbols.iter().all(|symbol| match symbol {
+                    Symbol::Terminal(_) => false,
+                    Symbol::Nonterminal(nt) => nullable.contains(nt),
+                })
+            });
+            if is_nullable {
+                nullable.insert(nt.clone());
+                changed = true;
+            }
+        }
+    }
+    nullable
+}
+
 #[derive(Clone)]
 pub struct FirstSets {
     map: Map<NonterminalString, TokenSet>,
@@ -103,3 +131,52 @@ impl FirstSets {
         set
     }
 }
+
+/// The FOLLOW set of each nonterminal `X`: the tokens that may appear
+/// immediately after `X` in some sentential form derived from the
+/// start symbol. EOF follows the start symbol.
+#[derive(Clone)]
+pub struct FollowSets {
+    map: Map<NonterminalString, TokenSet>,
+}
+
+impl FollowSets {
+    pub fn new(grammar: &Grammar, start: &NonterminalString) -> FollowSets {
+        FollowSets::with_first_sets(grammar, start, &FirstSets::new(grammar))
+    }
+
+    pub fn with_first_sets(
+        grammar: &Grammar,
+        start: &NonterminalString,
+        first_sets: &FirstSets,
+    ) -> FollowSets {
+        let mut map: Map<NonterminalString, TokenSet> = grammar
+            .nonterminals
+            .keys()
+            .map(|nt| (nt.clone(), TokenSet::new()))
+            .collect();
+        map.insert(start.clone(), TokenSet::eof());
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in grammar.nonterminals.values().flat_map(|p| &p.productions) {
+                for (index, symbol) in production.symbols.iter().enumerate() {
+                    if let Symbol::Nonterminal(ref nt) = *symbol {
+                        // FOLLOW(nt) includes FIRST(rest), and if
+                        // `rest` can be empty, FOLLOW(production.nonterminal)
+                        let rest = &production.symbols[index + 1..];
+                        let follow = first_sets.first1(rest, &map[&production.nonterminal]);
+                        changed |= map.get_mut(nt).unwrap().union_with(&follow);
+                    }
+                }
+            }
+        }
+
+        FollowSets { map }
+    }
+
+    pub fn follow(&self, nt: &NonterminalString) -> &TokenSet {
+        &self.map[nt]
+    }
+}
@@ -1,4 +1,4 @@
-use super::FirstSets;
+use super::{nullable_set, FirstSets, FollowSets};
 use crate::grammar::repr::*;
 use crate::lr1::lookahead::{Token, TokenSet};
 use crate::lr1::tls::Lr1Tls;
@@ -96,3 +96,85 @@ fn basic_first0() {
 
     assert_eq!(first0(&first_sets, &[nt("X")]), vec![la("E")]);
 }
+
+fn follow(follow_sets: &FollowSets, t: &str) -> Vec<Token> {
+    follow_sets
+        .follow(&NonterminalString(Atom::from(t)))
+        .iter()
+        .collect()
+}
+
+/// The expression grammar from the dragon book (4.28), whose FOLLOW
+/// sets are given in example 4.30.
+#[test]
+fn dragon_book_follow() {
+    let grammar = normalized_grammar(
+        r#"
+    grammar;
+    E = T EP;
+    EP = { "+" T EP, () };
+    T = F TP;
+    TP = { "*" F TP, () };
+    F = { "(" E ")", "id" };
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let follow_sets = FollowSets::new(&grammar, &NonterminalString(Atom::from("E")));
+
+    assert_eq!(follow(&follow_sets, "E"), vec![la(")"), Token::Eof]);
+    assert_eq!(follow(&follow_sets, "EP"), vec![la(")"), Token::Eof]);
+    assert_eq!(
+        follow(&follow_sets, "T"),
+        vec![la(")"), la("+"), Token::Eof]
+    );
+    assert_eq!(
+        follow(&follow_sets, "TP"),
+        vec![la(")"), la("+"), Token::Eof]
+    );
+    assert_eq!(
+        follow(&follow_sets, "F"),
+        vec![la(")"), la("*"), la("+"), Token::Eof]
+    );
+}
+
+#[test]
+fn unreachable_follow() {
+    let grammar = normalized_grammar(
+        r#"
+    grammar;
+    S: () = { A "x" => (), A B "y" => () };
+    A = "a";
+    B: () = { "b" => (), => () };
+    X = A; // intentionally unreachable
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let follow_sets = FollowSets::new(&grammar, &NonterminalString(Atom::from("S")));
+
+    assert_eq!(follow(&follow_sets, "S"), vec![Token::Eof]);
+    assert_eq!(follow(&follow_sets, "A"), vec![la("b"), la("x"), la("y")]);
+    assert_eq!(follow(&follow_sets, "B"), vec![la("y")]);
+    assert_eq!(follow(&follow_sets, "X"), vec![]);
+}
+
+#[test]
+fn nullable_chains() {
+    let grammar = normalized_grammar(
+        r#"
+    grammar;
+    S: () = { A B C "x" => (), D => () };
+    A: () = { B C => (), "a" => () };
+    B: () = { C => (), "b" => () };
+    C: () = { => (), "c" C => () };
+    D: () = { "d" => (), A "d" => () };
+    E: () = { E "e" => (), F => () };
+    F: () = E => ();
+"#,
+    );
+
+    let nullable: Vec<_> = nullable_set(&grammar)
+        .into_iter()
+        .map(|nt| nt.to_string())
+        .collect();
+    assert_eq!(nullable, vec!["A", "B", "C"]);
+}
@@ -86,8 +86,11 @@ where
                 token = tokens.next();
             } else if let Some(production) = L::reduction(state, &Token::Terminal(terminal.clone()))
             {
-                let more = self.reduce(production);
-                assert!(more);
+                // without lookahead, as in LR(0) states, the start
+                // symbol can be reduced before the input is used up
+                if !self.reduce(production) {
+                    return Err((state, Token::Terminal(terminal)));
+                }
             } else {
                 return Err((state, Token::Terminal(terminal.clone())));
             }
@@ -0,0 +1,235 @@
+//! Dumps LR(1) states and conflicts as JSON, for consumption by
+//! external tools. There is one section per public nonterminal, each
+//! with its own states. The schema is:
+//!
+//! ```text
+//! {
+//!   "terminals": [{ "name": "\"+\"", "pattern": "Tok::Plus" }, ...],
+//!   "starts": [
+//!     {
+//!       "start": "E",
+//!       "states": [
+//!         {
+//!           "index": 0,
+//!           "items": ["S = • E [Eof]", ...],
+//!           "shifts": { "\"(\"": 3, ... },
+//!           "gotos": { "E": 1, ... },
+//!           "reductions": [{ "lookahead": ["\"+\"", "Eof"], "production": "E = T" }, ...]
+//!         },
+//!         ...
+//!       ],
+//!       "conflicts": [
+//!         {
+//!           "state": 4,
+//!           "lookahead": ["\"+\""],
+//!           "production": "E = E \"+\" E",
+//!           "action": { "shift": 3 } or { "reduce": "E = E \"*\" E" }
+//!         },
+//!         ...
+//!       ]
+//!     },
+//!     ...
+//!   ]
+//! }
+//! ```
+//!
+//! Terminals and nonterminals are named as in the grammar, and
+//! lookahead tokens as in the `.report` file, where the end of input
+//! is `Eof`. All maps and lists are in a deterministic order.
+
+use crate::grammar::repr::*;
+use crate::lr1::core::*;
+use crate::lr1::lookahead::TokenSet;
+use crate::util::Sep;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+#[cfg(test)]
+mod test;
+
+/// Collects the states of each start symbol, to be written out as one
+/// JSON document once they have all been built.
+pub struct JsonReport<'grammar> {
+    grammar: &'grammar Grammar,
+    starts: Vec<Vec<u8>>,
+}
+
+impl<'grammar> JsonReport<'grammar> {
+    pub fn new(grammar: &'grammar Grammar) -> Self {
+        JsonReport {
+            grammar,
+            starts: vec![],
+        }
+    }
+
+    /// Adds the section for the public nonterminal `start`.
+    pub fn add_start(
+        &mut self,
+        start: &NonterminalString,
+        states: &[Lr1State<'_>],
+        conflicts: &[Lr1Conflict<'_>],
+    ) -> io::Result<()> {
+        let mut out = vec![];
+        start_to_json(start, states, conflicts, &mut out)?;
+        self.starts.push(out);
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+
+        writeln!(out, "  \"terminals\": [")?;
+        let terminals: Vec<_> = self.grammar.terminals().collect();
+        for (i, terminal) in terminals.iter().enumerate() {
+            write!(out, "    {{\"name\": {}", string(terminal))?;
+            if let Some(pattern) = self.grammar.conversions.get(terminal) {
+                write!(out, ", \"pattern\": {}", string(pattern))?;
+            }
+            writeln!(out, "}}{}", comma(i, terminals.len()))?;
+        }
+        writeln!(out, "  ],")?;
+
+        writeln!(out, "  \"starts\": [")?;
+        for (i, start) in self.starts.iter().enumerate() {
+            out.write_all(start)?;
+            writeln!(out, "{}", comma(i, self.starts.len()))?;
+        }
+        writeln!(out, "  ]")?;
+
+        writeln!(out, "}}")
+    }
+}
+
+/// Writes the section of one start symbol, without a newline at the
+/// end, so that the caller can put a comma after it.
+fn start_to_json<W: Write>(
+    start: &NonterminalString,
+    states: &[Lr1State<'_>],
+    conflicts: &[Lr1Conflict<'_>],
+    out: &mut W,
+) -> io::Result<()> {
+    writeln!(out, "    {{")?;
+    writeln!(out, "      \"start\": {},", string(start))?;
+
+    writeln!(out, "      \"states\": [")?;
+    for (i, state) in states.iter().enumerate() {
+        writeln!(out, "        {{")?;
+        writeln!(out, "          \"index\": {},", state.index.0)?;
+        writeln!(
+            out,
+            "          \"items\": [{}],",
+            Sep(
+                ", ",
+                &state
+                    .items
+                    .vec
+                    .iter()
+                    .map(|item| string(item.display()))
+                    .collect::<Vec<_>>()
+            )
+        )?;
+        writeln!(
+            out,
+            "          \"shifts\": {{{}}},",
+            Sep(
+                ", ",
+                &state
+                    .shifts
+                    .iter()
+                    .map(|(terminal, index)| format!("{}: {}", string(terminal), index.0))
+                    .collect::<Vec<_>>()
+            )
+        )?;
+        writeln!(
+            out,
+            "          \"gotos\": {{{}}},",
+            Sep(
+                ", ",
+                &state
+                    .gotos
+                    .iter()
+                    .map(|(nonterminal, index)| format!("{}: {}", string(nonterminal), index.0))
+                    .collect::<Vec<_>>()
+            )
+        )?;
+        writeln!(
+            out,
+            "          \"reductions\": [{}]",
+            Sep(
+                ", ",
+                &state
+                    .reductions
+                    .iter()
+                    .map(|(lookahead, production)| format!(
+                        "{{\"lookahead\": {}, \"production\": {}}}",
+                        tokens(lookahead),
+                        string(production_string(production))
+                    ))
+                    .collect::<Vec<_>>()
+            )
+        )?;
+        writeln!(out, "        }}{}", comma(i, states.len()))?;
+    }
+    writeln!(out, "      ],")?;
+
+    writeln!(out, "      \"conflicts\": [")?;
+    for (i, conflict) in conflicts.iter().enumerate() {
+        let action = match conflict.action {
+            Action::Shift(_, index) => format!("{{\"shift\": {}}}", index.0),
+            Action::Reduce(production) => {
+                format!("{{\"reduce\": {}}}", string(production_string(production)))
+            }
+        };
+        writeln!(
+            out,
+            "        {{\"state\": {}, \"lookahead\": {}, \"production\": {}, \"action\": {}}}{}",
+            conflict.state.0,
+            tokens(&conflict.lookahead),
+            string(production_string(conflict.production)),
+            action,
+            comma(i, conflicts.len())
+        )?;
+    }
+    writeln!(out, "      ]")?;
+
+    write!(out, "    }}")
+}
+
+fn production_string(production: &Production) -> String {
+    format!(
+        "{} = {}",
+        production.nonterminal,
+        Sep(" ", &production.symbols)
+    )
+}
+
+fn tokens(set: &TokenSet) -> String {
+    let tokens: Vec<_> = set.iter().map(string).collect();
+    format!("[{}]", Sep(", ", &tokens))
+}
+
+fn comma(index: usize, len: usize) -> &'static str {
+    if index + 1 < len {
+        ","
+    } else {
+        ""
+    }
+}
+
+/// Renders `value` as a JSON string literal.
+fn string<T: Display>(value: T) -> String {
+    let mut result = String::from("\"");
+    for c in value.to_string().chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
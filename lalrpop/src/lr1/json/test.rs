@@ -0,0 +1,195 @@
+use crate::grammar::repr::*;
+use crate::lr1::build::build_lr1_states;
+use crate::lr1::tls::Lr1Tls;
+use crate::test_util::{compare, normalized_grammar};
+use crate::tls::Tls;
+
+use super::JsonReport;
+
+fn to_json(grammar: &Grammar) -> String {
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let mut report = JsonReport::new(grammar);
+    for (user_nt, start_nt) in &grammar.start_nonterminals {
+        match build_lr1_states(grammar, start_nt.clone()) {
+            Ok(states) => report.add_start(user_nt, &states, &[]),
+            Err(err) => report.add_start(user_nt, &err.states, &err.conflicts),
+        }
+        .unwrap();
+    }
+    let mut out = vec![];
+    report.write(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn states() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X(..), "," => Tok::Comma } }
+pub S: () = { S "," "x" => (), "x" => () };
+"#,
+    );
+    compare(
+        to_json(&grammar),
+        r#"{
+  "terminals": [
+    {"name": "\",\"", "pattern": "Tok::Comma"},
+    {"name": "\"x\"", "pattern": "Tok::X(..)"}
+  ],
+  "starts": [
+    {
+      "start": "S",
+      "states": [
+        {
+          "index": 0,
+          "items": ["S = • S \",\" \"x\" [\",\", \"x\", Eof]", "S = • \"x\" [\",\", \"x\", Eof]", "__S = • S [\",\", \"x\", Eof]"],
+          "shifts": {"\"x\"": 2},
+          "gotos": {"S": 1},
+          "reductions": []
+        },
+        {
+          "index": 1,
+          "items": ["S = S • \",\" \"x\" [\",\", \"x\", Eof]", "__S = S • [\",\", \"x\", Eof]"],
+          "shifts": {"\",\"": 3},
+          "gotos": {},
+          "reductions": [{"lookahead": ["Eof"], "production": "__S = S"}]
+        },
+        {
+          "index": 2,
+          "items": ["S = \"x\" • [\",\", \"x\", Eof]"],
+          "shifts": {},
+          "gotos": {},
+          "reductions": [{"lookahead": ["\",\"", "Eof"], "production": "S = \"x\""}]
+        },
+        {
+          "index": 3,
+          "items": ["S = S \",\" • \"x\" [\",\", \"x\", Eof]"],
+          "shifts": {"\"x\"": 4},
+          "gotos": {},
+          "reductions": []
+        },
+        {
+          "index": 4,
+          "items": ["S = S \",\" \"x\" • [\",\", \"x\", Eof]"],
+          "shifts": {},
+          "gotos": {},
+          "reductions": [{"lookahead": ["\",\"", "Eof"], "production": "S = S \",\" \"x\""}]
+        }
+      ],
+      "conflicts": [
+      ]
+    }
+  ]
+}
+"#,
+    );
+}
+
+#[test]
+fn conflicts() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X, "+" => Tok::Plus } }
+pub S: () = { S "+" S => (), "x" => () };
+"#,
+    );
+    let json = to_json(&grammar);
+    let conflicts = &json[json.find("\"conflicts\"").unwrap()..];
+    compare(
+        conflicts,
+        r#""conflicts": [
+        {"state": 4, "lookahead": ["\"+\""], "production": "S = S \"+\" S", "action": {"shift": 3}},
+        {"state": 5, "lookahead": ["\"+\""], "production": "S = S \"+\" S", "action": {"shift": 3}}
+      ]
+    }
+  ]
+}
+"#,
+    );
+}
+
+/// Each public nonterminal gets its own section, with its own states.
+#[test]
+fn starts() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X, "y" => Tok::Y } }
+pub S: () = "x" => ();
+pub T: () = "y" => ();
+"#,
+    );
+    compare(
+        to_json(&grammar),
+        r#"{
+  "terminals": [
+    {"name": "\"x\"", "pattern": "Tok::X"},
+    {"name": "\"y\"", "pattern": "Tok::Y"}
+  ],
+  "starts": [
+    {
+      "start": "S",
+      "states": [
+        {
+          "index": 0,
+          "items": ["S = • \"x\" [\"x\", \"y\", Eof]", "__S = • S [\"x\", \"y\", Eof]"],
+          "shifts": {"\"x\"": 2},
+          "gotos": {"S": 1},
+          "reductions": []
+        },
+        {
+          "index": 1,
+          "items": ["__S = S • [\"x\", \"y\", Eof]"],
+          "shifts": {},
+          "gotos": {},
+          "reductions": [{"lookahead": ["Eof"], "production": "__S = S"}]
+        },
+        {
+          "index": 2,
+          "items": ["S = \"x\" • [\"x\", \"y\", Eof]"],
+          "shifts": {},
+          "gotos": {},
+          "reductions": [{"lookahead": ["Eof"], "production": "S = \"x\""}]
+        }
+      ],
+      "conflicts": [
+      ]
+    },
+    {
+      "start": "T",
+      "states": [
+        {
+          "index": 0,
+          "items": ["T = • \"y\" [\"x\", \"y\", Eof]", "__T = • T [\"x\", \"y\", Eof]"],
+          "shifts": {"\"y\"": 2},
+          "gotos": {"T": 1},
+          "reductions": []
+        },
+        {
+          "index": 1,
+          "items": ["__T = T • [\"x\", \"y\", Eof]"],
+          "shifts": {},
+          "gotos": {},
+          "reductions": [{"lookahead": ["Eof"], "production": "__T = T"}]
+        },
+        {
+          "index": 2,
+          "items": ["T = \"y\" • [\"x\", \"y\", Eof]"],
+          "shifts": {},
+          "gotos": {},
+          "reductions": [{"lookahead": ["Eof"], "production": "T = \"y\""}]
+        }
+      ],
+      "conflicts": [
+      ]
+    }
+  ]
+}
+"#,
+    );
+}
@@ -58,6 +58,18 @@ impl<'grammar> LaneTableConstruct<'grammar> {
                     // algorithm.
                     states
                 }
+                Err(TableConstructionError {
+                    states,
+                    inconsistency: Some(inconsistency),
+                    ..
+                }) => {
+                    return Err(TableConstructionError {
+                        states: self.promote_lr0_states(states),
+                        conflicts: vec![],
+                        inconsistency: Some(inconsistency),
+                        invalid_resolution: None,
+                    });
+                }
                 Err(TableConstructionError { states, .. }) => states,
             }
         };
@@ -84,7 +96,12 @@ impl<'grammar> LaneTableConstruct<'grammar> {
                     );
                     let conflicts: Vec<Conflict<'grammar, TokenSet>> =
                         states.iter().flat_map(Lookahead::conflicts).collect();
-                    return Err(TableConstructionError { states, conflicts });
+                    return Err(TableConstructionError {
+                        states,
+                        conflicts,
+                        inconsistency: None,
+                        invalid_resolution: None,
+                    });
                 }
             }
         }
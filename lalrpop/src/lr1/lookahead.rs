@@ -5,6 +5,7 @@ use crate::lr1::tls::Lr1Tls;
 use bit_set::{self, BitSet};
 use std::fmt::{Debug, Error, Formatter};
 use std::hash::Hash;
+use std::rc::Rc;
 
 pub trait Lookahead: Clone + Debug + Eq + Ord + Hash + Collection<Item = Self> {
     fn fmt_as_item_suffix(&self, fmt: &mut Formatter) -> Result<(), Error>;
@@ -142,9 +143,23 @@ impl Token {
     }
 }
 
+/// A set of lookahead tokens, kept as a dense bitset indexed by
+/// terminal number, with EOF and the error token after the last
+/// terminal. Unions, intersections and comparisons go a word at a
+/// time, and iteration skips empty words.
+///
+/// LR(1) construction creates a great many of these sets, most of them
+/// copies of one another, so the bits are
+/// shared between clones and only copied when a clone is modified.
+/// Sets computed independently can be made to share bits too, with
+/// `intern`.
+///
+/// Equality, ordering and hashing all go by the members of the set,
+/// in order, and never by whether the bits are shared or how much
+/// room they have.
 #[derive(Clone, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TokenSet {
-    bit_set: BitSet<u32>,
+    bit_set: Rc<BitSet<u32>>,
 }
 
 fn with<OP, RET>(op: OP) -> RET
@@ -157,7 +172,7 @@ where
 impl TokenSet {
     pub fn new() -> Self {
         with(|terminals| TokenSet {
-            bit_set: BitSet::with_capacity(terminals.all.len() + 2),
+            bit_set: Rc::new(BitSet::with_capacity(terminals.all.len() + 2)),
         })
     }
 
@@ -165,8 +180,9 @@ impl TokenSet {
     pub fn all() -> Self {
         let mut s = TokenSet::new();
         with(|terminals| {
+            let bit_set = s.bits_mut();
             for i in 0..terminals.all.len() {
-                s.bit_set.insert(i);
+                bit_set.insert(i);
             }
             s.insert_eof();
         });
@@ -195,39 +211,81 @@ impl TokenSet {
         }
     }
 
+    /// Replaces the bits of this set with an identical, shared copy
+    /// from a pool kept alongside the installed terminals.
+    pub fn intern(self) -> TokenSet {
+        TokenSet {
+            bit_set: Lr1Tls::intern(self.bit_set),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn shares_bits_with(&self, other: &TokenSet) -> bool {
+        Rc::ptr_eq(&self.bit_set, &other.bit_set)
+    }
+
+    /// Returns the bits to be modified, copying them first if they
+    /// are shared. Callers should check that the modification will
+    /// change something before calling this.
+    fn bits_mut(&mut self) -> &mut BitSet<u32> {
+        Rc::make_mut(&mut self.bit_set)
+    }
+
     pub fn reserve(&mut self, len: usize) {
-        self.bit_set.reserve_len(len)
+        self.bits_mut().reserve_len(len)
     }
 
     pub fn len(&self) -> usize {
         self.bit_set.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.bit_set.is_empty()
+    }
+
+    fn insert_bit(&mut self, bit: usize) -> bool {
+        !self.bit_set.contains(bit) && self.bits_mut().insert(bit)
+    }
+
     pub fn insert(&mut self, lookahead: Token) -> bool {
         let bit = self.bit(&lookahead);
-        self.bit_set.insert(bit)
+        self.insert_bit(bit)
     }
 
     pub fn insert_with(&mut self, lookahead: Token, terminals: &TerminalSet) -> bool {
         let bit = self.bit_with(&lookahead, terminals);
-        self.bit_set.insert(bit)
+        self.insert_bit(bit)
     }
 
     pub fn insert_eof(&mut self) -> bool {
         let bit = self.eof_bit();
-        self.bit_set.insert(bit)
+        self.insert_bit(bit)
     }
 
     pub fn union_with(&mut self, set: &TokenSet) -> bool {
-        let len = self.len();
-        self.bit_set.union_with(&set.bit_set);
-        self.len() != len
+        if set.bit_set.is_subset(&self.bit_set) {
+            return false;
+        }
+        self.bits_mut().union_with(&set.bit_set);
+        true
+    }
+
+    /// Removes the tokens in `set` from this set; returns true if
+    /// anything was removed.
+    pub fn difference_with(&mut self, set: &TokenSet) -> bool {
+        if self.is_disjoint(set) {
+            return false;
+        }
+        self.bits_mut().difference_with(&set.bit_set);
+        true
     }
 
     pub fn intersection(&self, set: &TokenSet) -> TokenSet {
-        let mut bit_set = self.bit_set.clone();
+        let mut bit_set = (*self.bit_set).clone();
         bit_set.intersect_with(&set.bit_set);
-        TokenSet { bit_set }
+        TokenSet {
+            bit_set: Rc::new(bit_set),
+        }
     }
 
     pub fn contains(&self, token: &Token) -> bool {
@@ -242,9 +300,7 @@ impl TokenSet {
     /// true. Otherwise, returns false.
     pub fn take_eof(&mut self) -> bool {
         let eof_bit = self.eof_bit();
-        let contains_eof = self.bit_set.contains(eof_bit);
-        self.bit_set.remove(eof_bit);
-        contains_eof
+        self.bit_set.contains(eof_bit) && self.bits_mut().remove(eof_bit)
     }
 
     pub fn is_disjoint(&self, other: &TokenSet) -> bool {
@@ -1,17 +1,27 @@
 //! Naive LR(1) generation algorithm.
 
+use crate::api;
+use crate::collections::{map, Map, Set};
 use crate::grammar::repr::*;
+use crate::message::Message;
+use crate::tls::Tls;
+use std::collections::VecDeque;
 
+mod analysis;
+mod automaton;
 mod build;
 mod build_lalr;
+mod build_slr;
 pub mod codegen;
 mod core;
 mod error;
 mod example;
 mod first;
+mod json;
 mod lane_table;
 mod lookahead;
 mod report;
+mod snapshot;
 mod state_graph;
 mod tls;
 mod trace;
@@ -19,23 +29,623 @@ use std::io::{self, Write};
 
 #[cfg(test)]
 mod interpret;
+#[cfg(test)]
+mod test;
 
+pub use self::analysis::analyze_grammar;
+pub use self::automaton::build_automaton;
 pub use self::core::Lr1Result;
-pub use self::error::report_error;
+pub use self::error::{report_error, report_hidden_left_recursion, report_unreduced_production};
+pub use self::json::JsonReport;
+pub use self::snapshot::snapshot_states;
 pub use self::tls::Lr1Tls;
 
+/// `build_states_reporting`, ignoring the warnings.
+#[cfg(test)]
 pub fn build_states(grammar: &Grammar, start: NonterminalString) -> Lr1Result<'_> {
-    let mut lr1_states = if !grammar.algorithm.lalr {
-        build::build_lr1_states(grammar, start)?
+    build_states_reporting(grammar, start, |_| {})
+}
+
+/// Builds the states for `start` with the construction that the
+/// grammar asks for, passing `warn` the warnings that come up on the
+/// way: for now, that an `#[SLR]` grammar is not SLR(1) after all and
+/// is built as LR(1) instead.
+pub fn build_states_reporting(
+    grammar: &Grammar,
+    start: NonterminalString,
+    mut warn: impl FnMut(Message),
+) -> Lr1Result<'_> {
+    let result = if grammar.algorithm.slr {
+        match build_slr::build_slr_states(grammar, start.clone()) {
+            Ok(states) => Ok(states),
+            Err(_) => {
+                warn(error::report_slr_fallback(grammar, &start));
+                build::build_lr1_states(grammar, start.clone())
+            }
+        }
+    } else if !grammar.algorithm.lalr {
+        build::build_lr1_states(grammar, start.clone())
     } else {
-        build_lalr::build_lalr_states(grammar, start)?
+        build_lalr::build_lalr_states(grammar, start.clone())
     };
 
-    rewrite_state_indices(grammar, &mut lr1_states);
+    let mut resolutions = vec![];
+    let result = match grammar.algorithm.expected_conflicts {
+        None if grammar.algorithm.disambiguate.is_some()
+            || grammar.algorithm.backtrack.is_some() =>
+        {
+            keep_conflicts(grammar, start, result)
+        }
+        None => match Tls::session().conflict_resolver {
+            Some(ref resolver) => apply_conflict_resolver(grammar, start, result, &**resolver),
+            None => result,
+        },
+        Some(expected) => {
+            accept_expected_conflicts(grammar, start, result, expected, &mut resolutions)
+        }
+    };
+
+    let mut lr1_states = result.map_err(|mut error| {
+        error.conflicts.sort();
+        error
+    })?;
+
+    if let Err(inconsistency) = check_reduce_lookaheads(&lr1_states) {
+        return Err(core::TableConstructionError {
+            states: lr1_states,
+            conflicts: vec![],
+            inconsistency: Some(inconsistency),
+            invalid_resolution: None,
+        });
+    }
+
+    let state_rewrite = rewrite_state_indices(grammar, &mut lr1_states);
+
+    for mut resolution in resolutions {
+        resolution.state.0 = state_rewrite[resolution.state.0];
+        log!(Tls::session(), Verbose, "{}", resolution);
+    }
 
     Ok(lr1_states)
 }
 
+/// Checks that every reduction in `states` has some lookahead token
+/// to fire on. The generated parsers have no way to express one that
+/// does not, so it would silently disappear from the table.
+fn check_reduce_lookaheads<'grammar>(
+    states: &[core::Lr1State<'grammar>],
+) -> Result<(), core::InternalInconsistency<'grammar>> {
+    for state in states {
+        for &(ref lookahead, production) in &state.reductions {
+            if lookahead.is_empty() {
+                return Err(core::InternalInconsistency::EmptyReduceLookahead {
+                    state: state.index,
+                    production,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handles `#[disambiguate]` and `#[backtrack]`: the conflicts stay in
+/// the states, whose reductions may then overlap with each other and
+/// with the shifts, and the generated parser chooses among them while
+/// parsing.
+///
+/// As with `#[expect_conflicts]`, these are the canonical LR(1)
+/// states, since the lane table construction gives up at the first
+/// conflict.
+fn keep_conflicts<'grammar>(
+    grammar: &'grammar Grammar,
+    start: NonterminalString,
+    result: Lr1Result<'grammar>,
+) -> Lr1Result<'grammar> {
+    match result {
+        Err(error) if error.inconsistency.is_some() => Err(error),
+        Err(_) => match build::build_canonical_lr1_states(grammar, start) {
+            Err(error) if error.inconsistency.is_none() => Ok(error.states),
+            result => result,
+        },
+        Ok(states) => Ok(states),
+    }
+}
+
+/// A conflict on one lookahead token which `#[expect_conflicts]`
+/// resolved, as shown in the verbose log.
+#[derive(Debug)]
+struct Resolution<'grammar> {
+    state: core::StateIndex,
+    lookahead: lookahead::Token,
+    /// Either the preferred one of shifting the lookahead and
+    /// reducing, or reducing the production that comes first.
+    winner: core::Action<'grammar>,
+    loser: core::Action<'grammar>,
+}
+
+/// For example: state 4, on lookahead `"else"`: shift rather than
+/// reduce by `S = "if" E "then" S` (shift is preferred).
+impl<'grammar> std::fmt::Display for Resolution<'grammar> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "state {}, on lookahead `{}`: ",
+            self.state, self.lookahead
+        )?;
+        match (&self.winner, &self.loser) {
+            (core::Action::Shift(..), core::Action::Reduce(loser)) => write!(
+                fmt,
+                "shift rather than reduce by `{}` (shift is preferred)",
+                core::DisplayProduction(loser)
+            ),
+            (core::Action::Reduce(winner), core::Action::Shift(..)) => write!(
+                fmt,
+                "reduce by `{}` rather than shift (reduce is preferred)",
+                core::DisplayProduction(winner)
+            ),
+            (core::Action::Reduce(winner), core::Action::Reduce(loser)) => write!(
+                fmt,
+                "reduce by `{}` rather than by `{}` (comes first)",
+                core::DisplayProduction(winner),
+                core::DisplayProduction(loser)
+            ),
+            (core::Action::Shift(..), core::Action::Shift(..)) => {
+                unreachable!("shift/shift conflict")
+            }
+        }
+    }
+}
+
+/// How `#[expect_conflicts]` resolves each of `conflicts`: shift/reduce
+/// conflicts in favor of `prefer`, and reduce/reduce conflicts in favor
+/// of the production that comes first by `production_order`. There is
+/// one resolution per conflict and lookahead token.
+fn resolve_conflicts<'grammar>(
+    grammar: &'grammar Grammar,
+    conflicts: &[core::Lr1Conflict<'grammar>],
+    prefer: Prefer,
+) -> Vec<Resolution<'grammar>> {
+    let mut resolutions = vec![];
+    for conflict in conflicts {
+        let reduce = core::Action::Reduce(conflict.production);
+        let (winner, loser) = match conflict.action {
+            core::Action::Shift(..) if prefer == Prefer::Shift => (conflict.action.clone(), reduce),
+            core::Action::Shift(..) => (reduce, conflict.action.clone()),
+            core::Action::Reduce(other)
+                if production_order(grammar, other)
+                    < production_order(grammar, conflict.production) =>
+            {
+                (core::Action::Reduce(other), reduce)
+            }
+            core::Action::Reduce(other) => (reduce, core::Action::Reduce(other)),
+        };
+        for lookahead in &conflict.lookahead {
+            resolutions.push(Resolution {
+                state: conflict.state,
+                lookahead,
+                winner: winner.clone(),
+                loser: loser.clone(),
+            });
+        }
+    }
+    resolutions
+}
+
+/// The position of `production` among all the productions of the
+/// grammar, taking the nonterminals in the order of `grammar.nonterminals`
+/// and the productions of each in the order declared. Unlike spans, this
+/// tells apart the productions that inlining and macro expansion derive
+/// from the same source.
+fn production_order(grammar: &Grammar, production: &Production) -> usize {
+    grammar
+        .nonterminals
+        .values()
+        .flat_map(|data| &data.productions)
+        .position(|p| std::ptr::eq(p, production))
+        .unwrap()
+}
+
+/// Handles `#[expect_conflicts]`: the grammar must have exactly the
+/// expected number of conflicts, which are then resolved as by
+/// `resolve_conflicts`.
+///
+/// The lane table construction gives up at the first state it cannot
+/// make consistent, so conflicts are counted in the canonical LR(1)
+/// states instead, which are also the ones used to build the parser.
+fn accept_expected_conflicts<'grammar>(
+    grammar: &'grammar Grammar,
+    start: NonterminalString,
+    result: Lr1Result<'grammar>,
+    expected: usize,
+    resolutions: &mut Vec<Resolution<'grammar>>,
+) -> Lr1Result<'grammar> {
+    let error = match result {
+        Err(error) if error.inconsistency.is_some() => return Err(error),
+        Err(_) => match build::build_canonical_lr1_states(grammar, start) {
+            Ok(states) => return no_conflicts(states, expected),
+            Err(error) => error,
+        },
+        Ok(states) => return no_conflicts(states, expected),
+    };
+
+    if error.inconsistency.is_some() || conflict_count(&error.conflicts) != expected {
+        return Err(error);
+    }
+
+    let mut states = error.states;
+    *resolutions = resolve_conflicts(grammar, &error.conflicts, grammar.algorithm.prefer);
+    for resolution in resolutions.iter() {
+        let state = &mut states[resolution.state.0];
+        match resolution.loser {
+            core::Action::Shift(ref terminal, _) => {
+                state.shifts.remove(terminal);
+            }
+            core::Action::Reduce(loser) => {
+                for (lookahead, production) in &mut state.reductions {
+                    if std::ptr::eq(*production, loser) {
+                        lookahead.difference_with(&resolution.lookahead.clone().into());
+                    }
+                }
+                state
+                    .reductions
+                    .retain(|(lookahead, _)| !lookahead.is_empty());
+            }
+        }
+    }
+    Ok(states)
+}
+
+/// Hands the conflicts to the resolver set with
+/// `Configuration::set_conflict_resolver`, once for each state and
+/// lookahead token, and applies its decisions. Any conflicts that it
+/// leaves to be reported make the construction fail as usual, and so
+/// does a resolution that does not apply to its conflict.
+///
+/// As with `#[expect_conflicts]`, these are the conflicts of the
+/// canonical LR(1) states.
+fn apply_conflict_resolver<'grammar>(
+    grammar: &'grammar Grammar,
+    start: NonterminalString,
+    result: Lr1Result<'grammar>,
+    resolver: &dyn api::ConflictResolver,
+) -> Lr1Result<'grammar> {
+    let error = match result {
+        Err(error) if error.inconsistency.is_some() => return Err(error),
+        Err(_) => match build::build_canonical_lr1_states(grammar, start) {
+            Err(error) if error.inconsistency.is_none() => error,
+            result => return result,
+        },
+        Ok(states) => return Ok(states),
+    };
+
+    let mut states = error.states;
+    let mut reported = Set::new();
+    for (index, token, conflict, productions) in api_conflicts(grammar, &error.conflicts) {
+        let resolution = resolver.resolve(&conflict);
+        log!(Tls::session(), Verbose, "{}: {:?}", conflict, resolution);
+
+        // the production whose reduction is kept, if any
+        let kept = match resolution {
+            api::Resolution::Report => {
+                reported.insert((index, token));
+                continue;
+            }
+            api::Resolution::Shift if conflict.shift => None,
+            api::Resolution::Reduce(api::ProductionIndex(i)) if i < productions.len() => {
+                Some(productions[i])
+            }
+            api::Resolution::Error => None,
+            api::Resolution::Shift | api::Resolution::Reduce(_) => {
+                return Err(core::TableConstructionError {
+                    states,
+                    conflicts: error.conflicts,
+                    inconsistency: None,
+                    invalid_resolution: Some(Box::new(core::InvalidResolution {
+                        conflict,
+                        resolution,
+                        production: productions[0],
+                    })),
+                });
+            }
+        };
+
+        let state = &mut states[index.0];
+        if resolution != api::Resolution::Shift {
+            if let lookahead::Token::Terminal(ref terminal) = token {
+                state.shifts.remove(terminal);
+            }
+        }
+        for (lookahead, production) in &mut state.reductions {
+            if kept.map_or(true, |kept| !std::ptr::eq(*production, kept)) {
+                lookahead.difference_with(&token.clone().into());
+            }
+        }
+        state
+            .reductions
+            .retain(|(lookahead, _)| !lookahead.is_empty());
+    }
+
+    if reported.is_empty() {
+        return Ok(states);
+    }
+    let conflicts = error
+        .conflicts
+        .into_iter()
+        .filter_map(|mut conflict| {
+            let mut lookahead = lookahead::TokenSet::new();
+            for token in conflict.lookahead.iter() {
+                if reported.contains(&(conflict.state, token.clone())) {
+                    lookahead.insert(token);
+                }
+            }
+            conflict.lookahead = lookahead;
+            Some(conflict).filter(|conflict| !conflict.lookahead.is_empty())
+        })
+        .collect();
+    Err(core::TableConstructionError {
+        states,
+        conflicts,
+        inconsistency: None,
+        invalid_resolution: None,
+    })
+}
+
+/// The conflicts as a `ConflictResolver` sees them, one for each state
+/// and lookahead token, along with the productions that each one
+/// names, in `production_order`.
+fn api_conflicts<'grammar>(
+    grammar: &'grammar Grammar,
+    conflicts: &[core::Lr1Conflict<'grammar>],
+) -> Vec<(
+    core::StateIndex,
+    lookahead::Token,
+    api::Conflict,
+    Vec<&'grammar Production>,
+)> {
+    // whether the token can be shifted, and the productions that can
+    // be reduced, for each state and lookahead token
+    let mut choices: Map<(core::StateIndex, lookahead::Token), (bool, Vec<&Production>)> = map();
+    for conflict in conflicts {
+        for token in conflict.lookahead.iter() {
+            let (shift, productions) = choices.entry((conflict.state, token)).or_default();
+            let mut add = |production: &'grammar Production| {
+                if !productions.iter().any(|p| std::ptr::eq(*p, production)) {
+                    productions.push(production);
+                }
+            };
+            add(conflict.production);
+            match conflict.action {
+                core::Action::Shift(..) => *shift = true,
+                core::Action::Reduce(other) => add(other),
+            }
+        }
+    }
+
+    choices
+        .into_iter()
+        .map(|((index, token), (shift, mut productions))| {
+            productions.sort_by_key(|production| production_order(grammar, production));
+            let conflict = api::Conflict {
+                state: index.0,
+                lookahead: token.to_string(),
+                shift,
+                productions: productions
+                    .iter()
+                    .map(|production| core::DisplayProduction(production).to_string())
+                    .collect(),
+            };
+            (index, token, conflict, productions)
+        })
+        .collect()
+}
+
+/// Checks whether `start` is LR(1), for `Configuration::check_lr1`,
+/// returning the conflicts of its canonical LR(1) automaton, if any.
+pub fn check_lr1(
+    grammar: &Grammar,
+    start: NonterminalString,
+) -> Result<Vec<api::Conflict>, String> {
+    match build::check_lr1(grammar, start) {
+        Ok(conflicts) => Ok(api_conflicts(grammar, &conflicts)
+            .into_iter()
+            .map(|(_, _, conflict, _)| conflict)
+            .collect()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// The outcome of `#[expect_conflicts]` for a grammar without
+/// conflicts.
+fn no_conflicts(states: Vec<core::Lr1State<'_>>, expected: usize) -> Lr1Result<'_> {
+    if expected == 0 {
+        Ok(states)
+    } else {
+        Err(core::TableConstructionError {
+            states,
+            conflicts: vec![],
+            inconsistency: None,
+            invalid_resolution: None,
+        })
+    }
+}
+
+/// The number of conflicts in a grammar, as checked by
+/// `#[expect_conflicts]`: each state and lookahead token with more
+/// than one possible action counts once.
+pub fn conflict_count(conflicts: &[core::Lr1Conflict<'_>]) -> usize {
+    conflicts
+        .iter()
+        .flat_map(|conflict| {
+            conflict
+                .lookahead
+                .iter()
+                .map(move |token| (conflict.state, token))
+        })
+        .collect::<Set<_>>()
+        .len()
+}
+
+/// The productions that the parser can never reduce, although their
+/// nonterminal is used, in grammar order. This happens when a
+/// production loses every conflict resolved by `#[expect_conflicts]`,
+/// and to the productions that can only follow it.
+///
+/// A state only counts if the parser can reach it: by shifting, or by
+/// a goto on a nonterminal that some reachable state reduces. Since
+/// each public nonterminal has its own states, `states` should hold
+/// the states of all of them.
+pub fn unreduced_productions<'grammar, 's>(
+    grammar: &'grammar Grammar,
+    states: impl IntoIterator<Item = &'s [core::Lr1State<'grammar>]>,
+) -> Vec<&'grammar Production>
+where
+    'grammar: 's,
+{
+    let mut reduced = Set::new();
+    let mut used = Set::new();
+    for states in states {
+        let mut reduced_nonterminals = Set::new();
+        let mut reachable = core::StateMap::new(states.len(), false);
+        reachable.set(core::StateIndex(0), true);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in states {
+                if !reachable[state.index] {
+                    continue;
+                }
+                for &(_, production) in &state.reductions {
+                    reduced.insert(production);
+                    changed |= reduced_nonterminals.insert(&production.nonterminal);
+                }
+                let gotos = state
+                    .gotos
+                    .iter()
+                    .filter(|&(nonterminal, _)| reduced_nonterminals.contains(nonterminal))
+                    .map(|(_, next)| next);
+                for &next in state.shifts.values().chain(gotos) {
+                    changed |= !reachable.set(next, true);
+                }
+                used.extend(state.gotos.keys());
+            }
+        }
+    }
+    grammar
+        .nonterminals
+        .iter()
+        .filter(|&(nonterminal, _)| used.contains(nonterminal))
+        .flat_map(|(_, data)| &data.productions)
+        .filter(|production| !reduced.contains(production))
+        .collect()
+}
+
+/// A production `X = a... Y ...` where `a...` can be empty and `Y` can
+/// start with `X` again. The parser would have to decide how many times
+/// to reduce the empty `a...` before seeing how deeply `X` is nested,
+/// so such a grammar is never LR(k).
+pub struct HiddenLeftRecursion<'grammar> {
+    pub production: &'grammar Production,
+    /// The number of nullable symbols in front of `Y`.
+    pub prefix: usize,
+    /// The nonterminals from `Y` back to `X`, each of which can start
+    /// with the next.
+    pub chain: Vec<NonterminalString>,
+}
+
+/// Finds the productions with hidden left recursion, in grammar order.
+/// Each production is reported at most once, with the shortest chain
+/// for its first recursive symbol.
+pub fn hidden_left_recursion(grammar: &Grammar) -> Vec<HiddenLeftRecursion<'_>> {
+    let nullable = first::nullable_set(grammar);
+
+    let mut result = vec![];
+    for production in grammar
+        .nonterminals
+        .values()
+        .flat_map(|data| &data.productions)
+    {
+        let target = &production.nonterminal;
+        for (prefix, start) in left_corners(&nullable, production) {
+            if prefix == 0 {
+                continue;
+            }
+
+            // breadth-first search from `start` back to `target`
+            let mut parents: Map<&NonterminalString, &NonterminalString> = map();
+            let mut queue = VecDeque::from(vec![start]);
+            let mut found = start == target;
+            while !found {
+                let Some(nt) = queue.pop_front() else { break };
+                for production in grammar.productions_for(nt) {
+                    for (_, next) in left_corners(&nullable, production) {
+                        if next != start && !parents.contains_key(next) {
+                            parents.insert(next, nt);
+                            queue.push_back(next);
+                            found |= next == target;
+                        }
+                    }
+                }
+            }
+            if !found {
+                continue;
+            }
+
+            let mut chain = vec![target.clone()];
+            let mut nt = target;
+            while nt != start {
+                nt = parents[nt];
+                chain.push(nt.clone());
+            }
+            chain.reverse();
+            result.push(HiddenLeftRecursion {
+                production,
+                prefix,
+                chain,
+            });
+            break;
+        }
+    }
+    result
+}
+
+/// The nonterminals that `production` can start with, along with the
+/// number of symbols in front of each.
+fn left_corners<'grammar>(
+    nullable: &Set<NonterminalString>,
+    production: &'grammar Production,
+) -> Vec<(usize, &'grammar NonterminalString)> {
+    let prefix_len = production
+        .symbols
+        .iter()
+        .position(|symbol| match symbol {
+            Symbol::Terminal(_) => true,
+            Symbol::Nonterminal(nt) => !nullable.contains(nt),
+        })
+        .map_or(production.symbols.len(), |i| i + 1);
+    production.symbols[..prefix_len]
+        .iter()
+        .enumerate()
+        .filter_map(|(i, symbol)| match symbol {
+            Symbol::Nonterminal(nt) => Some((i, nt)),
+            Symbol::Terminal(_) => None,
+        })
+        .collect()
+}
+
+/// The terminals that can begin `nt`, in the order in which the
+/// grammar declares them. If `nt` can derive the empty string, that is
+/// not reflected in the result.
+pub fn first_terminals(grammar: &Grammar, nt: &NonterminalString) -> Vec<TerminalString> {
+    let first_set = first::FirstSets::new(grammar).first0(&[Symbol::Nonterminal(nt.clone())]);
+    grammar
+        .terminals
+        .all
+        .iter()
+        .filter(|&terminal| first_set.contains(&lookahead::Token::Terminal(terminal.clone())))
+        .cloned()
+        .collect()
+}
+
 pub fn generate_report<'grammar, W: Write + 'grammar>(
     out: &'grammar mut W,
     lr1result: &Lr1Result<'grammar>,
@@ -44,8 +654,8 @@ pub fn generate_report<'grammar, W: Write + 'grammar>(
 }
 
 /// By packing all states which start a reduction we can generate a smaller goto table as any
-/// states not starting a reduction will not need a row
-fn rewrite_state_indices(grammar: &Grammar, states: &mut [core::Lr1State]) {
+/// states not starting a reduction will not need a row. Returns the new index of each state.
+fn rewrite_state_indices(grammar: &Grammar, states: &mut [core::Lr1State]) -> Vec<usize> {
     let mut start_states = vec![false; states.len()];
     for (index, state) in states.iter_mut().enumerate() {
         debug_assert!(state.index.0 == index);
@@ -75,4 +685,6 @@ fn rewrite_state_indices(grammar: &Grammar, states: &mut [core::Lr1State]) {
             shift.0 = state_rewrite[shift.0];
         }
     }
+
+    state_rewrite
 }
@@ -1,6 +1,7 @@
 use crate::collections::*;
 use crate::grammar::repr::*;
 use crate::lr1::core::*;
+use crate::lr1::state_graph::StateGraph;
 use std::cmp::max;
 use std::io::{self, Write};
 
@@ -55,13 +56,16 @@ where
                     "Constructed {} states",
                     table_construction_error.states.len()
                 )?;
+                if let Some(ref inconsistency) = table_construction_error.inconsistency {
+                    writeln!(self.out, "{}", inconsistency)?;
+                }
                 writeln!(
                     self.out,
                     "Has {} conflicts",
                     table_construction_error.conflicts.len()
                 )?;
-                let (sr, rr, conflict_map) =
-                    self.process_conflicts(&table_construction_error.conflicts);
+                let (sr, rr) = self.count_conflicts(&table_construction_error.conflicts);
+                let conflict_map = table_construction_error.conflicts_by_state();
                 if (sr > 0) {
                     writeln!(self.out, "{}shift/reduce:  {}", INDENT_STRING, sr)?;
                 }
@@ -79,27 +83,19 @@ where
         Ok(())
     }
 
-    fn process_conflicts<'grammar, L>(
-        &mut self,
-        conflicts: &'report [Conflict<'grammar, L>],
-    ) -> (usize, usize, ConflictStateMap<'report, 'grammar, L>)
+    fn count_conflicts<L>(&mut self, conflicts: &[Conflict<'_, L>]) -> (usize, usize)
     where
         L: Lookahead,
     {
         let mut sr: usize = 0;
         let mut rr: usize = 0;
-        let mut conflict_map = Map::new();
         for conflict in conflicts.iter() {
             match conflict.action {
                 Action::Shift(..) => sr += 1,
                 Action::Reduce(_) => rr += 1,
             }
-            conflict_map
-                .entry(conflict.state)
-                .or_insert_with(Vec::new)
-                .push(conflict);
         }
-        (sr, rr, conflict_map)
+        (sr, rr)
     }
 
     fn report_states<'grammar, L>(
@@ -111,9 +107,18 @@ where
         L: Lookahead + LookaheadPrinter<W>,
     {
         self.write_section_header("State Table")?;
+        let graph = if conflict_map.is_empty() {
+            None
+        } else {
+            Some(StateGraph::new(states))
+        };
         for state in states {
             writeln!(self.out)?;
-            self.report_state(state, conflict_map.get(&state.index))?;
+            let conflicts_opt = conflict_map.get(&state.index);
+            let prefix = conflicts_opt
+                .and(graph.as_ref())
+                .and_then(|graph| graph.shortest_path(state.index));
+            self.report_state(state, conflicts_opt, prefix.as_deref())?;
         }
         Ok(())
     }
@@ -122,6 +127,7 @@ where
         &mut self,
         state: &State<'grammar, L>,
         conflicts_opt: Option<&Vec<&'report Conflict<'grammar, L>>>,
+        prefix: Option<&[Symbol]>,
     ) -> io::Result<()>
     where
         L: Lookahead + LookaheadPrinter<W>,
@@ -145,6 +151,15 @@ where
             self.write_gotos(&state.gotos, max_width)?;
         }
 
+        if let Some(prefix) = prefix {
+            writeln!(self.out)?;
+            write!(self.out, "{}reached by input:", INDENT_STRING)?;
+            for symbol in prefix {
+                write!(self.out, " {}", symbol)?;
+            }
+            writeln!(self.out)?;
+        }
+
         if let Some(conflicts) = conflicts_opt {
             for conflict in conflicts.iter() {
                 self.write_conflict(conflict)?;
@@ -365,7 +380,7 @@ where
     }
 
     fn has_anything_to_print(&self) -> bool {
-        self.len() > 0
+        !self.is_empty()
     }
 }
 
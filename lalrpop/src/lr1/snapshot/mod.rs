@@ -0,0 +1,75 @@
+//! Renders the states of a parser as plain text, to be checked in next
+//! to a grammar so that changes to the parse table show up in review.
+//! The format is:
+//!
+//! ```text
+//! terminal "+" => Tok::Plus
+//! ...
+//!
+//! state 0
+//!   E = • E "+" T ["+", Eof]
+//!   ...
+//!   shift "(" => 3
+//!   goto E => 1
+//!   reduce E = T • ["+", Eof]
+//! ```
+//!
+//! Only the states and the grammar's symbols show up, not the spans or
+//! the action code, so reformatting a grammar or editing its actions
+//! leaves the snapshot unchanged. Each section is sorted, so its order
+//! does not depend on how the grammar declares its productions either.
+
+use crate::grammar::repr::*;
+use crate::lr1::core::*;
+use crate::lr1::lookahead::Lookahead;
+use std::fmt::Write;
+
+#[cfg(test)]
+mod test;
+
+pub fn snapshot_states<L: Lookahead>(grammar: &Grammar, states: &[State<'_, L>]) -> String {
+    let mut out = String::new();
+    for terminal in grammar.terminals() {
+        match grammar.conversions.get(&terminal) {
+            Some(pattern) => writeln!(out, "terminal {} => {}", terminal, pattern),
+            None => writeln!(out, "terminal {}", terminal),
+        }
+        .unwrap();
+    }
+
+    for state in states {
+        writeln!(out, "\nstate {}", state.index).unwrap();
+
+        let mut items: Vec<_> = state.items.vec.iter().map(Item::display).collect();
+        items.sort();
+        for item in items {
+            writeln!(out, "  {}", item).unwrap();
+        }
+
+        // `shifts` and `gotos` are maps, already ordered by symbol
+        for (terminal, next_state) in &state.shifts {
+            writeln!(out, "  shift {} => {}", terminal, next_state).unwrap();
+        }
+        for (nonterminal, next_state) in &state.gotos {
+            writeln!(out, "  goto {} => {}", nonterminal, next_state).unwrap();
+        }
+
+        let mut reductions: Vec<_> = state
+            .reductions
+            .iter()
+            .map(|(lookahead, production)| {
+                Item {
+                    production,
+                    index: production.symbols.len(),
+                    lookahead: lookahead.clone(),
+                }
+                .display()
+            })
+            .collect();
+        reductions.sort();
+        for reduction in reductions {
+            writeln!(out, "  reduce {}", reduction).unwrap();
+        }
+    }
+    out
+}
@@ -0,0 +1,132 @@
+use crate::grammar::repr::*;
+use crate::lr1::build::{build_lr0_states, build_lr1_states};
+use crate::lr1::tls::Lr1Tls;
+use crate::test_util::{compare, normalized_grammar};
+use crate::tls::Tls;
+use string_cache::DefaultAtom as Atom;
+
+use super::snapshot_states;
+
+fn snapshot(grammar: &Grammar) -> String {
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let states = build_lr1_states(grammar, NonterminalString(Atom::from("__S"))).unwrap();
+    snapshot_states(grammar, &states)
+}
+
+#[test]
+fn states() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X(..), "," => Tok::Comma } }
+pub S: () = { S "," "x" => (), "x" => () };
+"#,
+    );
+    compare(
+        snapshot(&grammar),
+        r#"terminal "," => Tok::Comma
+terminal "x" => Tok::X(..)
+
+state 0
+  S = • "x" [",", "x", Eof]
+  S = • S "," "x" [",", "x", Eof]
+  __S = • S [",", "x", Eof]
+  shift "x" => 2
+  goto S => 1
+
+state 1
+  S = S • "," "x" [",", "x", Eof]
+  __S = S • [",", "x", Eof]
+  shift "," => 3
+  reduce __S = S • [Eof]
+
+state 2
+  S = "x" • [",", "x", Eof]
+  reduce S = "x" • [",", Eof]
+
+state 3
+  S = S "," • "x" [",", "x", Eof]
+  shift "x" => 4
+
+state 4
+  S = S "," "x" • [",", "x", Eof]
+  reduce S = S "," "x" • [",", Eof]
+"#,
+    );
+}
+
+/// Reformatting the grammar and changing its types and actions leave
+/// the snapshot as it was.
+#[test]
+fn unchanged_by_refactoring() {
+    let _tls = Tls::test();
+    let before = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X(..), "," => Tok::Comma } }
+pub S: () = { S "," "x" => (), "x" => () };
+"#,
+    );
+    let after = normalized_grammar(
+        r#"
+grammar;
+extern {
+    enum Tok {
+        "," => Tok::Comma,
+        "x" => Tok::X(..),
+    }
+}
+
+pub S: usize = {
+    <s:S> "," "x" => s + 1,
+    "x" => 1,
+};
+"#,
+    );
+    assert_eq!(snapshot(&before), snapshot(&after));
+
+    // whereas a change to the language does show up
+    let changed = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X(..), "," => Tok::Comma } }
+pub S: () = { S "," "x" => (), "x" => (), => () };
+"#,
+    );
+    assert_ne!(snapshot(&before), snapshot(&changed));
+}
+
+/// LR(0) items and reductions have no lookahead.
+#[test]
+fn lr0_states() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+grammar;
+extern { enum Tok { "x" => Tok::X(..) } }
+pub S: () = "x" => ();
+"#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let states = build_lr0_states(&grammar, NonterminalString(Atom::from("__S"))).unwrap();
+    compare(
+        snapshot_states(&grammar, &states),
+        r#"terminal "x" => Tok::X(..)
+
+state 0
+  S = • "x"
+  __S = • S
+  shift "x" => 2
+  goto S => 1
+
+state 1
+  __S = S •
+  reduce __S = S •
+
+state 2
+  S = "x" •
+  reduce S = "x" •
+"#,
+    );
+}
@@ -4,6 +4,7 @@ use crate::lr1::lookahead::Lookahead;
 use petgraph::graph::NodeIndex;
 use petgraph::prelude::*;
 use petgraph::{EdgeDirection, Graph};
+use std::collections::VecDeque;
 
 // Each state `s` corresponds to the node in the graph with index
 // `s`. The edges are the shift transitions.
@@ -76,6 +77,41 @@ impl StateGraph {
         result
     }
 
+    /// Finds the shortest sequence of symbols that takes the parser
+    /// from state 0 to `target_state_index`, i.e. a minimal viable
+    /// prefix for that state. Returns `None` if the state is
+    /// unreachable.
+    pub fn shortest_path(&self, target_state_index: StateIndex) -> Option<Vec<Symbol>> {
+        let start = NodeIndex::new(0);
+        let target = NodeIndex::new(target_state_index.0);
+        let mut parents: Vec<Option<EdgeIndex>> = vec![None; self.graph.node_count()];
+        let mut visited = vec![false; self.graph.node_count()];
+        let mut queue = VecDeque::new();
+        visited[start.index()] = true;
+        queue.push_back(start);
+        while let Some(node) = queue.pop_front() {
+            if node == target {
+                let mut symbols = vec![];
+                let mut current = node;
+                while let Some(edge) = parents[current.index()] {
+                    symbols.push(self.graph[edge].clone());
+                    current = self.graph.edge_endpoints(edge).unwrap().0;
+                }
+                symbols.reverse();
+                return Some(symbols);
+            }
+            for edge in self.graph.edges_directed(node, EdgeDirection::Outgoing) {
+                let next = edge.target();
+                if !visited[next.index()] {
+                    visited[next.index()] = true;
+                    parents[next.index()] = Some(edge.id());
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
     pub fn successors(&self, state_index: StateIndex) -> impl Iterator<Item = StateIndex> + '_ {
         self.graph
             .edges_directed(NodeIndex::new(state_index.0), EdgeDirection::Outgoing)
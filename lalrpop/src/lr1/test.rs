@@ -0,0 +1,649 @@
+use super::interpret::interpret;
+use super::{
+    build_states, build_states_reporting, check_reduce_lookaheads, conflict_count,
+    hidden_left_recursion, report_error, resolve_conflicts, unreduced_productions,
+};
+use crate::grammar::repr::*;
+use crate::lr1::core::{Action, InternalInconsistency};
+use crate::lr1::lookahead::{Token, TokenSet};
+use crate::lr1::tls::Lr1Tls;
+use crate::test_util::normalized_grammar;
+use crate::tls::Tls;
+use string_cache::DefaultAtom as Atom;
+
+fn nt(t: &str) -> NonterminalString {
+    NonterminalString(Atom::from(t))
+}
+
+macro_rules! tokens {
+    ($($x:expr),*) => {
+        vec![$(TerminalString::quoted(Atom::from($x))),*]
+    }
+}
+
+fn dangling_else(annotation: &str, extra: &str) -> Grammar {
+    normalized_grammar(&format!(
+        r#"
+        {}
+        grammar;
+        extern {{ enum Tok {{ "if" => .., "then" => .., "else" => .., "C" => .., "X" => .., "+" => .. }} }}
+        Top: () = S => ();
+        S: () = {{
+            "if" "C" "then" S => (),
+            "if" "C" "then" S "else" S => (),
+            "X" => (),
+            {}
+        }};
+   "#,
+        annotation, extra
+    ))
+}
+
+#[test]
+fn expected_conflicts_shift() {
+    let _tls = Tls::test();
+    let grammar = dangling_else(r#"#[expect_conflicts(count = "1")]"#, "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let states = build_states(&grammar, nt("Top")).unwrap();
+
+    // the `else` goes with the innermost `if`
+    let tree = interpret(
+        &states,
+        tokens!["if", "C", "then", "if", "C", "then", "X", "else", "X"],
+    )
+    .unwrap();
+    assert_eq!(
+        &format!("{:?}", tree)[..],
+        r#"[Top: [S: "if", "C", "then", [S: "if", "C", "then", [S: "X"], "else", [S: "X"]]]]"#
+    );
+}
+
+#[test]
+fn expected_conflicts_prefer_reduce() {
+    let _tls = Tls::test();
+    let grammar = dangling_else(
+        r#"#[expect_conflicts(count = "1")] #[conflict_preference(prefer = "reduce")]"#,
+        "",
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let states = build_states(&grammar, nt("Top")).unwrap();
+
+    // the `else` goes with the outermost `if`
+    let tree = interpret(
+        &states,
+        tokens!["if", "C", "then", "if", "C", "then", "X", "else", "X"],
+    )
+    .unwrap();
+    assert_eq!(
+        &format!("{:?}", tree)[..],
+        r#"[Top: [S: "if", "C", "then", [S: "if", "C", "then", [S: "X"]], "else", [S: "X"]]]"#
+    );
+
+    drop(_lr1_tls);
+
+    let grammar = dangling_else("", "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    for resolution in resolve_conflicts(&grammar, &error.conflicts, Prefer::Reduce) {
+        assert_eq!(
+            resolution.to_string(),
+            format!(
+                r#"state {}, on lookahead `"else"`: reduce by `S = "if" "C" "then" S` rather than shift (reduce is preferred)"#,
+                resolution.state
+            )
+        );
+    }
+}
+
+#[test]
+fn conflict_resolver() {
+    use crate::api::{Conflict, ConflictResolver, ProductionIndex, Resolution};
+    use crate::file_text::FileText;
+    use crate::session::Session;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    /// Resolves the conflicts with a table of precedences, where `<` is
+    /// non-associative and the others associate to the left. `-` is
+    /// left to be reported.
+    struct Precedence;
+
+    impl ConflictResolver for Precedence {
+        fn resolve(&self, conflict: &Conflict) -> Resolution {
+            let level = |op: &str| ["<", "+", "*"].iter().position(|o| *o == op);
+            let reduce = conflict.productions[0].split('"').nth(1).unwrap();
+            match (level(reduce), level(conflict.lookahead.trim_matches('"'))) {
+                (Some(reduce), Some(shift)) if reduce < shift => Resolution::Shift,
+                (Some(0), Some(0)) => Resolution::Error,
+                (Some(_), Some(_)) => Resolution::Reduce(ProductionIndex(0)),
+                _ => Resolution::Report,
+            }
+        }
+    }
+
+    let mut session = Session::test();
+    session.conflict_resolver = Some(Arc::new(Precedence));
+    let _tls = Tls::install(Rc::new(session), Rc::new(FileText::test()));
+    let grammar = |extra: &str| {
+        normalized_grammar(&format!(
+            r#"
+            grammar;
+            extern {{ enum Tok {{ "N" => .., "<" => .., "+" => .., "*" => .., "-" => .. }} }}
+            Top: () = Expr => ();
+            Expr: () = {{
+                Expr "<" Expr => (),
+                Expr "+" Expr => (),
+                Expr "*" Expr => (),
+                "N" => (),
+                {}
+            }};
+       "#,
+            extra
+        ))
+    };
+
+    let grammar0 = grammar("");
+    let _lr1_tls = Lr1Tls::install(grammar0.terminals.clone());
+    let states = build_states(&grammar0, nt("Top")).unwrap();
+    let tree = interpret(&states, tokens!["N", "+", "N", "*", "N", "+", "N"]).unwrap();
+    assert_eq!(
+        &format!("{:?}", tree)[..],
+        r#"[Top: [Expr: [Expr: [Expr: "N"], "+", [Expr: [Expr: "N"], "*", [Expr: "N"]]], "+", [Expr: "N"]]]"#
+    );
+    assert!(interpret(&states, tokens!["N", "<", "N", "<", "N"]).is_err());
+    drop(_lr1_tls);
+
+    // only the conflicts involving `-` are reported
+    let grammar1 = grammar(r#"Expr "-" Expr => (),"#);
+    let _lr1_tls = Lr1Tls::install(grammar1.terminals.clone());
+    let error = build_states(&grammar1, nt("Top")).unwrap_err();
+    assert!(!error.conflicts.is_empty());
+    let minus = TerminalString::quoted(Atom::from("-"));
+    let uses_minus = |production: &Production| {
+        production
+            .symbols
+            .contains(&Symbol::Terminal(minus.clone()))
+    };
+    for conflict in &error.conflicts {
+        let other = match conflict.action {
+            Action::Shift(ref terminal, _) => *terminal == minus,
+            Action::Reduce(production) => uses_minus(production),
+        };
+        for token in conflict.lookahead.iter() {
+            assert!(
+                uses_minus(conflict.production) || other || token == Token::Terminal(minus.clone())
+            );
+        }
+    }
+}
+
+/// Builds a grammar with a reduce/reduce conflict using a resolver that
+/// gives `resolution` for every conflict, which does not apply to it.
+fn invalid_resolution(resolution: crate::api::Resolution) {
+    use crate::api::{Conflict, ConflictResolver, Resolution};
+    use crate::file_text::FileText;
+    use crate::session::Session;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    struct Always(Resolution);
+
+    impl ConflictResolver for Always {
+        fn resolve(&self, _: &Conflict) -> Resolution {
+            self.0
+        }
+    }
+
+    let mut session = Session::test();
+    session.conflict_resolver = Some(Arc::new(Always(resolution)));
+    let _tls = Tls::install(Rc::new(session), Rc::new(FileText::test()));
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "a" => .., "x" => .. } }
+        Top: () = { A "x" => (), B "x" => () };
+        A: () = "a" => ();
+        B: () = "a" => ();
+    "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    let invalid = error.invalid_resolution.as_ref().unwrap();
+    assert_eq!(invalid.resolution, resolution);
+    assert_eq!(invalid.conflict.lookahead, r#""x""#);
+    assert!(!invalid.conflict.shift);
+    assert_eq!(
+        invalid.conflict.productions,
+        vec![r#"A = "a""#, r#"B = "a""#]
+    );
+
+    // it is reported as an error in the grammar, rather than a panic
+    let mut messages = 0;
+    report_error(&grammar, &error, |_| -> Result<(), ()> {
+        messages += 1;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(messages, 1);
+}
+
+#[test]
+fn conflict_resolver_shift_without_shift() {
+    invalid_resolution(crate::api::Resolution::Shift);
+}
+
+#[test]
+fn conflict_resolver_reduce_out_of_range() {
+    invalid_resolution(crate::api::Resolution::Reduce(crate::api::ProductionIndex(
+        2,
+    )));
+}
+
+#[test]
+fn slr_fallback_warning() {
+    let _tls = Tls::test();
+    let grammar = |extra: &str| {
+        normalized_grammar(&format!(
+            r#"
+            #[SLR]
+            grammar;
+            extern {{ enum Tok {{ "=" => .., "*" => .., "id" => .. }} }}
+            pub S: () = {{
+                {}
+                R => (),
+            }};
+            L: () = {{
+                "*" R => (),
+                "id" => (),
+            }};
+            R: () = L => ();
+       "#,
+            extra
+        ))
+    };
+    let warnings = |grammar: &Grammar| {
+        let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+        let (user_nt, start_nt) = grammar.start_nonterminals.iter().next().unwrap();
+        assert_eq!(*user_nt, nt("S"));
+        let mut warnings = 0;
+        build_states_reporting(grammar, start_nt.clone(), |_| warnings += 1).unwrap();
+        warnings
+    };
+
+    // SLR(1), so it is built as asked
+    assert_eq!(warnings(&grammar("")), 0);
+
+    // LALR(1) but not SLR(1), as `"="` is in FOLLOW(R)
+    assert_eq!(warnings(&grammar(r#"L "=" R => (),"#)), 1);
+}
+
+#[test]
+fn accept_state() {
+    let _tls = Tls::test();
+    let grammar = dangling_else(r#"#[expect_conflicts(count = "1")]"#, "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    let states = build_states(&grammar, nt("Top")).unwrap();
+
+    // only the state reached on `S` from the start state reduces `Top`
+    let accepting: Vec<_> = states
+        .iter()
+        .filter(|state| state.is_accept(&nt("Top")))
+        .map(|state| state.index)
+        .collect();
+    assert_eq!(accepting, vec![states[0].gotos[&nt("S")]]);
+    assert!(states
+        .iter()
+        .all(|state| !state.gotos.contains_key(&nt("Top"))));
+}
+
+#[test]
+fn empty_reduce_lookahead() {
+    let _tls = Tls::test();
+    let grammar = dangling_else(r#"#[expect_conflicts(count = "1")]"#, "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let mut states = build_states(&grammar, nt("Top")).unwrap();
+    assert_eq!(check_reduce_lookaheads(&states), Ok(()));
+
+    // empty the lookahead of the reduction of `S = "X"`
+    let is_x = |production: &Production| {
+        production.nonterminal == nt("S") && production.symbols.len() == 1
+    };
+    let state = states
+        .iter_mut()
+        .find(|state| state.reductions.iter().any(|&(_, p)| is_x(p)))
+        .unwrap();
+    let index = state.index;
+    for (lookahead, production) in &mut state.reductions {
+        if is_x(production) {
+            *lookahead = TokenSet::new();
+        }
+    }
+
+    let inconsistency = check_reduce_lookaheads(&states).unwrap_err();
+    assert_eq!(
+        inconsistency.to_string(),
+        format!(r#"State {} reduces `S = "X"` on an empty lookahead"#, index)
+    );
+    match inconsistency {
+        InternalInconsistency::EmptyReduceLookahead { state, .. } => assert_eq!(state, index),
+        InternalInconsistency::DuplicateTransition { .. } => panic!("wrong inconsistency"),
+    }
+}
+
+#[test]
+fn conflict_witness() {
+    let _tls = Tls::test();
+    let grammar = dangling_else("", "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    let conflict = &error.conflicts[0];
+    assert_eq!(
+        conflict.witness(),
+        Token::Terminal(TerminalString::quoted(Atom::from("else")))
+    );
+    let next_state = match conflict.action {
+        Action::Shift(_, next_state) => next_state,
+        Action::Reduce(_) => panic!("expected a shift/reduce conflict"),
+    };
+    assert_eq!(
+        conflict.to_string(),
+        format!(
+            r#"on lookahead `"else"`, shift to state {} or reduce by `S = "if" "C" "then" S`"#,
+            next_state
+        )
+    );
+}
+
+#[test]
+fn expected_conflicts_resolutions() {
+    let _tls = Tls::test();
+
+    let grammar = dangling_else("", "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    let resolutions = resolve_conflicts(&grammar, &error.conflicts, Prefer::Shift);
+    assert_eq!(resolutions.len(), conflict_count(&error.conflicts));
+    for resolution in resolutions {
+        assert_eq!(
+            resolution.to_string(),
+            format!(
+                r#"state {}, on lookahead `"else"`: shift rather than reduce by `S = "if" "C" "then" S` (shift is preferred)"#,
+                resolution.state
+            )
+        );
+    }
+    drop(_lr1_tls);
+
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "X" => .. } }
+        Top: () = S => ();
+        S: () = { A => (), B => () };
+        A: () = "X" => ();
+        B: () = "X" => ();
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    let resolutions: Vec<_> = resolve_conflicts(&grammar, &error.conflicts, Prefer::Shift)
+        .iter()
+        .map(|resolution| resolution.to_string())
+        .collect();
+    assert_eq!(
+        resolutions,
+        vec![
+            format!(
+                r#"state {}, on lookahead `"X"`: reduce by `A = "X"` rather than by `B = "X"` (comes first)"#,
+                error.conflicts[0].state
+            ),
+            format!(
+                r#"state {}, on lookahead `Eof`: reduce by `A = "X"` rather than by `B = "X"` (comes first)"#,
+                error.conflicts[0].state
+            ),
+        ]
+    );
+}
+
+/// The two instances of `Tag` share the span of its one production, so
+/// only their order in the grammar tells which one is reduced.
+#[test]
+fn reduce_reduce_same_span() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "X" => .., "A" => .., "B" => .. } }
+        Top: () = S => ();
+        S: () = { Tag<"B"> => (), Tag<"A"> => () };
+        Tag<T>: () = "X" => ();
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    for resolution in resolve_conflicts(&grammar, &error.conflicts, Prefer::Shift) {
+        let resolution = resolution.to_string();
+        assert!(
+            resolution.ends_with(
+                r#": reduce by `Tag<"A"> = "X"` rather than by `Tag<"B"> = "X"` (comes first)"#
+            ),
+            "{}",
+            resolution
+        );
+    }
+}
+
+#[test]
+fn expected_conflicts_mismatch() {
+    let _tls = Tls::test();
+
+    // without the annotation, the conflict is an error
+    let grammar = dangling_else("", "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    assert!(build_states(&grammar, nt("Top")).is_err());
+    drop(_lr1_tls);
+
+    // too many expected conflicts
+    let grammar = dangling_else(r#"#[expect_conflicts(count = "2")]"#, "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    assert_eq!(conflict_count(&error.conflicts), 1);
+    drop(_lr1_tls);
+
+    // a second conflict, this time on `+`
+    let grammar = dangling_else(r#"#[expect_conflicts(count = "1")]"#, r#"S "+" S => (),"#);
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    assert!(conflict_count(&error.conflicts) > 1);
+}
+
+#[test]
+fn expected_conflicts_none() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+        #[expect_conflicts(count = "1")]
+        grammar;
+        extern { enum Tok { "X" => .. } }
+        Top: () = S => ();
+        S: () = "X" => ();
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let error = build_states(&grammar, nt("Top")).unwrap_err();
+    assert_eq!(conflict_count(&error.conflicts), 0);
+}
+
+#[test]
+fn unreduced_after_expected_conflicts() {
+    let _tls = Tls::test();
+
+    // the dangling `else` conflict is resolved, but both productions
+    // are still reduced somewhere
+    let grammar = dangling_else(r#"#[expect_conflicts(count = "1")]"#, "");
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let states = build_states(&grammar, nt("Top")).unwrap();
+    assert!(unreduced_productions(&grammar, [&states[..]]).is_empty());
+    drop(_lr1_tls);
+
+    // `B` always loses to `A`, which is declared first
+    let grammar = normalized_grammar(
+        r#"
+        #[expect_conflicts(count = "1")]
+        grammar;
+        extern { enum Tok { "X" => .. } }
+        Top: () = S => ();
+        S: () = { A => (), B => () };
+        A: () = "X" => ();
+        B: () = "X" => ();
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let states = build_states(&grammar, nt("Top")).unwrap();
+    let unreduced: Vec<_> = unreduced_productions(&grammar, [&states[..]])
+        .into_iter()
+        .map(|production| format!("{:?}", production))
+        .collect();
+    assert_eq!(
+        unreduced,
+        vec![r#"B = "X" => ActionFn(4);"#, "S = B => ActionFn(2);"]
+    );
+}
+
+#[test]
+fn unreduced_after_precedence() {
+    let _tls = Tls::test();
+
+    // precedence annotations are expanded into one nonterminal per
+    // level before the states are built, so no production is dropped
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "N" => .., "-" => .., "*" => .., "^" => .., "<" => .. } }
+        Top: () = Expr => ();
+        Expr: () = {
+            #[precedence(level="0")]
+            "N" => (),
+            #[precedence(level="1")]
+            "-" Expr => (),
+            #[precedence(level="2")] #[assoc(side="right")]
+            Expr "^" Expr => (),
+            #[precedence(level="3")] #[assoc(side="left")]
+            Expr "*" Expr => (),
+            Expr "-" Expr => (),
+            #[precedence(level="4")] #[assoc(side="none")]
+            Expr "<" Expr => (),
+        };
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let states = build_states(&grammar, nt("Top")).unwrap();
+    assert!(unreduced_productions(&grammar, [&states[..]]).is_empty());
+}
+
+#[test]
+fn augment_start() {
+    let _tls = Tls::test();
+    let mut grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "a" => .., "," => .. } }
+        List: () = { List "," "a" => (), "a" => () };
+        pub Top: () = List => ();
+   "#,
+    );
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+
+    // public nonterminals already have a start symbol
+    let count = grammar.nonterminals.len();
+    assert_eq!(grammar.augment_start(&nt("Top")), nt("__Top"));
+    assert_eq!(grammar.nonterminals.len(), count);
+
+    // `List` refers to itself, so it gets one; that one is left alone
+    let start = grammar.augment_start(&nt("List"));
+    assert_eq!(start, nt("__List"));
+    assert_eq!(grammar.augment_start(&start), start);
+    assert_eq!(grammar.nonterminals.len(), count + 1);
+
+    // only one state accepts, on EOF
+    let states = build_states(&grammar, start.clone()).unwrap();
+    let accepting: Vec<_> = states
+        .iter()
+        .flat_map(|state| &state.reductions)
+        .filter(|&&(_, production)| production.nonterminal == start)
+        .collect();
+    assert_eq!(accepting.len(), 1);
+    assert!(accepting[0].0.contains(&Token::Eof));
+    assert_eq!(
+        format!("{:?}", interpret(&states, tokens!["a", ",", "a"]).unwrap()),
+        r#"[__List: [List: [List: "a"], ",", "a"]]"#
+    );
+}
+
+#[test]
+fn conflicts_are_sorted() {
+    let _tls = Tls::test();
+    let grammar = dangling_else("", r#"S "+" S => (), "X" "X" => (), "X" "X" "X" => ()"#);
+    let _lr1_tls = Lr1Tls::install(grammar.terminals.clone());
+    let first = build_states(&grammar, nt("Top")).unwrap_err().conflicts;
+    assert!(first.len() > 1);
+    assert!(first.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    let second = build_states(&grammar, nt("Top")).unwrap_err().conflicts;
+    assert_eq!(format!("{:?}", first), format!("{:?}", second));
+}
+
+#[test]
+fn hidden_left_recursion_chains() {
+    let _tls = Tls::test();
+    let grammar = normalized_grammar(
+        r#"
+        grammar;
+        extern { enum Tok { "b" => .., "c" => .., "d" => .., "x" => .. } }
+        pub A: () = { B A "c" => (), B C => (), "x" => () };
+        B: () = { => (), "b" => () };
+        C: () = { A "d" => (), "b" B A => () };
+        pub D: () = { "d" A => (), A D => (), "x" => () };
+   "#,
+    );
+    let found: Vec<_> = hidden_left_recursion(&grammar)
+        .into_iter()
+        .map(|recursion| {
+            format!(
+                "{:?} at {}: {}",
+                recursion.production,
+                recursion.prefix,
+                recursion
+                    .chain
+                    .iter()
+                    .map(|nt| nt.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            r#"A = B, A, "c" => ActionFn(2); at 1: A"#,
+            r#"A = B, C => ActionFn(3); at 1: C -> A"#,
+        ]
+    );
+}
+
+#[test]
+fn state_map() {
+    use crate::lr1::core::{StateIndex, StateMap};
+
+    let mut map = StateMap::new(3, 10);
+    assert_eq!(map.set(StateIndex(1), 7), 10);
+    map[StateIndex(2)] += 1;
+    assert_eq!(
+        (map[StateIndex(0)], map[StateIndex(1)], map[StateIndex(2)]),
+        (10, 7, 11)
+    );
+}
@@ -1,10 +1,18 @@
 //! Thread-local data specific to LR(1) processing.
 
+use crate::collections::{set, Set};
 use crate::grammar::repr::TerminalSet;
+use bit_set::BitSet;
 use std::cell::RefCell;
+use std::rc::Rc;
 
 thread_local! {
-    static TERMINALS: RefCell<Option<TerminalSet>> = const { RefCell::new(None) }
+    static TERMINALS: RefCell<Option<TerminalSet>> = const { RefCell::new(None) };
+
+    // The bits of the lookahead sets interned so far; see
+    // `TokenSet::intern`. They are only meaningful relative to the
+    // installed terminals, so the pool is emptied along with them.
+    static TOKEN_SETS: RefCell<Set<Rc<BitSet<u32>>>> = RefCell::new(set());
 }
 
 pub struct Lr1Tls {
@@ -14,6 +22,7 @@ pub struct Lr1Tls {
 impl Lr1Tls {
     pub fn install(terminals: TerminalSet) -> Lr1Tls {
         let old_value = TERMINALS.with(|s| s.borrow_mut().replace(terminals));
+        TOKEN_SETS.with(|s| s.borrow_mut().clear());
         Lr1Tls { old_value }
     }
 
@@ -23,10 +32,26 @@ impl Lr1Tls {
     {
         TERMINALS.with(|s| op(s.borrow().as_ref().expect("LR1 TLS not installed")))
     }
+
+    /// Returns the pooled copy of `bits`, adding it to the pool if
+    /// there is none yet.
+    pub fn intern(bits: Rc<BitSet<u32>>) -> Rc<BitSet<u32>> {
+        TOKEN_SETS.with(|s| {
+            let mut pool = s.borrow_mut();
+            match pool.get(&bits) {
+                Some(pooled) => pooled.clone(),
+                None => {
+                    pool.insert(bits.clone());
+                    bits
+                }
+            }
+        })
+    }
 }
 
 impl Drop for Lr1Tls {
     fn drop(&mut self) {
         TERMINALS.with(|s| *s.borrow_mut() = self.old_value.take());
+        TOKEN_SETS.with(|s| s.borrow_mut().clear());
     }
 }
@@ -26,7 +26,9 @@ Options:
     -c, --color          Force colorful output, even if this is not a TTY.
     --no-whitespace      Removes redundant whitespace from the generated file. (Default: false)
     --comments           Enable comments in the generated code.
-    --report             Generate report files.\
+    --report             Generate report files.
+    --json-report        Generate the parser states and conflicts as JSON.
+    --snapshot           Generate the parser states as a text snapshot.\
 ";
 
 #[derive(Debug)]
@@ -41,6 +43,8 @@ struct Args {
     flag_comments: bool,
     flag_no_whitespace: bool,
     flag_report: bool,
+    flag_json_report: bool,
+    flag_snapshot: bool,
     flag_version: bool,
 }
 
@@ -78,6 +82,8 @@ fn parse_args(mut args: Arguments) -> Result<Args, pico_args::Error> {
         flag_comments: args.contains("--comments"),
         flag_no_whitespace: args.contains("--no-whitespace"),
         flag_report: args.contains("--report"),
+        flag_json_report: args.contains("--json-report"),
+        flag_snapshot: args.contains("--snapshot"),
         flag_version: args.contains(["-V", "--version"]),
         arg_inputs: args.finish(),
     })
@@ -128,6 +134,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         config.emit_report(true);
     }
 
+    if args.flag_json_report {
+        config.emit_json_report(true);
+    }
+
+    if args.flag_snapshot {
+        config.emit_snapshot(true);
+    }
+
     if args.arg_inputs.is_empty() {
         return Err("Error: no input files specified! Try --help for help.".into());
     }
@@ -1,4 +1,5 @@
 use crate::grammar::parse_tree::Span;
+use crate::message::vert::Vert;
 use crate::message::Content;
 use crate::style::Style;
 use crate::tls::Tls;
@@ -42,6 +43,14 @@ impl Message {
             body,
         }
     }
+
+    /// Appends `note` to the body as a paragraph of its own.
+    pub fn with_note(self, note: Box<dyn Content>) -> Self {
+        Message {
+            body: Box::new(Vert::new(vec![self.body, note], 2)),
+            ..self
+        }
+    }
 }
 
 impl Content for Message {
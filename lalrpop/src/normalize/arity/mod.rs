@@ -0,0 +1,28 @@
+//! Check that the action fns match the productions that call them.
+
+use crate::grammar::repr::*;
+use crate::normalize::{NormError, NormResult};
+
+#[cfg(test)]
+mod test;
+
+pub fn validate(grammar: &Grammar) -> NormResult<()> {
+    for production in grammar
+        .nonterminals
+        .values()
+        .flat_map(|data| &data.productions)
+    {
+        let expected = production.symbols.len();
+        let found = grammar.action_fn_defns[production.action.index()].arity();
+        if expected != found {
+            return_err!(
+                production.span,
+                "the action code of this `{}` alternative takes {} symbols, but the alternative has {}",
+                production.nonterminal,
+                found,
+                expected
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::grammar::repr::NonterminalString;
+use crate::test_util::{check_norm_err, normalized_grammar};
+use string_cache::DefaultAtom as Atom;
+
+use super::validate;
+
+const GRAMMAR: &str = r#"
+grammar;
+pub Sum: i32 = {
+    <l:Sum> "+" <r:Num> => l + r,
+    Num,
+};
+Num: i32 = r"[0-9]+" => <>.parse().unwrap();
+"#;
+
+#[test]
+fn matching_arity() {
+    assert!(validate(&normalized_grammar(GRAMMAR)).is_ok());
+}
+
+#[test]
+fn mismatched_arity() {
+    let mut grammar = normalized_grammar(GRAMMAR);
+    let sum = NonterminalString(Atom::from("Sum"));
+    let production = grammar
+        .nonterminals
+        .get_mut(&sum)
+        .unwrap()
+        .productions
+        .iter_mut()
+        .find(|production| production.symbols.len() == 3)
+        .unwrap();
+    production.symbols.pop();
+
+    check_norm_err(
+        "the action code of this `Sum` alternative takes 3 symbols, but the alternative has 2",
+        r#"
+grammar;
+pub Sum: i32 = {
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Num,
+};
+Num: i32 = r"[0-9]+" => <>.parse().unwrap();
+"#,
+        validate(&grammar).unwrap_err(),
+    );
+}
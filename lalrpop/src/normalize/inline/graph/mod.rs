@@ -1,6 +1,7 @@
 use crate::collections::{map, Map};
 use crate::grammar::consts::INLINE;
 use crate::grammar::repr::*;
+use crate::grammar::visit::{walk_grammar, walk_nonterminal, GrammarVisitor};
 use crate::normalize::{NormError, NormResult};
 use petgraph::graph::{Graph, NodeIndex};
 use string_cache::DefaultAtom as Atom;
@@ -54,28 +55,38 @@ impl<'grammar> NonterminalGraph<'grammar> {
     }
 
     fn add_edges(&mut self) {
-        for production in self
-            .grammar
-            .nonterminals
-            .values()
-            .flat_map(|d| &d.productions)
-        {
-            let from_index = match self.nonterminal_map.get(&production.nonterminal) {
-                Some(&index) => index,
-                None => continue, // this is not an inlined nonterminal
-            };
+        struct AddEdges<'graph> {
+            graph: &'graph mut Graph<NonterminalString, ()>,
+            nonterminal_map: &'graph Map<NonterminalString, NodeIndex>,
+            from_index: Option<NodeIndex>,
+        }
 
-            for symbol in &production.symbols {
-                match *symbol {
-                    Symbol::Nonterminal(ref to) => {
-                        if let Some(&to_index) = self.nonterminal_map.get(to) {
-                            self.graph.add_edge(from_index, to_index, ());
-                        }
-                    }
-                    Symbol::Terminal(_) => {}
+        impl<'grammar> GrammarVisitor<'grammar> for AddEdges<'_> {
+            fn visit_nonterminal(&mut self, data: &'grammar NonterminalData) {
+                // only inlined nonterminals have nodes
+                self.from_index = self.nonterminal_map.get(&data.name).cloned();
+                if self.from_index.is_some() {
+                    walk_nonterminal(self, data);
+                }
+            }
+
+            fn visit_nonterminal_symbol(&mut self, to: &'grammar NonterminalString) {
+                if let (Some(from_index), Some(&to_index)) =
+                    (self.from_index, self.nonterminal_map.get(to))
+                {
+                    self.graph.add_edge(from_index, to_index, ());
                 }
             }
         }
+
+        walk_grammar(
+            self.grammar,
+            &mut AddEdges {
+                graph: &mut self.graph,
+                nonterminal_map: &self.nonterminal_map,
+                from_index: None,
+            },
+        );
     }
 
     fn inline_order(&self) -> NormResult<Vec<NonterminalString>> {
@@ -2,16 +2,16 @@
 //!
 
 use crate::collections::{map, Map};
-use crate::grammar::consts::CFG;
+use crate::grammar::consts::{CFG, EOF, SKIP};
 use crate::grammar::parse_tree as pt;
 use crate::grammar::parse_tree::{
     read_algorithm, GrammarItem, InternToken, Lifetime, MatchMapping, Name, NonterminalString,
-    Path, TerminalString,
+    Path, TerminalLiteral, TerminalString,
 };
 use crate::grammar::pattern::{Pattern, PatternKind};
 use crate::grammar::repr as r;
 use crate::normalize::norm_util::{self, Symbols};
-use crate::normalize::NormResult;
+use crate::normalize::{NormError, NormResult};
 use crate::session::Session;
 use string_cache::DefaultAtom as Atom;
 
@@ -110,13 +110,27 @@ impl<'s> LowerState<'s> {
                 pt::GrammarItem::ExternToken(data) => {
                     if let Some(enum_token) = data.enum_token {
                         token_span = Some(enum_token.type_span);
-                        self.conversions
-                            .extend(enum_token.conversions.iter().map(|conversion| {
-                                (
-                                    conversion.from.clone(),
-                                    conversion.to.map(&mut |t| t.type_repr()),
-                                )
-                            }));
+                        for conversion in &enum_token.conversions {
+                            let pattern = conversion.to.map(&mut |t| t.type_repr());
+                            // a terminal declared more than once matches
+                            // any of its patterns
+                            match self
+                                .conversions
+                                .iter_mut()
+                                .find(|(terminal, _)| *terminal == conversion.from)
+                            {
+                                Some((_, existing)) => match existing.kind {
+                                    PatternKind::Or(ref mut pats) => pats.push(pattern),
+                                    _ => {
+                                        *existing = Pattern {
+                                            span: existing.span,
+                                            kind: PatternKind::Or(vec![existing.clone(), pattern]),
+                                        }
+                                    }
+                                },
+                                None => self.conversions.push((conversion.from.clone(), pattern)),
+                            }
+                        }
                     }
                 }
 
@@ -176,6 +190,18 @@ impl<'s> LowerState<'s> {
 
         read_algorithm(&grammar.annotations, &mut algorithm);
 
+        let eof_terminal = match grammar.annotations.iter().find(|a| a.id == *EOF) {
+            Some(annotation) => Some(self.unused_terminal(annotation)?),
+            None => None,
+        };
+
+        let skip_terminals = grammar
+            .annotations
+            .iter()
+            .filter(|a| a.id == *SKIP)
+            .map(|annotation| self.unused_terminal(annotation))
+            .collect::<NormResult<Vec<_>>>()?;
+
         let mut all_terminals: Vec<_> = self
             .conversions
             .iter()
@@ -210,9 +236,54 @@ impl<'s> LowerState<'s> {
                 bits: terminal_bits,
             },
             module_attributes: grammar.module_attributes,
+            eof_terminal,
+            skip_terminals,
         })
     }
 
+    /// Resolves the terminal named by an `#[eof(terminal = "...")]` or
+    /// `#[skip(terminal = "...")]` annotation. It must be declared, but
+    /// since the parser never shifts it, it cannot be used in any
+    /// production.
+    fn unused_terminal(&self, annotation: &pt::Annotation) -> NormResult<TerminalString> {
+        let (_, ref name) = *annotation.arg.as_ref().unwrap(); // checked by prevalidate
+        let terminal = self
+            .conversions
+            .iter()
+            .map(|(terminal, _)| terminal)
+            .find(|terminal| match terminal {
+                TerminalString::Bare(id) => id == name,
+                TerminalString::Literal(TerminalLiteral::Quoted(s)) => s == name,
+                _ => false,
+            });
+        let terminal = match terminal {
+            Some(terminal) => terminal.clone(),
+            None => return_err!(
+                annotation.id_span,
+                "`{}` terminal `{}` is not declared in the extern token enum",
+                annotation.id,
+                name
+            ),
+        };
+
+        let used = self
+            .nonterminals
+            .values()
+            .flat_map(|nt| &nt.productions)
+            .flat_map(|production| &production.symbols)
+            .any(|symbol| *symbol == r::Symbol::Terminal(terminal.clone()));
+        if used {
+            return_err!(
+                annotation.id_span,
+                "`{}` terminal `{}` cannot be used in the grammar",
+                annotation.id,
+                terminal
+            );
+        }
+
+        Ok(terminal)
+    }
+
     fn synthesize_start_symbols(
         &mut self,
         grammar: &pt::Grammar,
@@ -37,10 +37,22 @@ pub fn expand_macros(input: Grammar) -> NormResult<Grammar> {
     Ok(Grammar { items, ..input })
 }
 
+/// How many macro instantiations may be nested inside one another. A
+/// macro that uses itself with the same arguments is expanded only once,
+/// so this is only reached by a macro that keeps instantiating itself
+/// with larger arguments, as in `Nest<T> = Nest<(T T)>`. The limit is
+/// kept low because such arguments can double in size at every level.
+const MAX_MACRO_DEPTH: usize = 16;
+
 struct MacroExpander {
     macro_defs: HashMap<NonterminalString, NonterminalData>,
     expansion_set: HashSet<NonterminalString>,
     expansion_stack: Vec<Symbol>,
+
+    // number of macro instantiations that led to each expanded
+    // nonterminal, and to the item whose symbols are being replaced
+    expansion_depths: HashMap<NonterminalString, usize>,
+    depth: usize,
 }
 
 impl MacroExpander {
@@ -49,6 +61,8 @@ impl MacroExpander {
             macro_defs,
             expansion_stack: Vec::new(),
             expansion_set: HashSet::new(),
+            expansion_depths: HashMap::new(),
+            depth: 0,
         }
     }
 
@@ -104,6 +118,7 @@ impl MacroExpander {
                 // they've already been siphoned off.
                 assert!(!data.is_macro_def());
 
+                self.depth = self.expansion_depths.get(&data.name).copied().unwrap_or(0);
                 for alternative in &mut data.alternatives {
                     self.replace_symbols(&mut alternative.expr.symbols);
                 }
@@ -151,7 +166,12 @@ impl MacroExpander {
             kind: SymbolKind::Nonterminal(key.clone()),
         };
         let to_expand = mem::replace(symbol, replacement);
-        if self.expansion_set.insert(key) {
+        if self.expansion_set.insert(key.clone()) {
+            let depth = match to_expand.kind {
+                SymbolKind::Macro(_) => self.depth + 1,
+                _ => self.depth,
+            };
+            self.expansion_depths.insert(key, depth);
             self.expansion_stack.push(to_expand);
         }
     }
@@ -167,6 +187,16 @@ impl MacroExpander {
             None => return_err!(span, "no macro definition found for `{}`", msym.name),
         };
 
+        if self.expansion_depths[&msym_name] > MAX_MACRO_DEPTH {
+            return_err!(
+                span,
+                "macro `{}` is nested more than {} levels deep; \
+                 does it use itself with ever larger arguments?",
+                msym.name,
+                MAX_MACRO_DEPTH
+            );
+        }
+
         if mdef.args.len() != msym.args.len() {
             return_err!(
                 span,
@@ -116,3 +116,57 @@ fn test_lookahead() {
 
     compare(actual, expected);
 }
+
+#[test]
+fn test_shared_instantiation() {
+    let grammar = parser::parse_grammar(
+        r#"
+grammar;
+    Pair<E>: (E, E) = E E;
+
+    A = Pair<"X">;
+    B = Pair<"Y">;
+    C = Pair<"X">;
+"#,
+    )
+    .unwrap();
+
+    let actual = expand_macros(grammar).unwrap();
+
+    let expected = parser::parse_grammar(
+        r##"
+grammar;
+    A = `Pair<"X">`;
+    B = `Pair<"Y">`;
+    C = `Pair<"X">`;
+
+    `Pair<"Y">`: (#"Y"#, #"Y"#) = "Y" "Y";
+    `Pair<"X">`: (#"X"#, #"X"#) = "X" "X";
+"##,
+    )
+    .unwrap();
+
+    compare(actual, expected);
+}
+
+#[test]
+fn test_unbounded_recursion() {
+    let grammar = parser::parse_grammar(
+        r#"
+grammar;
+    Nest<E> = { E, Nest<(E E)> };
+
+    A = Nest<"X">;
+"#,
+    )
+    .unwrap();
+
+    let error = expand_macros(grammar).unwrap_err();
+    assert!(
+        error
+            .message
+            .starts_with("macro `Nest` is nested more than 16 levels deep"),
+        "{}",
+        error.message
+    );
+}
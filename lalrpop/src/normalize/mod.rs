@@ -40,6 +40,7 @@ fn normalize_helper(
 ) -> NormResult<r::Grammar> {
     let grammar = lower_helper(session, grammar, validate)?;
     let grammar = profile!(session, "Inlining", inline::inline(grammar)?);
+    profile!(session, "Action arity check", arity::validate(&grammar)?);
     Ok(grammar)
 }
 
@@ -62,6 +63,11 @@ fn lower_helper(session: &Session, grammar: pt::Grammar, validate: bool) -> Norm
         "Macro expansion",
         macro_expand::expand_macros(grammar)?
     );
+    let grammar = profile!(
+        session,
+        "Action stripping",
+        recognize::strip_actions(grammar)
+    );
     let grammar = profile!(session, "Token check", token_check::validate(grammar)?);
     let types = profile!(session, "Infer types", tyinfer::infer_types(&grammar)?);
     let grammar = profile!(session, "Lowering", lower::lower(session, grammar, types)?);
@@ -93,6 +99,9 @@ mod precedence;
 // may occur.
 mod macro_expand;
 
+// Replaces all action code with `()` in a `#[recognizer]` grammar.
+mod recognize;
+
 // Check if there is an extern token and all terminals have have a
 // conversion; if no extern token, synthesize an intern token.
 mod token_check;
@@ -110,6 +119,11 @@ mod lower;
 // Inline nonterminals that have requested it.
 mod inline;
 
+// Check that each production has as many symbols as its action fn
+// takes arguments, so that a mismatch is reported against the grammar
+// rather than as a type error in the generated code.
+mod arity;
+
 ///////////////////////////////////////////////////////////////////////////
 // Shared routines
 
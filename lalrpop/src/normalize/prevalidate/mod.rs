@@ -7,6 +7,7 @@ use super::{NormError, NormResult};
 use crate::collections::{set, Multimap};
 use crate::grammar::consts::*;
 use crate::grammar::parse_tree::*;
+use crate::grammar::pattern::PatternKind;
 use crate::grammar::repr as r;
 use crate::util::Sep;
 use string_cache::DefaultAtom as Atom;
@@ -46,9 +47,40 @@ impl<'grammar> Validator<'grammar> {
     fn validate(&self) -> NormResult<()> {
         let allowed_names = [
             Atom::from(LALR),
+            Atom::from(SLR),
             Atom::from(TABLE_DRIVEN),
             Atom::from(RECURSIVE_ASCENT),
             Atom::from(TEST_ALL),
+            Atom::from(PUSH_PARSER),
+            Atom::from(INCREMENTAL),
+            Atom::from(EVENT_PARSER),
+            Atom::from(PARTIAL_RESULTS),
+            Atom::from(PARSE_PREFIX),
+            Atom::from(PARSE_ITEMS),
+            Atom::from(COUNT_REDUCTIONS),
+            Atom::from(NARROW_EXPECTED),
+            Atom::from(COMPACT_TABLES),
+            Atom::from(RECURSION_LIMIT),
+            Atom::from(REDUCTION_LIMIT),
+            Atom::from(STACK_CAPACITY),
+            Atom::from(TRACE),
+            Atom::from(PARSER_TRAIT),
+            Atom::from(TOKEN_NAMES),
+            Atom::from(PARSE_STR),
+            Atom::from(CLASSIFY),
+            Atom::from(SLICE_INPUT),
+            Atom::from(FIRST_SETS),
+            Atom::from(ACTION_TRAIT),
+            Atom::from(EXPECT_CONFLICTS),
+            Atom::from(CONFLICT_PREFERENCE),
+            Atom::from(DISAMBIGUATE),
+            Atom::from(BACKTRACK),
+            Atom::from(EOF),
+            Atom::from(SKIP),
+            Atom::from(BYTES),
+            Atom::from(CHARS),
+            Atom::from(STATE_MODULES),
+            Atom::from(RECOGNIZER),
         ];
         for annotation in &self.grammar.annotations {
             if !allowed_names.contains(&annotation.id) {
@@ -57,6 +89,426 @@ impl<'grammar> Validator<'grammar> {
                     "unrecognized annotation `{}`",
                     annotation.id
                 );
+            } else if annotation.id == *EOF {
+                match annotation.arg {
+                    Some((ref name, _)) if name == "terminal" => (),
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`eof` annotations must have a `terminal = "MyEof"` argument"#
+                    ),
+                }
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`eof` annotations require an extern token enum"
+                    );
+                }
+            } else if annotation.id == *SKIP {
+                match annotation.arg {
+                    Some((ref name, _)) if name == "terminal" => (),
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`skip` annotations must have a `terminal = "Whitespace"` argument"#
+                    ),
+                }
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`skip` annotations require an extern token enum"
+                    );
+                }
+            } else if annotation.id == *BYTES || annotation.id == *CHARS {
+                // the tokens are synthesized from the terminals
+                if self.match_token.is_some()
+                    || self.extern_token.is_some_and(|d| d.enum_token.is_some())
+                {
+                    return_err!(
+                        annotation.id_span,
+                        "`{}` annotations cannot be combined with a match block or an extern token enum",
+                        annotation.id
+                    );
+                }
+                if annotation.id == *CHARS
+                    && self.grammar.annotations.iter().any(|a| a.id == *BYTES)
+                {
+                    return_err!(
+                        annotation.id_span,
+                        "`chars` annotations cannot be combined with `bytes` annotations"
+                    );
+                }
+            } else if annotation.id == *PARSER_TRAIT {
+                // the trait hands over a token iterator, and nothing else
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`parser_trait` annotations require an extern token enum"
+                    );
+                }
+                if !self.grammar.parameters.is_empty() {
+                    return_err!(
+                        annotation.id_span,
+                        "`parser_trait` annotations cannot be used with grammar parameters"
+                    );
+                }
+            } else if annotation.id == *PARSE_ITEMS {
+                // the iterator cannot borrow the lexer of the parser
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`parse_items` annotations require an extern token enum"
+                    );
+                }
+            } else if annotation.id == *TOKEN_NAMES {
+                // the tokens of the generated lexer already display as
+                // their text
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`token_names` annotations require an extern token enum"
+                    );
+                }
+            } else if annotation.id == *PARSE_STR {
+                match annotation.arg {
+                    Some((ref name, ref value)) if name == "tokenizer" && !value.is_empty() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`parse_str` annotations must have a `tokenizer = "path::to::function"` argument"#
+                    ),
+                }
+                // with the generated lexer, `parse` already takes a string
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`parse_str` annotations require an extern token enum"
+                    );
+                }
+                if !self.grammar.parameters.is_empty() {
+                    return_err!(
+                        annotation.id_span,
+                        "`parse_str` annotations cannot be used with grammar parameters"
+                    );
+                }
+            } else if annotation.id == *ACTION_TRAIT {
+                match annotation.arg {
+                    Some((ref name, ref value)) if name == "parameter" => {
+                        if !self.grammar.parameters.iter().any(|p| p.name == *value) {
+                            return_err!(
+                                annotation.id_span,
+                                "`{}` is not a parameter of the grammar",
+                                value
+                            );
+                        }
+                    }
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`action_trait` annotations must have a `parameter = "actions"` argument"#
+                    ),
+                }
+            } else if annotation.id == *SLICE_INPUT {
+                // the generated lexer reads a string, not tokens
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`slice_input` annotations require an extern token enum"
+                    );
+                }
+                // these hand `parse` an iterator, or return one that
+                // would have to borrow the slice
+                for other in [PARSER_TRAIT, PARSE_STR, PARSE_ITEMS] {
+                    if self.grammar.annotations.iter().any(|a| a.id == *other) {
+                        return_err!(
+                            annotation.id_span,
+                            "`slice_input` and `{}` annotations cannot be combined",
+                            other
+                        );
+                    }
+                }
+            } else if annotation.id == *CLASSIFY {
+                match annotation.arg {
+                    Some((ref name, ref value)) if name == "function" && !value.is_empty() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`classify` annotations must have a `function = "path::to::function"` argument"#
+                    ),
+                }
+                // the generated lexer's tokens are matched by the lexer
+                if !self.extern_token.is_some_and(|d| d.enum_token.is_some()) {
+                    return_err!(
+                        annotation.id_span,
+                        "`classify` annotations require an extern token enum"
+                    );
+                }
+            } else if annotation.id == *RECURSION_LIMIT {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "depth" && value.parse::<usize>().is_ok() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`recursion_limit` annotations must have a `depth = "N"` argument"#
+                    ),
+                }
+            } else if annotation.id == *STATE_MODULES {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "count" && value.parse::<usize>().is_ok_and(|n| n > 0) => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`state_modules` annotations must have a `count = "N"` argument, where N is at least 1"#
+                    ),
+                }
+            } else if annotation.id == *REDUCTION_LIMIT {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "count" && value.parse::<usize>().is_ok() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`reduction_limit` annotations must have a `count = "N"` argument"#
+                    ),
+                }
+            } else if annotation.id == *STACK_CAPACITY {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "depth" && value.parse::<usize>().is_ok() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`stack_capacity` annotations must have a `depth = "N"` argument"#
+                    ),
+                }
+            } else if annotation.id == *EXPECT_CONFLICTS {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "count" && value.parse::<usize>().is_ok() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`expect_conflicts` annotations must have a `count = "N"` argument"#
+                    ),
+                }
+            } else if annotation.id == *CONFLICT_PREFERENCE {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "prefer" && (value == "shift" || value == "reduce") => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`conflict_preference` annotations must have a `prefer = "shift"` or `prefer = "reduce"` argument"#
+                    ),
+                }
+                if !self
+                    .grammar
+                    .annotations
+                    .iter()
+                    .any(|a| a.id == *EXPECT_CONFLICTS)
+                {
+                    return_err!(
+                        annotation.id_span,
+                        "`conflict_preference` annotations require an `expect_conflicts` annotation"
+                    );
+                }
+            } else if annotation.id == *DISAMBIGUATE {
+                match annotation.arg {
+                    Some((ref name, ref value)) if name == "function" && !value.is_empty() => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`disambiguate` annotations must have a `function = "path::to::function"` argument"#
+                    ),
+                }
+                if self
+                    .grammar
+                    .annotations
+                    .iter()
+                    .any(|a| a.id == *EXPECT_CONFLICTS)
+                {
+                    return_err!(
+                        annotation.id_span,
+                        "`disambiguate` and `expect_conflicts` annotations cannot be combined"
+                    );
+                }
+            } else if annotation.id == *BACKTRACK {
+                match annotation.arg {
+                    Some((ref name, ref value))
+                        if name == "window" && value.parse::<usize>().is_ok_and(|n| n > 0) => {}
+                    _ => return_err!(
+                        annotation.id_span,
+                        r#"`backtrack` annotations must have a `window = "N"` argument, where N is at least 1"#
+                    ),
+                }
+                // only `Parser` backtracks; the push, event and
+                // incremental parsers would take the first alternative
+                for other in [
+                    EXPECT_CONFLICTS,
+                    DISAMBIGUATE,
+                    PARSE_PREFIX,
+                    PARSE_ITEMS,
+                    PUSH_PARSER,
+                    EVENT_PARSER,
+                    INCREMENTAL,
+                ] {
+                    if self.grammar.annotations.iter().any(|a| a.id == *other) {
+                        return_err!(
+                            annotation.id_span,
+                            "`backtrack` and `{}` annotations cannot be combined",
+                            other
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut algorithm = r::Algorithm::default();
+        read_algorithm(&self.grammar.annotations, &mut algorithm);
+        if algorithm.slr && algorithm.lalr {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *SLR)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "`SLR` and `LALR` annotations cannot be combined"
+            );
+        }
+
+        if algorithm.recursion_limit.is_some()
+            && algorithm.codegen != r::LrCodeGeneration::RecursiveAscent
+        {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *RECURSION_LIMIT)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "recursion limits are only supported by recursive ascent parsers"
+            );
+        }
+
+        if algorithm.state_modules.is_some()
+            && algorithm.codegen != r::LrCodeGeneration::RecursiveAscent
+        {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *STATE_MODULES)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "state modules are only supported by recursive ascent parsers"
+            );
+        }
+
+        if algorithm.trace && algorithm.codegen != r::LrCodeGeneration::RecursiveAscent {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *TRACE)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "tracing is only supported by recursive ascent parsers"
+            );
+        }
+
+        if algorithm.classify.is_some() && algorithm.codegen != r::LrCodeGeneration::TableDriven {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *CLASSIFY)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "classifying tokens is only supported by table-driven parsers"
+            );
+        }
+
+        if algorithm.disambiguate.is_some() && algorithm.codegen != r::LrCodeGeneration::TableDriven
+        {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *DISAMBIGUATE)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "disambiguating at runtime is only supported by table-driven parsers"
+            );
+        }
+
+        if algorithm.backtrack.is_some() && algorithm.codegen != r::LrCodeGeneration::TableDriven {
+            let annotation = self
+                .grammar
+                .annotations
+                .iter()
+                .find(|a| a.id == *BACKTRACK)
+                .unwrap();
+            return_err!(
+                annotation.id_span,
+                "backtracking is only supported by table-driven parsers"
+            );
+        }
+
+        if algorithm.codegen == r::LrCodeGeneration::RecursiveAscent {
+            for annotation in &self.grammar.annotations {
+                if annotation.id == *PUSH_PARSER {
+                    return_err!(
+                        annotation.id_span,
+                        "push parsers are not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *INCREMENTAL {
+                    return_err!(
+                        annotation.id_span,
+                        "incremental parsing is not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *EVENT_PARSER {
+                    return_err!(
+                        annotation.id_span,
+                        "event parsers are not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *PARTIAL_RESULTS {
+                    return_err!(
+                        annotation.id_span,
+                        "partial results are not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *PARSE_PREFIX {
+                    return_err!(
+                        annotation.id_span,
+                        "prefix parsing is not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *PARSE_ITEMS {
+                    return_err!(
+                        annotation.id_span,
+                        "parsing items is not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *COUNT_REDUCTIONS {
+                    return_err!(
+                        annotation.id_span,
+                        "counting reductions is not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *NARROW_EXPECTED {
+                    return_err!(
+                        annotation.id_span,
+                        "narrowing expected tokens is not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *REDUCTION_LIMIT {
+                    return_err!(
+                        annotation.id_span,
+                        "reduction limits are not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *STACK_CAPACITY {
+                    return_err!(
+                        annotation.id_span,
+                        "stack capacities are not supported by recursive ascent parsers"
+                    );
+                } else if annotation.id == *COMPACT_TABLES {
+                    return_err!(
+                        annotation.id_span,
+                        "compact tables are not supported by recursive ascent parsers"
+                    );
+                }
             }
         }
 
@@ -112,6 +564,10 @@ impl<'grammar> Validator<'grammar> {
                             );
                         }
                     }
+
+                    if let Some(ref enum_token) = data.enum_token {
+                        self.validate_conversions(&enum_token.conversions)?;
+                    }
                 }
                 GrammarItem::Nonterminal(ref data) => {
                     if data.visibility.is_pub() && !data.args.is_empty() {
@@ -119,7 +575,12 @@ impl<'grammar> Validator<'grammar> {
                     }
                     let inline_annotation = Atom::from(INLINE);
                     let cfg_annotation = Atom::from(CFG);
-                    let known_annotations = [inline_annotation.clone(), cfg_annotation.clone()];
+                    let known_annotations = [
+                        inline_annotation.clone(),
+                        cfg_annotation.clone(),
+                        Atom::from(BOXED),
+                        Atom::from(PARSE_FN),
+                    ];
                     let mut found_annotations = set();
                     for annotation in &data.annotations {
                         if !known_annotations.contains(&annotation.id) {
@@ -139,6 +600,25 @@ impl<'grammar> Validator<'grammar> {
                                 annotation.id_span,
                                 "public items cannot be marked #[inline]"
                             );
+                        } else if annotation.id == *PARSE_FN {
+                            if !data.visibility.is_pub() {
+                                return_err!(
+                                    annotation.id_span,
+                                    "private items cannot be marked #[parse_fn]"
+                                );
+                            }
+                            if self.grammar.items.iter().any(|item| match *item {
+                                GrammarItem::Nonterminal(ref other) => {
+                                    other.name != data.name
+                                        && other.annotations.iter().any(|a| a.id == *PARSE_FN)
+                                }
+                                _ => false,
+                            }) {
+                                return_err!(
+                                    annotation.id_span,
+                                    "only one nonterminal can be marked #[parse_fn]"
+                                );
+                            }
                         } else if annotation.id == cfg_annotation {
                             if data.visibility.is_pub() {
                                 match annotation.arg {
@@ -169,6 +649,54 @@ impl<'grammar> Validator<'grammar> {
         Ok(())
     }
 
+    /// Checks that no two terminals of an extern token enum match the
+    /// same token, since the later one could then never be matched.
+    /// A bare `..` is not a real pattern, and is ignored. A terminal
+    /// may be declared more than once, but then each of its patterns
+    /// must bind values of the same types.
+    fn validate_conversions(&self, conversions: &[Conversion]) -> NormResult<()> {
+        let is_placeholder = |conversion: &Conversion| conversion.to.kind == PatternKind::DotDot;
+        let bindings = |conversion: &Conversion| {
+            let mut types = vec![];
+            conversion
+                .to
+                .for_each_binding(&mut |ty| types.push(ty.clone()));
+            types
+        };
+        for (index, conversion) in conversions.iter().enumerate() {
+            if let Some(earlier) = conversions[..index]
+                .iter()
+                .find(|earlier| earlier.from == conversion.from)
+            {
+                if bindings(earlier) != bindings(conversion) {
+                    return_err!(
+                        conversion.to.span,
+                        "the pattern `{}` for terminal `{}` must bind the same values as the pattern `{}`",
+                        conversion.to,
+                        conversion.from,
+                        earlier.to
+                    );
+                }
+            }
+            if is_placeholder(conversion) {
+                continue;
+            }
+            for earlier in &conversions[..index] {
+                if !is_placeholder(earlier) && earlier.to.overlaps(&conversion.to) {
+                    return_err!(
+                        conversion.to.span,
+                        "the pattern `{}` for terminal `{}` overlaps the pattern `{}` for terminal `{}`",
+                        conversion.to,
+                        conversion.from,
+                        earlier.to,
+                        earlier.from
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn validate_precedence(&self, alternatives: &[Alternative]) -> NormResult<()> {
         let with_precedence = alternatives.iter().any(|alt| {
             alt.annotations
@@ -34,6 +34,61 @@ fn dup_assoc_type() {
     );
 }
 
+#[test]
+fn overlapping_terminal_patterns() {
+    check_err(
+        r#"the pattern `Tok::A` for terminal `"b"` overlaps the pattern `Tok::A` for terminal `"a"`"#,
+        r#"grammar; extern { enum Tok { "a" => Tok::A, "b" => Tok::A } }"#,
+        r#"                                                   ~~~~~~    "#,
+    );
+    check_err(
+        r#"the pattern `Tok::Num\(_\)` for terminal `Zero` overlaps"#,
+        r#"grammar; extern { enum Tok { Num => Tok::Num(<i32>), Zero => Tok::Num(_) } }"#,
+        r#"                                                             ~~~~~~~~~~~    "#,
+    );
+    check_err(
+        r#"for terminal `Named` overlaps the pattern `Tok::Id \{ raw: false, \.\. \}`"#,
+        r#"grammar; extern { enum Tok { Id => Tok::Id { raw: false, .. }, Named => Tok::Id { name: <String>, raw: false } } }"#,
+        r#"                                                                        ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~    "#,
+    );
+}
+
+#[test]
+fn alias_terminal_bindings() {
+    check_err(
+        r#"the pattern `Tok::Hex` for terminal `Num` must bind the same values as the pattern `Tok::Int\(i64\)`"#,
+        r#"grammar; extern { enum Tok { Num => Tok::Int(<i64>), Num => Tok::Hex } }"#,
+        r#"                                                            ~~~~~~~~    "#,
+    );
+    check_err(
+        r#"the pattern `Tok::Hex\(u8\)` for terminal `Num` must bind the same values"#,
+        r#"grammar; extern { enum Tok { Num => Tok::Int(<i64>), Num => Tok::Hex(<u8>) } }"#,
+        r#"                                                            ~~~~~~~~~~~~~~   "#,
+    );
+}
+
+#[test]
+fn disjoint_terminal_patterns() {
+    let grammar = r#"
+        grammar;
+        extern {
+            enum Tok {
+                "a" => Tok::A,
+                "b" => Tok::B,
+                One => Tok::Char('1'),
+                Two => Tok::Char('2'),
+                Raw => Tok::Id { raw: true, .. },
+                Id => Tok::Id { raw: false, .. },
+                "(" => Tok::Pair(_, 'x'),
+                ")" => Tok::Pair(.., 'y'),
+            }
+        }
+        Term = "a";
+    "#;
+    let parsed_grammar = parser::parse_grammar(grammar).unwrap();
+    assert!(super::validate(&parsed_grammar).is_ok());
+}
+
 #[test]
 fn lookahead_without_loc_type() {
     check_err(
@@ -61,6 +116,332 @@ fn unrecognized_annotation() {
     );
 }
 
+#[test]
+fn push_parser_recursive_ascent() {
+    check_err(
+        r#"push parsers are not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[push_parser] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn eof_annotation_without_terminal() {
+    check_err(
+        r#"`eof` annotations must have a `terminal = "MyEof"` argument"#,
+        r#"#[eof] grammar; extern { enum Tok { } } Term = ();"#,
+        r#"  ~~~                                             "#,
+    );
+}
+
+#[test]
+fn eof_annotation_without_extern() {
+    check_err(
+        r#"`eof` annotations require an extern token enum"#,
+        r#"#[eof(terminal = "Eof")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn skip_annotation_without_terminal() {
+    check_err(
+        r#"`skip` annotations must have a `terminal = "Whitespace"` argument"#,
+        r#"#[skip] grammar; extern { enum Tok { } } Term = ();"#,
+        r#"  ~~~~                                             "#,
+    );
+}
+
+#[test]
+fn skip_annotation_without_extern() {
+    check_err(
+        r#"`skip` annotations require an extern token enum"#,
+        r#"#[skip(terminal = "Space")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn event_parser_recursive_ascent() {
+    check_err(
+        r#"event parsers are not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[event_parser] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn incremental_recursive_ascent() {
+    check_err(
+        r#"incremental parsing is not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[incremental] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn partial_results_recursive_ascent() {
+    check_err(
+        r#"partial results are not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[partial_results] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn parse_prefix_recursive_ascent() {
+    check_err(
+        r#"prefix parsing is not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[parse_prefix] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn parse_items_recursive_ascent() {
+    check_err(
+        r#"parsing items is not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[parse_items] grammar; extern { enum Tok { } } Term = ();"#,
+        r#"                      ~~~~~~~~~~~                                             "#,
+    );
+}
+
+#[test]
+fn parse_items_without_extern() {
+    check_err(
+        r#"`parse_items` annotations require an extern token enum"#,
+        r#"#[parse_items] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn count_reductions_recursive_ascent() {
+    check_err(
+        r#"counting reductions is not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[count_reductions] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn compact_tables_recursive_ascent() {
+    check_err(
+        r#"compact tables are not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[compact_tables] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn narrow_expected_recursive_ascent() {
+    check_err(
+        r#"narrowing expected tokens is not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[narrow_expected] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn token_names_intern_token() {
+    check_err(
+        r#"`token_names` annotations require an extern token enum"#,
+        r#"#[token_names] grammar; Term = "x";"#,
+        r#"  ~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn parse_str_tokenizer() {
+    check_err(
+        r#"`parse_str` annotations must have a `tokenizer = "path::to::function"` argument"#,
+        r#"#[parse_str(lexer = "tokenize")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"`parse_str` annotations require an extern token enum"#,
+        r#"#[parse_str(tokenizer = "tokenize")] grammar; Term = "x";"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn classify_function() {
+    check_err(
+        r#"`classify` annotations must have a `function = "path::to::function"` argument"#,
+        r#"#[classify(kind = "kind")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"`classify` annotations require an extern token enum"#,
+        r#"#[classify(function = "kind")] grammar; Term = "x";"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+    check_err(
+        r#"classifying tokens is only supported by table-driven parsers"#,
+        r#"#[recursive_ascent] #[classify(function = "kind")] grammar; extern { enum Tok { "x" => Tok::X } } Term = "x";"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~~~~~                                                            "#,
+    );
+}
+
+#[test]
+fn disambiguate_function() {
+    check_err(
+        r#"`disambiguate` annotations must have a `function = "path::to::function"` argument"#,
+        r#"#[disambiguate] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"`disambiguate` and `expect_conflicts` annotations cannot be combined"#,
+        r#"#[expect_conflicts(count = "1")] #[disambiguate(function = "choose")] grammar; Term = ();"#,
+        r#"                                   ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+    check_err(
+        r#"disambiguating at runtime is only supported by table-driven parsers"#,
+        r#"#[recursive_ascent] #[disambiguate(function = "choose")] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn backtrack_window() {
+    check_err(
+        r#"`backtrack` annotations must have a `window = "N"` argument, where N is at least 1"#,
+        r#"#[backtrack(window = "0")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"`backtrack` and `disambiguate` annotations cannot be combined"#,
+        r#"#[disambiguate(function = "choose")] #[backtrack(window = "2")] grammar; Term = ();"#,
+        r#"                                       ~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+    check_err(
+        r#"`backtrack` and `push_parser` annotations cannot be combined"#,
+        r#"#[push_parser] #[backtrack(window = "2")] grammar; Term = ();"#,
+        r#"                 ~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+    check_err(
+        r#"`backtrack` and `event_parser` annotations cannot be combined"#,
+        r#"#[event_parser] #[backtrack(window = "2")] grammar; Term = ();"#,
+        r#"                  ~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+    check_err(
+        r#"`backtrack` and `incremental` annotations cannot be combined"#,
+        r#"#[incremental] #[backtrack(window = "2")] grammar; Term = ();"#,
+        r#"                 ~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+    check_err(
+        r#"backtracking is only supported by table-driven parsers"#,
+        r#"#[recursive_ascent] #[backtrack(window = "2")] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn recursion_limit_depth() {
+    check_err(
+        r#"`recursion_limit` annotations must have a `depth = "N"` argument"#,
+        r#"#[recursive_ascent] #[recursion_limit(depth = "x")] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~~~~~~                      "#,
+    );
+}
+
+#[test]
+fn slr_and_lalr() {
+    check_err(
+        r#"`SLR` and `LALR` annotations cannot be combined"#,
+        r#"#[LALR] #[SLR] grammar; Term = ();"#,
+        r#"          ~~~                     "#,
+    );
+}
+
+#[test]
+fn recursion_limit_table_driven() {
+    check_err(
+        r#"recursion limits are only supported by recursive ascent parsers"#,
+        r#"#[recursion_limit(depth = "10")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn state_modules_count() {
+    check_err(
+        r#"`state_modules` annotations must have a `count = "N"` argument, where N is at least 1"#,
+        r#"#[recursive_ascent] #[state_modules(count = "0")] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn state_modules_table_driven() {
+    check_err(
+        r#"state modules are only supported by recursive ascent parsers"#,
+        r#"#[state_modules(count = "4")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn reduction_limit_count() {
+    check_err(
+        r#"`reduction_limit` annotations must have a `count = "N"` argument"#,
+        r#"#[reduction_limit(depth = "10")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"reduction limits are not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[reduction_limit(count = "10")] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn stack_capacity_depth() {
+    check_err(
+        r#"`stack_capacity` annotations must have a `depth = "N"` argument"#,
+        r#"#[stack_capacity(count = "64")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"stack capacities are not supported by recursive ascent parsers"#,
+        r#"#[recursive_ascent] #[stack_capacity(depth = "64")] grammar; Term = ();"#,
+        r#"                      ~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn trace_table_driven() {
+    check_err(
+        r#"tracing is only supported by recursive ascent parsers"#,
+        r#"#[trace] grammar; Term = ();"#,
+        r#"  ~~~~~                     "#,
+    );
+}
+
+#[test]
+fn expect_conflicts_count() {
+    check_err(
+        r#"`expect_conflicts` annotations must have a `count = "N"` argument"#,
+        r#"#[expect_conflicts(number = "1")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
+#[test]
+fn conflict_preference() {
+    check_err(
+        r#"`conflict_preference` annotations must have a `prefer = "shift"` or `prefer = "reduce"` argument"#,
+        r#"#[expect_conflicts(count = "1")] #[conflict_preference(prefer = "left")] grammar; Term = ();"#,
+        r#"                                   ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        "`conflict_preference` annotations require an `expect_conflicts` annotation",
+        r#"#[conflict_preference(prefer = "reduce")] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                     "#,
+    );
+}
+
 #[test]
 fn duplicate_annotation() {
     check_err(
@@ -70,6 +451,20 @@ fn duplicate_annotation() {
     );
 }
 
+#[test]
+fn parse_fn_annotation() {
+    check_err(
+        r"private items cannot be marked #\[parse_fn\]",
+        r#"grammar; #[parse_fn] Term = ();"#,
+        r#"           ~~~~~~~~            "#,
+    );
+    check_err(
+        r"only one nonterminal can be marked #\[parse_fn\]",
+        r#"grammar; #[parse_fn] pub A = (); #[parse_fn] pub B = ();"#,
+        r#"           ~~~~~~~~                                     "#,
+    );
+}
+
 #[test]
 fn pub_inline_annotation() {
     check_err(
@@ -222,3 +617,58 @@ fn missing_macro_arg() {
         r#"                                                                               ~~~~~~~"#,
     )
 }
+
+#[test]
+fn parser_trait_intern_token() {
+    check_err(
+        r#"`parser_trait` annotations require an extern token enum"#,
+        r#"#[parser_trait] grammar; pub Term = "x";"#,
+        r#"  ~~~~~~~~~~~~                           "#,
+    );
+}
+
+#[test]
+fn action_trait_parameter() {
+    check_err(
+        r#"`action_trait` annotations must have a `parameter = "actions"` argument"#,
+        r#"#[action_trait] grammar; Term = ();"#,
+        r#"  ~~~~~~~~~~~~                     "#,
+    );
+    check_err(
+        r#"`actions` is not a parameter of the grammar"#,
+        r#"#[action_trait(parameter = "actions")] grammar(scale: i32); Term = ();"#,
+        r#"  ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~                                 "#,
+    );
+}
+
+#[test]
+fn slice_input() {
+    check_err(
+        r#"`slice_input` annotations require an extern token enum"#,
+        r#"#[slice_input] grammar; pub Term = "x";"#,
+        r#"  ~~~~~~~~~~~                          "#,
+    );
+    check_err(
+        r#"`slice_input` and `parse_items` annotations cannot be combined"#,
+        r#"#[parse_items] #[slice_input] grammar; extern { enum Tok { "x" => Tok::X } } pub Term = "x";"#,
+        r#"                 ~~~~~~~~~~~                                                                "#,
+    );
+}
+
+#[test]
+fn bytes_match_block() {
+    check_err(
+        r#"`bytes` annotations cannot be combined with a match block or an extern token enum"#,
+        r#"#[bytes] grammar; match { "x" } pub Term = "x";"#,
+        r#"  ~~~~~                                         "#,
+    );
+}
+
+#[test]
+fn chars_with_bytes() {
+    check_err(
+        r#"`chars` annotations cannot be combined with `bytes` annotations"#,
+        r#"#[bytes] #[chars] grammar; pub Term = "x";"#,
+        r#"           ~~~~~                           "#,
+    );
+}
@@ -0,0 +1,28 @@
+//! In a `#[recognizer]` grammar, the parser only checks whether the
+//! input is valid, so every nonterminal is given the type `()` and
+//! every action code is replaced by `()`. The user's action code,
+//! including `@L`, `@R` and fallible actions, is dropped without being
+//! compiled.
+
+use crate::grammar::consts::RECOGNIZER;
+use crate::grammar::parse_tree::{ActionKind, Grammar, GrammarItem, TypeRef};
+
+#[cfg(test)]
+mod test;
+
+pub fn strip_actions(mut grammar: Grammar) -> Grammar {
+    if !grammar.annotations.iter().any(|a| a.id == *RECOGNIZER) {
+        return grammar;
+    }
+
+    for item in &mut grammar.items {
+        if let GrammarItem::Nonterminal(ref mut data) = *item {
+            data.type_decl = Some(TypeRef::Tuple(vec![]));
+            for alternative in &mut data.alternatives {
+                alternative.action = Some(ActionKind::User("()".to_string()));
+            }
+        }
+    }
+
+    grammar
+}
@@ -0,0 +1,47 @@
+use crate::parser;
+use crate::test_util::compare;
+
+use super::strip_actions;
+
+#[test]
+fn unit_actions() {
+    let grammar = parser::parse_grammar(
+        r#"
+#[recognizer]
+grammar;
+    pub Expr: Box<Expr> = {
+       <l:Expr> "+" <r:Term> => Box::new(Expr::Add(l, r)),
+       <l:@L> Term <r:@R> =>? check(l, r),
+    };
+
+    Term = "N";
+"#,
+    )
+    .unwrap();
+
+    let expected = parser::parse_grammar(
+        r#"
+#[recognizer]
+grammar;
+    pub Expr: () = {
+       <l:Expr> "+" <r:Term> => (),
+       <l:@L> Term <r:@R> => (),
+    };
+
+    Term: () = "N" => ();
+"#,
+    )
+    .unwrap();
+
+    compare(strip_actions(grammar), expected);
+}
+
+#[test]
+fn other_grammars_unchanged() {
+    let text = r#"
+grammar;
+    pub Expr: u32 = <l:Expr> "+" "N" => l + 1;
+"#;
+    let grammar = parser::parse_grammar(text).unwrap();
+    compare(strip_actions(grammar), parser::parse_grammar(text).unwrap());
+}
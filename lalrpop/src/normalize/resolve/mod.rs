@@ -3,7 +3,7 @@
 
 use super::{NormError, NormResult};
 
-use crate::collections::{map, Map};
+use crate::collections::{map, set, Map};
 use crate::grammar::parse_tree::*;
 use string_cache::DefaultAtom as Atom;
 
@@ -23,6 +23,9 @@ fn resolve_in_place(grammar: &mut Grammar) -> NormResult<()> {
             .filter_map(GrammarItem::as_nonterminal)
             .map(|nt| (nt.span, nt.name.0.clone(), Def::Nonterminal(nt.args.len())));
 
+        // a terminal may be declared more than once in the extern token
+        // enum, to match any of several tokens
+        let mut declared = set();
         let terminal_identifiers = grammar
             .items
             .iter()
@@ -32,7 +35,8 @@ fn resolve_in_place(grammar: &mut Grammar) -> NormResult<()> {
             .filter_map(|conversion| match conversion.from {
                 TerminalString::Literal(..) | TerminalString::Error => None,
                 TerminalString::Bare(ref id) => Some((conversion.span, id.clone(), Def::Terminal)),
-            });
+            })
+            .filter(|(_, id, _)| declared.insert(id.clone()));
 
         // Extract all the bare identifiers that appear in the RHS of a `match` declaration.
         // Example:
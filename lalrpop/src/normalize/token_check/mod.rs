@@ -10,9 +10,11 @@ use super::{NormError, NormResult};
 use crate::collections::{Map, Set};
 use crate::grammar::consts::*;
 use crate::grammar::parse_tree::*;
+use crate::grammar::pattern::{Pattern, PatternKind};
 use crate::lexer::dfa::{self, DfaConstructionError, Precedence};
 use crate::lexer::nfa::NfaConstructionError::*;
 use crate::lexer::re;
+use regex_syntax::hir::{Class, HirKind};
 use string_cache::DefaultAtom as Atom;
 
 #[cfg(test)]
@@ -53,6 +55,18 @@ pub fn validate(mut grammar: Grammar) -> NormResult<Grammar> {
         TokenMode::Extern { .. } => {
             // If using an external tokenizer, we're all done at this point.
         }
+        TokenMode::Internal { match_block }
+            if grammar.annotations.iter().any(|a| a.id == *BYTES) =>
+        {
+            // A `#[bytes]` grammar reads the bytes themselves as tokens.
+            construct_units(&mut grammar, match_block, Unit::Byte)?;
+        }
+        TokenMode::Internal { match_block }
+            if grammar.annotations.iter().any(|a| a.id == *CHARS) =>
+        {
+            // and a `#[chars]` grammar, the chars.
+            construct_units(&mut grammar, match_block, Unit::Char)?;
+        }
         TokenMode::Internal { match_block } => {
             // Otherwise, construct the `InternToken` item.
             construct(&mut grammar, match_block)?;
@@ -424,3 +438,169 @@ fn construct(grammar: &mut Grammar, match_block: MatchBlock) -> NormResult<()> {
 
     Ok(())
 }
+
+///////////////////////////////////////////////////////////////////////////
+// Byte construction phase -- in a `#[bytes]` grammar, each terminal
+// stands for a set of bytes, and we synthesize an extern token enum
+// of `u8` whose conversions match those bytes. A `#[chars]` grammar
+// is the same, with `char` in place of `u8`.
+
+/// The tokens of a grammar without a tokenizer.
+#[derive(Copy, Clone)]
+enum Unit {
+    Byte,
+    Char,
+}
+
+impl Unit {
+    fn name(self) -> &'static str {
+        match self {
+            Unit::Byte => "byte",
+            Unit::Char => "char",
+        }
+    }
+
+    fn type_name(self) -> &'static str {
+        match self {
+            Unit::Byte => "u8",
+            Unit::Char => "char",
+        }
+    }
+
+    /// The pattern matching the tokens that `literal` matches, if it
+    /// matches exactly one token.
+    fn pattern(self, literal: &TerminalLiteral) -> Option<PatternKind<TypeRef>> {
+        match self {
+            Unit::Byte => byte_ranges(literal).map(PatternKind::ByteRanges),
+            Unit::Char => char_ranges(literal).map(PatternKind::CharRanges),
+        }
+    }
+}
+
+fn construct_units(grammar: &mut Grammar, match_block: MatchBlock, unit: Unit) -> NormResult<()> {
+    let MatchBlock {
+        match_entries,
+        spans,
+        ..
+    } = match_block;
+
+    let mut conversions: Vec<Conversion> = Vec::with_capacity(match_entries.len());
+    for match_entry in match_entries {
+        let literal = match_entry.match_literal;
+        let span = spans[&literal];
+        let kind = match unit.pattern(&literal) {
+            Some(kind) => kind,
+            None => return_err!(
+                span,
+                "terminal `{}` does not match exactly one {}, as terminals of a `{}s` grammar must",
+                literal,
+                unit.name(),
+                unit.name()
+            ),
+        };
+        let conversion = Conversion {
+            span,
+            from: TerminalString::Literal(literal),
+            to: Pattern { span, kind },
+        };
+        if let Some(other) = conversions.iter().find(|c| c.to.overlaps(&conversion.to)) {
+            return_err!(
+                span,
+                "the terminals `{}` and `{}` match some of the same {}s",
+                other.from,
+                conversion.from,
+                unit.name()
+            );
+        }
+        conversions.push(conversion);
+    }
+
+    let enum_token = EnumToken {
+        type_name: TypeRef::Id(Atom::from(unit.type_name())),
+        type_span: grammar.span,
+        conversions,
+    };
+
+    // an `extern` block may already declare the error type
+    let location = AssociatedType {
+        type_span: grammar.span,
+        type_name: Atom::from(LOCATION),
+        type_ref: TypeRef::Id(Atom::from("usize")),
+    };
+    match grammar.items.iter_mut().find_map(|item| match item {
+        GrammarItem::ExternToken(extern_token) => Some(extern_token),
+        _ => None,
+    }) {
+        Some(extern_token) => {
+            if extern_token.associated_type(Atom::from(LOCATION)).is_none() {
+                extern_token.associated_types.push(location);
+            }
+            extern_token.enum_token = Some(enum_token);
+        }
+        None => grammar.items.push(GrammarItem::ExternToken(ExternToken {
+            span: grammar.span,
+            associated_types: vec![location],
+            enum_token: Some(enum_token),
+        })),
+    }
+
+    Ok(())
+}
+
+/// The inclusive ranges of bytes that a terminal matches, if it
+/// matches exactly one byte: `"+"` or `r"[0-9a-f]"`, say.
+fn byte_ranges(literal: &TerminalLiteral) -> Option<Vec<(u8, u8)>> {
+    let regex = match *literal {
+        TerminalLiteral::Quoted(ref s) => re::parse_literal(s),
+        TerminalLiteral::Regex(ref s) => re::parse_regex(s).ok()?,
+    };
+    match regex.kind() {
+        HirKind::Literal(literal) => match *literal.0 {
+            [byte] => Some(vec![(byte, byte)]),
+            _ => None,
+        },
+        HirKind::Class(Class::Bytes(class)) => Some(
+            class
+                .ranges()
+                .iter()
+                .map(|range| (range.start(), range.end()))
+                .collect(),
+        ),
+        HirKind::Class(Class::Unicode(class)) => class
+            .ranges()
+            .iter()
+            .map(|range| {
+                let start = u8::try_from(range.start()).ok().filter(u8::is_ascii)?;
+                let end = u8::try_from(range.end()).ok().filter(u8::is_ascii)?;
+                Some((start, end))
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// The inclusive ranges of chars that a terminal matches, if it
+/// matches exactly one char: `"λ"` or `r"[a-zA-Z_]"`, say.
+fn char_ranges(literal: &TerminalLiteral) -> Option<Vec<(char, char)>> {
+    let regex = match *literal {
+        TerminalLiteral::Quoted(ref s) => re::parse_literal(s),
+        TerminalLiteral::Regex(ref s) => re::parse_regex(s).ok()?,
+    };
+    match regex.kind() {
+        HirKind::Literal(literal) => {
+            let mut chars = std::str::from_utf8(&literal.0).ok()?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(vec![(c, c)]),
+                _ => None,
+            }
+        }
+        HirKind::Class(Class::Unicode(class)) => Some(
+            class
+                .ranges()
+                .iter()
+                .map(|range| (range.start(), range.end()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
@@ -241,3 +241,39 @@ fn same_literal_twice() {
         r#"                                         ~~~~~~~~~~~~~~~ "#,
     );
 }
+
+#[test]
+fn bytes_multibyte_terminal() {
+    check_err(
+        r#"terminal `"ab"` does not match exactly one byte"#,
+        r#"#[bytes] grammar; X = X "ab";"#,
+        r#"                        ~~~~ "#,
+    );
+}
+
+#[test]
+fn bytes_overlapping_terminals() {
+    check_err(
+        r#"the terminals `r.*\[0-9\].*` and `"7"` match some of the same bytes"#,
+        r#"#[bytes] grammar; X = r"[0-9]" "7";"#,
+        r#"                               ~~~ "#,
+    );
+}
+
+#[test]
+fn chars_multichar_terminal() {
+    check_err(
+        r#"terminal `"λx"` does not match exactly one char"#,
+        r#"#[chars] grammar; X = X "λx";"#,
+        r#"                        ~~~~~ "#,
+    );
+}
+
+#[test]
+fn chars_overlapping_terminals() {
+    check_err(
+        r#"the terminals `r.*\[a-zλ\].*` and `"λ"` match some of the same chars"#,
+        r#"#[chars] grammar; X = r"[a-zλ]" "λ";"#,
+        r#"                                 ~~~~ "#,
+    );
+}
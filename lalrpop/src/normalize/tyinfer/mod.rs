@@ -124,10 +124,14 @@ impl<'grammar> TypeInferencer<'grammar> {
             // e.g. "(" => Lparen(..) ==> no custom type
             //      "Num" => Num(<u32>) ==> custom type is u32
             //      "Fraction" => Real(<u32>,<u32>) ==> custom type is (u32, u32)
+            // a terminal declared more than once binds the same types
+            // each time, as checked during validation
+            let mut declared = HashSet::new();
             for conversion in grammar
                 .enum_token()
                 .into_iter()
                 .flat_map(|et| &et.conversions)
+                .filter(|conversion| declared.insert(&conversion.from))
             {
                 let mut tys = Vec::new();
                 conversion
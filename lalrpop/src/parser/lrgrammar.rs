@@ -6367,6 +6367,14 @@ ___tokens,
 )
 }
 }
+impl TopParser {
+#[allow(dead_code)]
+pub(crate) const TERMINALS: &'static [&'static str] = &["\"!\"", "\"!=\"", "\"!~\"", "\"#\"", "\"#![...]\"", "\"&\"", "\"(\"", "\")\"", "\"*\"", "\"+\"", "\",\"", "\"->\"", "\"..\"", "\":\"", "\"::\"", "\";\"", "\"<\"", "\"=\"", "\"==\"", "\"=>\"", "\"=>?\"", "\"=>@L\"", "\"=>@R\"", "\">\"", "\"?\"", "\"@L\"", "\"@R\"", "\"CharLiteral\"", "\"Escape\"", "\"Id\"", "\"Lifetime\"", "\"MacroId\"", "\"RegexLiteral\"", "\"StartGrammar\"", "\"StartGrammarWhereClauses\"", "\"StartMatchMapping\"", "\"StartPattern\"", "\"StartTypeRef\"", "\"StringLiteral\"", "\"[\"", "\"]\"", "\"_\"", "\"dyn\"", "\"else\"", "\"enum\"", "\"extern\"", "\"for\"", "\"grammar\"", "\"if\"", "\"in\"", "\"match\"", "\"mut\"", "\"pub\"", "\"type\"", "\"use\"", "\"where\"", "\"{\"", "\"}\"", "\"~~\""];
+#[allow(dead_code)]
+pub(crate) const NONTERMINALS: &'static [&'static str] = &["\"::\"?", "\";\"?", "\"mut\"?", "(\"->\" <TypeRef>)", "(\"->\" <TypeRef>)?", "(\":\" <TypeRef>)", "(\":\" <TypeRef>)?", "(\"<\" <Comma<TypeBoundParameter>> \">\")", "(\"<\" <Comma<TypeBoundParameter>> \">\")?", "(\"if\" <Cond>)", "(\"if\" <Cond>)?", "()", "(<Alternative> \",\")", "(<Alternative> \",\")*", "(<Alternative> \",\")+", "(<Conversion> \",\")", "(<Conversion> \",\")*", "(<Conversion> \",\")+", "(<FieldPattern> \",\")", "(<FieldPattern> \",\")*", "(<FieldPattern> \",\")+", "(<GrammarParameter> \",\")", "(<GrammarParameter> \",\")*", "(<GrammarParameter> \",\")+", "(<GrammarWhereClause> \",\")", "(<GrammarWhereClause> \",\")*", "(<GrammarWhereClause> \",\")+", "(<Id> \"::\")", "(<Id> \"::\")*", "(<Id> \"::\")+", "(<Lifetime> \"+\")", "(<Lifetime> \"+\")*", "(<Lifetime> \"+\")+", "(<MatchItem> \",\")", "(<MatchItem> \",\")*", "(<MatchItem> \",\")+", "(<NotMacroId> \",\")", "(<NotMacroId> \",\")*", "(<NotMacroId> \",\")+", "(<Pattern> \",\")", "(<Pattern> \",\")*", "(<Pattern> \",\")+", "(<Symbol> \",\")", "(<Symbol> \",\")*", "(<Symbol> \",\")+", "(<TypeBound> \"+\")", "(<TypeBound> \"+\")*", "(<TypeBound> \"+\")+", "(<TypeBoundParameter> \",\")", "(<TypeBoundParameter> \",\")*", "(<TypeBoundParameter> \",\")+", "(<TypeParameter> \",\")", "(<TypeParameter> \",\")*", "(<TypeParameter> \",\")+", "(<TypeRef> \",\")", "(<TypeRef> \",\")*", "(<TypeRef> \",\")+", "(<TypeRefOrLifetime> \",\")", "(<TypeRefOrLifetime> \",\")*", "(<TypeRefOrLifetime> \",\")+", "@L", "@R", "Action", "Action?", "Alternative", "Alternative?", "Alternatives", "Annotation", "Annotation*", "Annotation+", "AnnotationArg", "AnnotationArg?", "AssociatedType", "AssociatedType*", "AssociatedType+", "Comma<Alternative>", "Comma<Conversion>", "Comma<GrammarParameter>", "Comma<GrammarWhereClause>", "Comma<MatchItem>", "Comma<NotMacroId>", "Comma<Pattern>", "Comma<Symbol>", "Comma<TypeBoundParameter>", "Comma<TypeParameter>", "Comma<TypeRef>", "Comma<TypeRefOrLifetime>", "Cond", "CondOp", "Conversion", "Conversion?", "EnumToken", "Escape", "ExprSymbol", "ExternToken", "FieldPattern", "FieldPattern?", "ForAll", "Grammar", "GrammarItem", "GrammarItem*", "GrammarItem+", "GrammarParameter", "GrammarParameter?", "GrammarParameters", "GrammarParameters?", "GrammarTypeParameters", "GrammarTypeParameters?", "GrammarWhereClause", "GrammarWhereClause?", "GrammarWhereClauses", "GrammarWhereClauses?", "Id", "Lifetime", "Lifetime?", "MacroId", "MatchContents", "MatchItem", "MatchItem?", "MatchMapping", "MatchSymbol", "MatchToken", "MatchTokenInt", "Nonterminal", "NonterminalName", "NotMacroId", "NotMacroId?", "Path", "Pattern", "Pattern?", "PatternKind", "Plus<Lifetime>", "Plus<TypeBound>", "QuotedLiteral", "QuotedTerminal", "RegexLiteral", "RepeatOp", "ShebangAttribute", "ShebangAttribute*", "ShebangAttribute+", "StringConstant", "StringLiteral", "Symbol", "Symbol*", "Symbol+", "Symbol0", "Symbol1", "Symbol?", "SymbolKind1", "Terminal", "Top", "TypeBound", "TypeBound?", "TypeBoundParameter", "TypeBoundParameter?", "TypeBounds", "TypeParameter", "TypeParameter?", "TypeRef", "TypeRef?", "TypeRefOrLifetime", "TypeRefOrLifetime?", "Use", "Use*", "Use+", "Visibility", "___Top"];
+#[allow(dead_code)]
+pub(crate) const START_STATE: usize = 0;
+}
 fn ___accepts<
     'input,
 >(
@@ -2,11 +2,14 @@
 //! to `configuration::Configuration`, but it is not exported outside the
 //! crate. Note that all fields are public and so forth for convenience.
 
+use crate::api::automaton;
+use crate::api::ConflictResolver;
 use crate::log::{Level, Log};
 use crate::style::{self, Style};
 use std::collections::BTreeSet;
 use std::default::Default;
 use std::path;
+use std::sync::Arc;
 
 // These two, ubiquitous types are defined here so that their fields can be private
 // across crate, but visible within the crate:
@@ -24,6 +27,9 @@ pub enum ColorConfig {
     IfTty,
 }
 
+/// Set with `Configuration::set_state_observer`.
+pub type StateObserver = dyn Fn(&automaton::State) + Send + Sync;
+
 /// Various options to control debug output. Although this struct is
 /// technically part of LALRPOP's exported interface, it is not
 /// considered part of the semver guarantees as end-users are not
@@ -51,6 +57,12 @@ pub struct Session {
     /// Emit report file about generated code
     pub emit_report: bool,
 
+    /// Emit the states and conflicts of the generated parser as JSON
+    pub emit_json_report: bool,
+
+    /// Emit the states of the generated parser as a text snapshot
+    pub emit_snapshot: bool,
+
     pub color_config: ColorConfig,
 
     /// Stop after you find `max_errors` errors. If this value is 0,
@@ -89,6 +101,19 @@ pub struct Session {
 
     /// Features used for conditional compilation
     pub features: Option<BTreeSet<String>>,
+
+    /// Resolves the conflicts of the grammars, if set; otherwise they
+    /// are reported
+    pub conflict_resolver: Option<Arc<dyn ConflictResolver + Send + Sync>>,
+
+    /// Called with the index of each state that `build_automaton` and
+    /// `check_lr1` construct and the number of conflicts found so far;
+    /// returning `false` cancels construction
+    pub progress_callback: Option<Arc<dyn Fn(usize, usize) -> bool + Send + Sync>>,
+
+    /// Shown each state that `build_automaton` and `check_lr1`
+    /// construct, as soon as it is complete
+    pub state_observer: Option<Arc<StateObserver>>,
 }
 
 impl Session {
@@ -102,6 +127,8 @@ impl Session {
             emit_comments: false,
             emit_whitespace: true,
             emit_report: false,
+            emit_json_report: false,
+            emit_snapshot: false,
             color_config: ColorConfig::default(),
             max_errors: 1,
             heading: style::FG_WHITE.with(style::BOLD),
@@ -114,6 +141,9 @@ impl Session {
             hint_text: style::FG_BRIGHT_MAGENTA.with(style::BOLD),
             unit_test: false,
             features: Default::default(),
+            conflict_resolver: None,
+            progress_callback: None,
+            state_observer: None,
         }
     }
 
@@ -129,6 +159,8 @@ impl Session {
             emit_comments: false,
             emit_whitespace: true,
             emit_report: false,
+            emit_json_report: false,
+            emit_snapshot: false,
             color_config: ColorConfig::IfTty,
             max_errors: 1,
             heading: Style::new(),
@@ -141,6 +173,9 @@ impl Session {
             hint_text: Style::new(),
             unit_test: true,
             features: Default::default(),
+            conflict_resolver: None,
+            progress_callback: None,
+            state_observer: None,
         }
     }
 